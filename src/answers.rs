@@ -0,0 +1,167 @@
+//! Answers to the wizard's questions, so the same project can be scaffolded again
+//!
+//! `--record answers.toml` saves the answers of a run, and `--answers answers.toml` replays them.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{GetflakeError, Result},
+    scaffold::{Ci, GitHubRepo, NewOrInit, Readme},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Answers {
+    pub template: Option<String>,
+
+    /// Whether to create a `new` project or `init` one in the current folder
+    pub mode: Option<NewOrInit>,
+
+    /// Name of the project
+    pub name: Option<String>,
+
+    pub author: Option<String>,
+
+    pub email: Option<String>,
+
+    pub description: Option<String>,
+
+    pub license: Option<String>,
+
+    /// Whether to initialize a Git repository
+    pub git: Option<bool>,
+
+    /// Whether to commit the generated files
+    pub commit: Option<bool>,
+
+    pub commit_message: Option<String>,
+
+    /// Whether to create a `private` or `public` GitHub repository with gh, or `skip` it
+    pub github_repo: Option<GitHubRepo>,
+
+    /// URL of the remote repository, empty for none
+    pub remote: Option<String>,
+
+    /// Whether to push the initial commit to the remote
+    pub push: Option<bool>,
+
+    /// Whether to install a pre-commit hook checking the flake
+    pub pre_commit: Option<bool>,
+
+    /// Whether to `keep`, `retitle` or `replace` the README.md file
+    #[serde(alias = "clear_readme")]
+    pub readme: Option<Readme>,
+
+    /// Whether to write a `.envrc` and allow it
+    pub direnv: Option<bool>,
+
+    /// Which forge to write a CI workflow for: `github`, `gitlab`, or `skip`
+    pub ci: Option<Ci>,
+
+    /// Whether to write a `.devcontainer/devcontainer.json`
+    pub devcontainer: Option<bool>,
+
+    /// Extra placeholders to replace, mapped to their values
+    pub variables: BTreeMap<String, String>,
+}
+
+/// The answers that can be changed once they're all given, before the project is scaffolded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Question {
+    Template,
+    Name,
+    Author,
+    Email,
+    Description,
+    License,
+    /// Whether to initialize a Git repository, and everything about it
+    Git,
+    Readme,
+    Direnv,
+    Ci,
+    Devcontainer,
+}
+
+impl Question {
+    pub const ALL: &'static [Question] = &[
+        Question::Template,
+        Question::Name,
+        Question::Author,
+        Question::Email,
+        Question::Description,
+        Question::License,
+        Question::Git,
+        Question::Readme,
+        Question::Direnv,
+        Question::Ci,
+        Question::Devcontainer,
+    ];
+
+    /// What the question is about, e.g. in "Change the template"
+    pub fn subject(self) -> &'static str {
+        match self {
+            Question::Template => "the template",
+            Question::Name => "the project name",
+            Question::Author => "the author",
+            Question::Email => "the email address",
+            Question::Description => "the description",
+            Question::License => "the license",
+            Question::Git => "the Git settings",
+            Question::Readme => "what to do with README.md",
+            Question::Direnv => "whether to set up direnv",
+            Question::Ci => "the CI workflow",
+            Question::Devcontainer => "whether to add a dev container",
+        }
+    }
+}
+
+impl Answers {
+    /// Forgets the answer to `question`, and the answers that depend on it, so they're asked for
+    /// again
+    pub fn forget(&mut self, question: Question) {
+        match question {
+            // Another template has other variables
+            Question::Template => {
+                self.template = None;
+                self.variables.clear();
+            }
+            Question::Name => self.name = None,
+            Question::Author => self.author = None,
+            Question::Email => self.email = None,
+            Question::Description => self.description = None,
+            Question::License => self.license = None,
+            Question::Git => {
+                self.git = None;
+                self.commit = None;
+                self.commit_message = None;
+                self.github_repo = None;
+                self.remote = None;
+                self.push = None;
+                self.pre_commit = None;
+            }
+            Question::Readme => self.readme = None,
+            Question::Direnv => self.direnv = None,
+            Question::Ci => self.ci = None,
+            Question::Devcontainer => self.devcontainer = None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| GetflakeError::InvalidAnswers {
+            path: path.to_string_lossy().into_owned(),
+            message: e.to_string(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).map_err(|e| GetflakeError::InvalidAnswers {
+            path: path.to_string_lossy().into_owned(),
+            message: e.to_string(),
+        })?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,592 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use getflake::{answers::Question, workspace::Member, NewOrInit, Readme, Result};
+
+/// Easily fetch Nix Flake Templates
+///
+/// Running without a subcommand starts the interactive wizard. Any value not
+/// supplied on the command line is asked for interactively.
+#[derive(Parser, Debug, Default)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Flake reference of the template repository [default: github:nulladmin1/nix-flake-templates]
+    #[arg(long, global = true)]
+    pub source: Option<String>,
+
+    /// Revision or tag of the template repository to use (e.g. `v1.2.0`)
+    #[arg(long, global = true, conflicts_with = "source_branch")]
+    pub source_rev: Option<String>,
+
+    /// Branch of the template repository to use (e.g. `dev`)
+    #[arg(long, global = true)]
+    pub source_branch: Option<String>,
+
+    /// Use the settings of this profile from the config file, on top of the others
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Download templates from GitHub instead of fetching them with nix
+    #[arg(long, global = true)]
+    pub no_nix: bool,
+
+    /// Stop nix, git and the other commands getflake runs when they take longer than this, in
+    /// seconds
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// Don't use the network: use the cached template list, and the templates nix already
+    /// downloaded
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    pub offline: bool,
+
+    /// Fetch the template list again instead of using the cached one
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Format of the output; with `json`, messages are printed to stderr instead
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub output: OutputFormat,
+
+    /// Print every command run; twice to also print their output
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print errors (and questions)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Start messages with ASCII tags like `[git]` instead of emoji
+    #[arg(long, global = true)]
+    pub no_emoji: bool,
+
+    /// When to color the output; `auto` colors it in terminals, unless NO_COLOR is set
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub color: ColorChoice,
+
+    /// Create a new project in a new directory
+    #[arg(long, conflicts_with = "init")]
+    pub new: bool,
+
+    /// Initialize the project in the current directory
+    #[arg(long)]
+    pub init: bool,
+
+    /// Name of the project
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    #[command(flatten)]
+    pub scaffold: ScaffoldArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Create a new project in a new directory
+    New {
+        /// Name of the project (and of the directory it is created in)
+        name: String,
+
+        #[command(flatten)]
+        scaffold: ScaffoldArgs,
+    },
+    /// Initialize a project in the current directory
+    Init {
+        /// Name of the project
+        #[arg(short, long)]
+        name: Option<String>,
+
+        #[command(flatten)]
+        scaffold: ScaffoldArgs,
+    },
+    /// Create a workspace: several projects in folders of their own, with a flake at its root
+    /// that wires them together
+    ///
+    /// e.g. `getflake workspace my-app --add api:rust --add web:typescript`
+    Workspace {
+        /// Name of the workspace (and of the directory it is created in)
+        name: String,
+
+        /// A project of the workspace, as name:template; it's created in the name folder
+        #[arg(long = "add", value_name = "NAME:TEMPLATE", required = true)]
+        members: Vec<Member>,
+
+        #[command(flatten)]
+        scaffold: ScaffoldArgs,
+    },
+    /// Add a file or folder of a template to the project in the current folder, like its
+    /// justfile, GitHub Actions workflows or devcontainer
+    ///
+    /// It's taken from the template the project was generated from, unless --template picks
+    /// another.
+    Add {
+        /// File or folder of the template to add (e.g. `justfile` or `.github/workflows`)
+        component: String,
+
+        /// Template to take it from
+        #[arg(short, long)]
+        template: Option<String>,
+
+        /// What to do with the files the project already has: keep, replace, or both (save the
+        /// template's next to it); asked when not given
+        #[arg(long, value_name = "ACTION")]
+        on_conflict: Option<String>,
+
+        /// Print what would be added without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the available templates
+    List,
+    /// Show details about a template
+    Info {
+        /// Name of the template
+        template: String,
+    },
+    /// Merge the changes made to the template since the project was generated
+    ///
+    /// Run it from the project's folder, which must have a .getflake.toml file.
+    Upgrade {
+        /// Print what would be changed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show how the project differs from the template it was generated from
+    ///
+    /// Run it from the project's folder, which must have a .getflake.toml file.
+    Diff,
+    /// Check that everything getflake relies on is in place
+    Doctor,
+    /// Ask for the author, license, Git and template settings again, and save them to the config
+    /// file
+    Setup,
+    /// List the projects scaffolded so far, or scaffold one of them again
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+    /// Take back the last scaffold: remove the project it created, or the files it added to an
+    /// existing folder, restoring the ones it changed
+    Undo {
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Manage the aliases that stand for flake references wherever a source is expected
+    Source {
+        #[command(subcommand)]
+        action: SourceAction,
+    },
+    /// Write templates of your own
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+    /// Manage the templates pinned at the top of the picker
+    Favorite {
+        #[command(subcommand)]
+        action: FavoriteAction,
+    },
+    /// Print a completion script for a shell
+    ///
+    /// Template names are completed from the cached template list, so run
+    /// `getflake list` first.
+    Completions {
+        /// Shell to generate the script for
+        shell: Shell,
+    },
+
+    /// Any other command runs the `getflake-<name>` plugin on the PATH, with the rest of the
+    /// arguments, and getflake's context as JSON on stdin
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryAction {
+    /// Scaffold a project from the history again, with the same answers but a new name
+    Rerun {
+        /// Number of the project, as listed by `getflake history`
+        id: u32,
+
+        /// Name of the new project
+        name: String,
+
+        #[command(flatten)]
+        scaffold: ScaffoldArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SourceAction {
+    /// Add an alias, e.g. `getflake source add work github:my-company/nix-templates`
+    Add {
+        /// Name to use instead of the flake reference, e.g. with --source
+        alias: String,
+
+        /// Flake reference the alias stands for
+        url: String,
+    },
+    /// Remove an alias
+    Remove {
+        /// Name of the alias
+        alias: String,
+    },
+    /// List the aliases
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateAction {
+    /// Create a template in a templates repository, with a flake, a README and a getflake.toml
+    /// using getflake's placeholders, and add it to the repository's flake
+    New {
+        /// Name of the template
+        name: String,
+
+        /// Folder of the templates repository
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+
+        /// Description of the template, as the templates output lists it
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Check a template: that the flake evaluates and describes it, that its getflake.toml is
+    /// well-formed and its placeholders add up, and that a project generated from it passes
+    /// `nix flake check`
+    Validate {
+        /// Name of the template in the source, or `<flake-ref>#<template>`
+        template: String,
+
+        /// Don't run `nix flake check` on the generated project, which may build it
+        #[arg(long)]
+        no_check: bool,
+
+        /// Fail on warnings too, like a variable that's declared but never used
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FavoriteAction {
+    /// Pin a template at the top of the picker
+    Add {
+        /// Name of the template
+        template: String,
+    },
+    /// Unpin a template
+    Remove {
+        /// Name of the template
+        template: String,
+    },
+    /// List the pinned templates
+    List,
+}
+
+/// Options shared by everything that scaffolds a project
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ScaffoldArgs {
+    /// Template to use (e.g. `rust`)
+    #[arg(short, long)]
+    pub template: Option<String>,
+
+    /// Also add the files of another template to the project (e.g. `--with docs-mdbook`)
+    #[arg(long, value_name = "TEMPLATE")]
+    pub with: Vec<String>,
+
+    /// What to do with the files of a `--with` template the project already has: keep,
+    /// replace, or both (save the template's next to it); asked when not given
+    #[arg(long, value_name = "ACTION", requires = "with")]
+    pub on_conflict: Option<String>,
+
+    /// Author of the project [default: user.name from the Git config]
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Email address of the author [default: user.email from the Git config]
+    #[arg(long)]
+    pub email: Option<String>,
+
+    /// Short description of the project
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// SPDX identifier of the project's license (e.g. `MIT`, `Apache-2.0`, `GPL-3.0-only`)
+    #[arg(long)]
+    pub license: Option<String>,
+
+    /// Don't write a LICENSE file or set the license in Cargo.toml, pyproject.toml and flake.nix
+    #[arg(long)]
+    pub no_license_file: bool,
+
+    /// Initialize a Git repository
+    #[arg(long, conflicts_with = "no_git")]
+    pub git: bool,
+
+    /// Don't initialize a Git repository
+    #[arg(long)]
+    pub no_git: bool,
+
+    /// Commit the generated files to the new Git repository
+    #[arg(long, conflicts_with = "no_commit")]
+    pub commit: bool,
+
+    /// Don't commit the generated files
+    #[arg(long)]
+    pub no_commit: bool,
+
+    /// Message of the initial commit
+    #[arg(long)]
+    pub commit_message: Option<String>,
+
+    /// Create a GitHub repository for the project with the gh CLI, as its `origin` remote
+    #[arg(long, value_name = "VISIBILITY", value_parser = ["private", "public", "skip"])]
+    pub github_repo: Option<String>,
+
+    /// URL of the remote repository to add as `origin`
+    #[arg(long, value_name = "URL")]
+    pub remote: Option<String>,
+
+    /// Push the initial commit to the remote repository
+    #[arg(long, conflicts_with = "no_push")]
+    pub push: bool,
+
+    /// Don't push the initial commit
+    #[arg(long)]
+    pub no_push: bool,
+
+    /// What to do with the template's README.md: keep it, retitle it (keep its content, with the
+    /// project's name and description), or replace it with one rendered from the README template
+    #[arg(long, value_name = "ACTION", conflicts_with_all = ["clear_readme", "no_clear_readme"])]
+    pub readme: Option<String>,
+
+    /// Replace the README.md file, like --readme replace
+    #[arg(long, conflicts_with = "no_clear_readme")]
+    pub clear_readme: bool,
+
+    /// Keep the README.md file from the template, like --readme keep
+    #[arg(long)]
+    pub no_clear_readme: bool,
+
+    /// Don't run `nix fmt` once the placeholders are replaced
+    #[arg(long)]
+    pub no_format: bool,
+
+    /// Don't run the hooks of the config file or the template
+    #[arg(long)]
+    pub no_hooks: bool,
+
+    /// Write a .envrc with `use flake`, and run `direnv allow` if direnv is installed
+    #[arg(long, conflicts_with = "no_direnv")]
+    pub direnv: bool,
+
+    /// Don't set up direnv
+    #[arg(long)]
+    pub no_direnv: bool,
+
+    /// Merge the template's inputs and outputs into the flake.nix the folder already has
+    #[arg(long, conflicts_with = "no_merge_flake")]
+    pub merge_flake: bool,
+
+    /// Leave the folder's flake.nix alone, even though nix refuses to overwrite it
+    #[arg(long)]
+    pub no_merge_flake: bool,
+
+    /// Write a CI workflow running nix flake check and nix build: github, gitlab, or skip
+    #[arg(long, value_name = "FORGE")]
+    pub ci: Option<String>,
+
+    /// Write a .devcontainer/devcontainer.json with Nix and `nix develop` as the shell, for
+    /// Codespaces and Dev Containers
+    #[arg(long, conflicts_with = "no_devcontainer")]
+    pub devcontainer: bool,
+
+    /// Don't write a devcontainer.json
+    #[arg(long)]
+    pub no_devcontainer: bool,
+
+    /// Install a pre-commit hook checking the flake: the template's, with pre-commit, or else
+    /// one running `nix flake check`
+    #[arg(long, conflicts_with = "no_pre_commit")]
+    pub pre_commit: bool,
+
+    /// Don't install a pre-commit hook
+    #[arg(long)]
+    pub no_pre_commit: bool,
+
+    /// Overwrite the project's folder if it already exists and isn't empty
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Show the files of the picked template, and confirm it before going on
+    #[arg(long)]
+    pub preview: bool,
+
+    /// Pick the template and fill in the project's options on one screen, with a preview of the
+    /// template's files
+    #[arg(long, conflicts_with = "yes")]
+    pub tui: bool,
+
+    /// Answer every question that has a default (from the config file, or getflake's own) with
+    /// it, and don't ask for confirmation; only the ones without one are asked
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Folder to create the project in, instead of one named after it (or the current one, for
+    /// `init`); the folders leading to it are created as needed
+    #[arg(long, value_name = "PATH")]
+    pub dir: Option<String>,
+
+    /// Print what would be done without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// With --dry-run, also print the files the project would get, with the placeholders replaced
+    #[arg(long, requires = "dry_run")]
+    pub show_diff: bool,
+
+    /// Open the project once it's created, in EDITOR if given, or else the configured editor,
+    /// $VISUAL, $EDITOR or VS Code
+    #[arg(long, value_name = "EDITOR", num_args = 0..=1, default_missing_value = "")]
+    pub open: Option<String>,
+
+    /// Enter the project's dev shell with `nix develop` once it's created
+    #[arg(long, conflicts_with = "no_develop")]
+    pub develop: bool,
+
+    /// Don't offer to enter the dev shell
+    #[arg(long)]
+    pub no_develop: bool,
+
+    /// Don't replace placeholders in the files and folders matching a glob (e.g. `vendor/**`)
+    #[arg(long, value_name = "GLOB")]
+    pub ignore: Vec<String>,
+
+    /// Take the answers to the questions from a file saved with `--record`
+    #[arg(long, value_name = "FILE")]
+    pub answers: Option<PathBuf>,
+
+    /// Save the answers to the questions to a file, to replay them with `--answers`
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn new_or_init(&self) -> Option<NewOrInit> {
+        if self.new {
+            Some(NewOrInit::New)
+        } else if self.init {
+            Some(NewOrInit::Init)
+        } else {
+            None
+        }
+    }
+}
+
+impl ScaffoldArgs {
+    /// Forgets the options that answer `question`, like [`Answers::forget`](getflake::answers::Answers::forget)
+    pub fn forget(&mut self, question: Question) {
+        match question {
+            Question::Template => self.template = None,
+            // The name isn't one of these options
+            Question::Name => {}
+            Question::Author => self.author = None,
+            Question::Email => self.email = None,
+            Question::Description => self.description = None,
+            Question::License => self.license = None,
+            Question::Git => {
+                (self.git, self.no_git) = (false, false);
+                (self.commit, self.no_commit) = (false, false);
+                self.commit_message = None;
+                self.github_repo = None;
+                self.remote = None;
+                (self.push, self.no_push) = (false, false);
+                (self.pre_commit, self.no_pre_commit) = (false, false);
+            }
+            Question::Readme => {
+                self.readme = None;
+                (self.clear_readme, self.no_clear_readme) = (false, false);
+            }
+            Question::Direnv => (self.direnv, self.no_direnv) = (false, false),
+            Question::Ci => self.ci = None,
+            Question::Devcontainer => (self.devcontainer, self.no_devcontainer) = (false, false),
+        }
+    }
+
+    pub fn init_git(&self) -> Option<bool> {
+        Self::flag(self.git, self.no_git)
+    }
+
+    pub fn commit(&self) -> Option<bool> {
+        Self::flag(self.commit, self.no_commit)
+    }
+
+    pub fn push(&self) -> Option<bool> {
+        Self::flag(self.push, self.no_push)
+    }
+
+    pub fn readme(&self) -> Result<Option<Readme>> {
+        match &self.readme {
+            Some(readme) => readme.parse().map(Some),
+            None => Ok(
+                Self::flag(self.clear_readme, self.no_clear_readme).map(|clear| {
+                    if clear {
+                        Readme::Replace
+                    } else {
+                        Readme::Keep
+                    }
+                }),
+            ),
+        }
+    }
+
+    pub fn direnv(&self) -> Option<bool> {
+        Self::flag(self.direnv, self.no_direnv)
+    }
+
+    pub fn pre_commit(&self) -> Option<bool> {
+        Self::flag(self.pre_commit, self.no_pre_commit)
+    }
+
+    pub fn devcontainer(&self) -> Option<bool> {
+        Self::flag(self.devcontainer, self.no_devcontainer)
+    }
+
+    pub fn merge_flake(&self) -> Option<bool> {
+        Self::flag(self.merge_flake, self.no_merge_flake)
+    }
+
+    pub fn develop(&self) -> Option<bool> {
+        Self::flag(self.develop, self.no_develop)
+    }
+
+    fn flag(yes: bool, no: bool) -> Option<bool> {
+        match (yes, no) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Writing templates: `getflake template new` scaffolds one into a templates repository, ready to
+//! be filled in, with getflake's placeholders and `getflake.toml` already in place, and
+//! `getflake template validate` checks one before it's published
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use regex::Regex;
+
+use crate::{
+    colors::{GREEN, RESET},
+    error::{GetflakeError, Result},
+    icons::{DONE, ERROR, HINT, OK, TEMPLATE, WARNING},
+    manifest::{self, Manifest, VariableKind},
+    output::RunLogged,
+    postprocess::current_year,
+    progress,
+    render::{self, render},
+    say,
+    templates::{Source, TemplateDetails},
+};
+
+/// The placeholders getflake always replaces, in `snake_case`
+const PLACEHOLDERS: &[&str] = &[
+    "project_name",
+    "project_author",
+    "project_email",
+    "project_description",
+    "project_license",
+    "project_year",
+];
+
+/// Name of the project the template is generated as to check it
+const SAMPLE_NAME: &str = "sample-project";
+
+/// The files of a new template, relative to its folder
+///
+/// They use the placeholders getflake replaces, so a project generated from the template gets its
+/// own name and description right away.
+const SKELETON: &[(&str, &str)] = &[
+    (
+        "flake.nix",
+        r#"{
+  description = "project_description";
+
+  inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+
+  outputs =
+    { nixpkgs, ... }:
+    let
+      forAllSystems = nixpkgs.lib.genAttrs nixpkgs.lib.systems.flakeExposed;
+    in
+    {
+      devShells = forAllSystems (system: {
+        default = nixpkgs.legacyPackages.${system}.mkShell {
+          packages = [ ];
+        };
+      });
+
+      formatter = forAllSystems (system: nixpkgs.legacyPackages.${system}.nixfmt-rfc-style);
+    };
+}
+"#,
+    ),
+    (
+        "README.md",
+        "# project_name
+
+project_description
+
+## Development
+
+```shell
+nix develop
+```
+",
+    ),
+    (".gitignore", "result\nresult-*\n.direnv/\n"),
+    (
+        manifest::FILE_NAME,
+        r#"# Read by getflake, and left out of the projects generated from this template
+
+# Copied as they are, without replacing placeholders
+ignore = []
+
+# Asked for after the built-in questions, and replaced like project_name
+# [[variables]]
+# name = "project_port"
+# type = "integer"
+# default = 8080
+# prompt = "What port does the server listen on?"
+
+# Run in the project's folder once it's scaffolded
+# [hooks]
+# post = []
+"#,
+    ),
+];
+
+/// Scaffolds the `name` template in the templates repository at `repository`, and adds it to the
+/// `templates` output of the repository's flake (writing one if there's none)
+///
+/// The template goes under `templates/` if the repository has that folder, and at its root
+/// otherwise.
+pub fn new_template(repository: &Path, name: &str, description: &str) -> Result<()> {
+    let is_name = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !is_name {
+        return Err(GetflakeError::InvalidInput(format!(
+            "{name} can only have letters, digits, - and _"
+        )));
+    }
+
+    let relative = if repository.join("templates").is_dir() {
+        Path::new("templates").join(name)
+    } else {
+        PathBuf::from(name)
+    };
+    let directory = repository.join(&relative);
+    if directory.exists() {
+        return Err(GetflakeError::InvalidInput(format!(
+            "{0} already exists",
+            directory.display()
+        )));
+    }
+
+    say!("{TEMPLATE} Creating the {GREEN}{name}{RESET} template...");
+    fs::create_dir_all(&directory)?;
+    for (file, content) in SKELETON {
+        fs::write(directory.join(file), content)?;
+        say!("- {OK} Wrote {0}", relative.join(file).display());
+    }
+
+    let flake_path = repository.join("flake.nix");
+    let entry = Entry {
+        name,
+        path: &relative,
+        description,
+    };
+    match fs::read_to_string(&flake_path) {
+        Ok(flake) => match add_entry(&flake, &entry) {
+            Some(flake) => {
+                fs::write(&flake_path, flake)?;
+                say!("- {OK} Added it to the templates of flake.nix");
+            }
+            None => {
+                eprintln!("{WARNING} Unable to find the templates of flake.nix, add it yourself:");
+                eprintln!("\n{0}", entry.binding("  "));
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            fs::write(&flake_path, root_flake(&entry))?;
+            say!("- {OK} Wrote flake.nix, with the template as its only one");
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    say!("{DONE} Created the {GREEN}{name}{RESET} template");
+    say!(
+        "{HINT} Try it with {GREEN}getflake new my-project --source {0} --template {name}{RESET}",
+        source_argument(repository)
+    );
+    Ok(())
+}
+
+/// Checks the `template` of `source`: that the flake evaluates and describes it, that its
+/// `getflake.toml` is well-formed and its placeholders add up, and that a project generated from
+/// it passes `nix flake check` (unless `flake_check` is false)
+///
+/// Prints how each check went, and returns whether they all passed. Warnings, like a variable
+/// that's declared but never used, only fail the template when `strict` is set.
+pub fn validate(source: &Source, template: &str, flake_check: bool, strict: bool) -> bool {
+    say!(
+        "\n{TEMPLATE} Validating the {GREEN}{template}{RESET} template of {0}...",
+        source.url
+    );
+
+    let Some(details) = check_evaluates(source, template) else {
+        say!("\n{WARNING} The template can't be checked any further");
+        return false;
+    };
+    let mut passed = check_description(&details);
+    let manifest = match source.template_manifest(template) {
+        Ok(manifest) => {
+            say!("- {OK} {0} is well-formed", manifest::FILE_NAME);
+            manifest
+        }
+        Err(e) => {
+            eprintln!("- {ERROR}{e}");
+            say!("\n{WARNING} The template can't be checked any further");
+            return false;
+        }
+    };
+    passed &= check_placeholders(&details.path, &manifest, strict);
+    passed &= check_generated(source, template, &manifest, flake_check);
+
+    if passed {
+        say!("\n{DONE} The {GREEN}{template}{RESET} template is ready");
+    } else {
+        say!("\n{WARNING} Some checks failed, see above");
+    }
+    passed
+}
+
+fn check_evaluates(source: &Source, template: &str) -> Option<TemplateDetails> {
+    match progress::spin("Evaluating the flake", || source.template_details(template)) {
+        Ok(details) => {
+            say!("- {OK} The flake evaluates, and has the {template} template");
+            Some(details)
+        }
+        Err(e) => {
+            eprintln!("- {ERROR}The flake doesn't evaluate, or has no {template} template\n{e}");
+            None
+        }
+    }
+}
+
+fn check_description(details: &TemplateDetails) -> bool {
+    if details.description.trim().is_empty() {
+        eprintln!("- {ERROR}The template has no description, which the template list shows");
+        return false;
+    }
+    say!(
+        "- {OK} The template is described as \"{0}\"",
+        details.description
+    );
+    true
+}
+
+/// Checks that the files the manifest lists exist, that nothing looks like a misspelled
+/// placeholder, and that its variables are used, which only fails the template when `strict` is set
+fn check_placeholders(directory: &Path, manifest: &Manifest, strict: bool) -> bool {
+    let mut passed = true;
+    let mut warned = false;
+    let files = render::files(directory);
+
+    for file in &manifest.files {
+        if !directory.join(&file.path).exists() {
+            eprintln!(
+                "- {ERROR}{0} lists {1}, which the template doesn't have",
+                manifest::FILE_NAME,
+                file.path.display()
+            );
+            passed = false;
+        }
+    }
+
+    // Everything placeholders are replaced in: contents, file names and conditions
+    let mut text: Vec<String> = files
+        .iter()
+        .filter(|file| *file != Path::new(manifest::FILE_NAME))
+        .flat_map(|file| {
+            let content = fs::read_to_string(directory.join(file)).unwrap_or_default();
+            [file.to_string_lossy().into_owned(), content]
+        })
+        .collect();
+    text.extend(manifest.files.iter().map(|file| file.when.clone()));
+
+    let declared: BTreeSet<&str> = manifest
+        .variables
+        .iter()
+        .map(|variable| variable.name.as_str())
+        .collect();
+    for variable in &declared {
+        if !text.iter().any(|text| text.contains(variable)) {
+            eprintln!("- {WARNING} The {variable} variable is declared, but never used");
+            warned = true;
+        }
+    }
+
+    let known = |name: &str| PLACEHOLDERS.contains(&name) || declared.contains(name);
+    let looks_like_placeholder = Regex::new(r"\bproject_[a-z0-9_]+\b").expect("valid regex");
+    let unknown: BTreeSet<&str> = text
+        .iter()
+        .flat_map(|text| looks_like_placeholder.find_iter(text))
+        .map(|found| found.as_str())
+        .filter(|name| !known(name))
+        .collect();
+    for name in unknown {
+        eprintln!(
+            "- {ERROR}{name} looks like a placeholder, but isn't declared in {0}",
+            manifest::FILE_NAME
+        );
+        passed = false;
+    }
+
+    if !text
+        .iter()
+        .any(|text| PLACEHOLDERS.iter().any(|name| text.contains(name)))
+    {
+        eprintln!(
+            "- {WARNING} The template uses no placeholder, so every project gets the same names"
+        );
+        warned = true;
+    }
+    if passed && !warned {
+        say!("- {OK} The placeholders add up");
+    }
+    passed && !(strict && warned)
+}
+
+/// Generates the template as a sample project, and runs `nix flake check` on it
+fn check_generated(
+    source: &Source,
+    template: &str,
+    manifest: &Manifest,
+    flake_check: bool,
+) -> bool {
+    let mut variables: BTreeMap<String, String> = manifest
+        .variables
+        .iter()
+        .map(|variable| {
+            let value = variable
+                .default_value()
+                .unwrap_or_else(|| match variable.kind {
+                    VariableKind::String => "sample".to_owned(),
+                    VariableKind::Bool => "true".to_owned(),
+                    VariableKind::Integer => "1".to_owned(),
+                });
+            (variable.name.clone(), value)
+        })
+        .collect();
+    variables.extend([
+        ("project_name".to_owned(), SAMPLE_NAME.to_owned()),
+        ("project_author".to_owned(), "Jane Doe".to_owned()),
+        ("project_email".to_owned(), "jane@example.com".to_owned()),
+        (
+            "project_description".to_owned(),
+            "A project generated to validate the template".to_owned(),
+        ),
+        ("project_license".to_owned(), "MIT".to_owned()),
+        ("project_year".to_owned(), current_year().to_string()),
+    ]);
+
+    let rendered = match render(source, template, &variables) {
+        Ok(rendered) => {
+            say!("- {OK} The template generates {SAMPLE_NAME}");
+            rendered
+        }
+        Err(e) => {
+            eprintln!("- {ERROR}Generating {SAMPLE_NAME} from the template failed\n{e}");
+            return false;
+        }
+    };
+    if !flake_check {
+        return true;
+    }
+
+    let output = progress::spin("Running nix flake check", || {
+        Command::new("nix")
+            .args([
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "flake",
+                "check",
+                "path:.",
+            ])
+            .current_dir(rendered.path())
+            .run_logged()
+    });
+    match output {
+        Ok(output) if output.status.success() => {
+            say!("- {OK} {SAMPLE_NAME} passes nix flake check");
+            true
+        }
+        Ok(output) => {
+            eprintln!(
+                "- {ERROR}{SAMPLE_NAME} fails nix flake check\n{0}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            false
+        }
+        Err(e) => {
+            eprintln!("- {ERROR}Unable to run nix flake check\n{e}");
+            false
+        }
+    }
+}
+
+/// A template, as the `templates` output of a flake lists it
+struct Entry<'a> {
+    name: &'a str,
+    /// Relative to the flake
+    path: &'a Path,
+    description: &'a str,
+}
+
+impl Entry<'_> {
+    /// The `name = { ... };` binding, each line starting with `indent`
+    fn binding(&self, indent: &str) -> String {
+        let description = self.description.replace('\\', "\\\\").replace('"', "\\\"");
+        format!(
+            "{indent}{0} = {{\n{indent}  path = ./{1};\n{indent}  description = \"{description}\";\n{indent}}};\n",
+            self.name,
+            self.path.display()
+        )
+    }
+}
+
+/// Returns `flake` with `entry` added to its `templates`, or `None` if they can't be found
+///
+/// They're found as a `templates = { ... }` set, or as `templates.<name> = ...` bindings, which
+/// covers how templates repositories write them.
+fn add_entry(flake: &str, entry: &Entry) -> Option<String> {
+    let set = Regex::new(r"(?m)^([ \t]*)templates\s*=\s*(rec\s*)?\{[ \t]*\n").expect("valid regex");
+    if let Some(captures) = set.captures(flake) {
+        let whole = captures.get(0)?;
+        let indent = format!("{0}  ", &captures[1]);
+        let mut flake = flake.to_owned();
+        flake.insert_str(whole.end(), &entry.binding(&indent));
+        return Some(flake);
+    }
+
+    let bindings = Regex::new(r"(?m)^([ \t]*)templates\.").expect("valid regex");
+    let captures = bindings.captures(flake)?;
+    let indent = &captures[1];
+    let binding = entry.binding("");
+    let line = format!(
+        "{indent}templates.{0}",
+        binding.replace('\n', &format!("\n{indent}"))
+    );
+    let mut flake = flake.to_owned();
+    flake.insert_str(captures.get(0)?.start(), line.trim_end_matches([' ', '\t']));
+    Some(flake)
+}
+
+/// A flake whose only output is the templates, with `entry` as the first one
+fn root_flake(entry: &Entry) -> String {
+    format!(
+        r#"{{
+  description = "Nix flake templates";
+
+  outputs =
+    {{ self }}:
+    {{
+      templates = {{
+{0}      }};
+    }};
+}}
+"#,
+        entry.binding("        ")
+    )
+}
+
+/// How `repository` is passed to `--source`, so it's read as a local folder
+fn source_argument(repository: &Path) -> String {
+    let starts_relative = matches!(
+        repository.components().next(),
+        Some(Component::Normal(_)) | None
+    );
+    if starts_relative {
+        format!("./{0}", repository.display())
+    } else {
+        repository.display().to_string()
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::cache_dir, error::Result, templates::Templates};
+
+/// How long the template list is cached for when the config doesn't say otherwise
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    sources: HashMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    templates: Templates,
+}
+
+pub fn path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("templates.json"))
+}
+
+/// Returns the cached templates of `source`, unless they are older than `ttl`
+pub fn load(source: &str, ttl: Duration) -> Option<Templates> {
+    let mut cache_file = read()?;
+    let entry = cache_file.sources.remove(source)?;
+
+    let age = now().saturating_sub(entry.fetched_at);
+    (age < ttl.as_secs()).then_some(entry.templates)
+}
+
+pub fn store(source: &str, templates: &Templates) -> Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let mut cache_file = read().unwrap_or_default();
+    cache_file.sources.insert(
+        source.to_owned(),
+        CacheEntry {
+            fetched_at: now(),
+            templates: templates.clone(),
+        },
+    );
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&cache_file)?)?;
+
+    Ok(())
+}
+
+fn read() -> Option<CacheFile> {
+    let content = fs::read_to_string(path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -1,314 +1,668 @@
-use std::{
-    collections::HashSet,
-    error::Error,
-    fmt,
-    fs::{self, File},
-    io::{self, Write},
-    process::Command,
+use std::{io, path::PathBuf, process::Command};
+
+use clap::{builder::PossibleValuesParser, Arg, CommandFactory};
+use clap_complete::Shell;
+
+use getflake::{
+    answers::{Answers, Question},
+    authoring::{new_template, validate},
+    cache,
+    colors::{self, BLUE, GREEN, RESET},
+    config::Config,
+    history,
+    icons::{
+        DONE, ERROR, FAVORITE, FOLDER, GIT, HISTORY, OK, RECORD, SETUP, SOURCE, TEMPLATE, UNDO,
+        WARNING, WELCOME, WORKSPACE,
+    },
+    output::{self, RunLogged},
+    prompt, recent, say,
+    scaffold::{
+        default_editor, expand_home, git_config, is_non_empty_dir, open_in_editor, DEFAULT_LICENSE,
+    },
+    script::Scripts,
+    templates::{self, Source, Sources, DEFAULT_SOURCE},
+    tree, undo,
+    workspace::{self, Member},
+    GetflakeError, NewOrInit, Result, Scaffolder,
 };
 
-use serde_json;
+use crate::{
+    args::{Args, FavoriteAction, HistoryAction, ScaffoldArgs, SourceAction, TemplateAction},
+    tui,
+    wizard::Wizard,
+};
 
-struct Template {
-    name: String,
-    print_str: String,
+pub struct Cli {
+    scaffolder: Scaffolder,
+    dry_run: bool,
+    /// Editor to open the project in once it's created
+    editor: Option<String>,
+    develop: Option<bool>,
+    /// Everything the project is scaffolded with, kept in the history
+    answers: Answers,
+    /// Whether the answers were shown and confirmed already
+    confirmed: bool,
 }
 
-const BLUE: &str = "\x1b[0;34m";
-const GREEN: &str = "\x1B[0;32m";
-const RESET: &str = "\x1B[0m";
-
-pub enum NewOrInit {
-    New,
-    Init,
+/// A question of `getflake setup`
+#[derive(Clone, Copy, Debug)]
+enum SetupStep {
+    Author,
+    License,
+    Git,
+    Source,
 }
 
-impl fmt::Display for NewOrInit {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::New => write!(f, "new"),
-            Self::Init => write!(f, "init"),
-        }
-    }
-}
+/// The questions of `getflake setup`, in the order they're asked
+const SETUP_STEPS: &[SetupStep] = &[
+    SetupStep::Author,
+    SetupStep::License,
+    SetupStep::Git,
+    SetupStep::Source,
+];
 
-type Templates = Vec<Template>;
+impl Cli {
+    pub fn init(
+        sources: &Sources,
+        config: &Config,
+        mut args: ScaffoldArgs,
+        mut new_or_init: Option<NewOrInit>,
+        mut project_name: Option<String>,
+    ) -> Result<Self> {
+        let answers = match &args.answers {
+            Some(path) => Answers::load(path)?,
+            None => Answers::default(),
+        };
+        if args.tui && prompt::is_interactive() {
+            let picked = tui::run(
+                sources,
+                config,
+                &mut args,
+                new_or_init,
+                project_name,
+                &answers,
+            )?;
+            new_or_init = Some(picked.new_or_init);
+            project_name = Some(picked.project_name);
+        }
+        let mut cli = Self::with_answers(
+            sources,
+            config,
+            args.clone(),
+            new_or_init,
+            project_name.clone(),
+            answers,
+        )?;
+        if args.dry_run || args.yes || !prompt::is_interactive() {
+            return Ok(cli);
+        }
 
-pub struct Cli {
-    pub template: String,
-    pub new_or_init: NewOrInit,
-    pub project_name: String,
-    pub init_git: bool,
-    pub clear_readme: bool,
+        // Changing an answer asks that question again, keeping all the others
+        loop {
+            cli.scaffolder.print_summary();
+            let Some(question) = prompt::confirm()? else {
+                break;
+            };
+            let mut answers = cli.answers.clone();
+            answers.forget(question);
+            let mut args = args.clone();
+            args.forget(question);
+            let project_name = project_name.clone().filter(|_| question != Question::Name);
+
+            cli = match Self::with_answers(
+                sources,
+                config,
+                args.clone(),
+                new_or_init,
+                project_name,
+                answers.clone(),
+            ) {
+                // The name may not suit the new template
+                Err(GetflakeError::InvalidInput(message)) if question == Question::Template => {
+                    eprintln!("{WARNING} {message}");
+                    answers.name = None;
+                    Self::with_answers(sources, config, args, new_or_init, None, answers)?
+                }
+                result => result?,
+            };
+        }
+        cli.confirmed = true;
+        Ok(cli)
+    }
 
-    url: String,
-}
+    /// Like [`Cli::init`], with `answers` instead of the ones `--answers` points to
+    pub fn with_answers(
+        sources: &Sources,
+        config: &Config,
+        args: ScaffoldArgs,
+        new_or_init: Option<NewOrInit>,
+        project_name: Option<String>,
+        answers: Answers,
+    ) -> Result<Self> {
+        let answered =
+            Wizard::new(sources, config, &args, new_or_init, project_name, answers)?.run()?;
+
+        let recorded = answered.answers();
+        if let Some(path) = &args.record {
+            recorded.save(path)?;
+            say!(
+                "{RECORD} Saved the answers to {GREEN}{0}{RESET}",
+                path.display()
+            );
+        }
 
-impl Cli {
-    pub fn init() -> Result<Self, Box<dyn Error>> {
-        let templates = Self::fetch_templates()?;
+        let hooks = config
+            .defaults(Some(&answered.template))
+            .hooks
+            .unwrap_or_default();
+        let scaffolder =
+            Scaffolder::new(answered.source, &answered.template, &answered.project_name)
+                .new_or_init(answered.new_or_init)
+                .target_directory(answered.target_directory)
+                .existing_directory(answered.existing_directory)
+                .merge_flake(answered.merge_flake)
+                .extra_templates(args.with.clone())
+                .on_conflict(args.on_conflict.as_deref().map(str::parse).transpose()?)
+                .author(&answered.author)
+                .email(&answered.email)
+                .description(&answered.description)
+                .license(&answered.license)
+                .license_file(!args.no_license_file)
+                .init_git(answered.init_git)
+                .commit(answered.commit)
+                .commit_message(&answered.commit_message)
+                .github_repo(answered.github_repo)
+                .remote(answered.remote)
+                .push(answered.push)
+                .pre_commit(answered.pre_commit)
+                .readme(answered.readme)
+                .format(!args.no_format && config.format.unwrap_or(true))
+                .editorconfig(config.editorconfig.unwrap_or(false))
+                .gitattributes(config.gitattributes.unwrap_or(false))
+                .direnv(answered.direnv)
+                .ci(answered.ci)
+                .devcontainer(answered.devcontainer)
+                .pre_hooks(hooks.pre)
+                .post_hooks(hooks.post)
+                .hooks(!args.no_hooks)
+                .variables(answered.variables)
+                .show_diff(args.show_diff)
+                .ignore(
+                    config
+                        .ignore
+                        .iter()
+                        .flatten()
+                        .chain(&args.ignore)
+                        .cloned()
+                        .collect(),
+                )
+                .scripts(if args.no_hooks {
+                    Scripts::default()
+                } else {
+                    Scripts::load()?
+                })?;
 
         Ok(Self {
-            template: Self::get_template(&templates)?,
-            new_or_init: Self::get_new_or_init()?,
-            project_name: Self::get_project_name()?,
-            init_git: Self::get_init_git()?,
-            clear_readme: Self::get_clear_readme()?,
-
-            url: String::from("github:nulladmin1/nix-flake-templates"),
+            scaffolder,
+            dry_run: args.dry_run,
+            editor: match args.open.as_deref() {
+                Some(editor) if !editor.is_empty() => Some(editor.to_owned()),
+                Some(_) => Some(config.editor.clone().unwrap_or_else(default_editor)),
+                None if config.open.unwrap_or(false) => {
+                    Some(config.editor.clone().unwrap_or_else(default_editor))
+                }
+                None => None,
+            },
+            develop: args.develop().or(args.yes.then_some(false)),
+            answers: recorded,
+            confirmed: false,
         })
     }
 
-    fn fetch_templates() -> Result<Templates, Box<dyn Error>> {
-        println!("📥 Fetching templates...");
-
-        let args = [
-            "--extra-experimental-features",
-            "'nix-command flakes'",
-            "flake",
-            "show",
-            "--json",
-            "github:nulladmin1/nix-flake-templates",
-        ];
-        let mut command = Command::new("nix");
-        command.args(args);
-
-        match command.output() {
-            Ok(output) => {
-                let output_json = String::from_utf8(output.stdout)?;
-                let parsed_json: serde_json::Value = serde_json::from_str(&output_json)?;
-                let templates_json = parsed_json.get("templates").unwrap();
-
-                let mut templates: Templates = Vec::new();
-
-                for (key, value) in templates_json.as_object().unwrap() {
-                    let description = if key == &"default".to_owned() {
-                        "Empty/Blank".to_string()
-                    } else {
-                        value
-                            .get("description")
-                            .unwrap()
-                            .as_str()
-                            .unwrap()
-                            .strip_prefix("Nix Flake Template for ")
-                            .unwrap()
-                            .to_string()
-                    };
-                    templates.push(Template {
-                        name: key.to_string(),
-                        print_str: description,
-                    });
-                }
-                let mut duplicate_descriptions: HashSet<String> = HashSet::new();
-                templates
-                    .retain(|template| duplicate_descriptions.insert(template.print_str.clone()));
+    pub fn list(sources: &Sources) -> Result<()> {
+        let templates = sources.unique_templates()?;
+        if output::is_json() {
+            return output::print_json(&templates);
+        }
 
-                Ok(templates)
-            }
-            Err(e) => {
-                eprintln!("❌ Failed to fetch templates: {e}");
-                Err(Box::from("Failed to fetch templates"))
+        say!("\n{TEMPLATE} Available templates:");
+        for (category, templates) in templates::by_category(&templates) {
+            say!("\n{BLUE}{category}{RESET}");
+            for template in templates {
+                let names = template.names().collect::<Vec<_>>().join(", ");
+                say!("- {GREEN}{names}{RESET}: {0}", template.print_str);
             }
         }
-    }
-
-    pub fn run(&self) -> Result<(), Box<dyn Error>> {
-        println!("\n🫵 You selected: ");
-        println!("- Template: {GREEN}{0}{RESET}", self.template);
-        println!("- To {GREEN}{0}{RESET}", self.new_or_init);
-        println!("- Project name: {GREEN}{0}{RESET}", self.project_name);
-        println!("- Initialize Git: {GREEN}{0}{RESET}", self.init_git);
-        println!("- Clear README.md: {GREEN}{0}{RESET}", self.clear_readme);
 
-        println!("\n🚀 Initializing project...");
-
-        let url = format!("{}#{}", self.url.as_str(), self.template.as_str());
-
-        let new_or_init_string = self.new_or_init.to_string();
-        let new_or_init = new_or_init_string.as_str();
+        Ok(())
+    }
 
-        let args = [
-            "--extra-experimental-features",
-            "'nix-command flakes'",
-            "flake",
-            new_or_init,
-            "--template",
-            &url,
-        ];
+    /// Lists the projects scaffolded so far, or scaffolds one of them again
+    pub fn history(
+        mut source: Source,
+        config: &Config,
+        action: Option<HistoryAction>,
+    ) -> Result<()> {
+        let Some(HistoryAction::Rerun { id, name, scaffold }) = action else {
+            let entries = history::load();
+            if output::is_json() {
+                return output::print_json(&entries);
+            }
+            if entries.is_empty() {
+                say!("{HISTORY} No projects scaffolded yet");
+                return Ok(());
+            }
+            say!("\n{HISTORY} Projects scaffolded so far:");
+            for entry in &entries {
+                say!(
+                    "  {BLUE}{0}){RESET} {1} {GREEN}{2}{RESET} from {3}, in {4}",
+                    entry.id,
+                    entry.date(),
+                    entry.answers.name.as_deref().unwrap_or_default(),
+                    entry.template,
+                    entry.directory.display()
+                );
+            }
+            return Ok(());
+        };
 
-        let mut command_string = "nix ".to_string() + args.join(" ").as_str();
+        let Some(entry) = history::find(id) else {
+            return Err(GetflakeError::InvalidInput(format!(
+                "there's no project {id} in the history, see `getflake history`"
+            )));
+        };
+        let mut answers = entry.answers;
+        // The remote repository was the old project's
+        answers.remote = None;
+        source.url = entry.source;
+        Self::with_answers(
+            &Sources::single(source),
+            config,
+            scaffold,
+            None,
+            Some(name),
+            answers,
+        )?
+        .run()
+    }
 
-        let mut command = Command::new("nix");
+    /// Scaffolds each of `members` in its own folder of the workspace `name`, and writes the
+    /// flake that wires them together at its root
+    pub fn workspace(
+        sources: &Sources,
+        config: &Config,
+        name: &str,
+        members: Vec<Member>,
+        args: ScaffoldArgs,
+    ) -> Result<()> {
+        let directory = PathBuf::from(
+            args.dir
+                .as_deref()
+                .map_or_else(|| name.to_owned(), expand_home),
+        );
+        if is_non_empty_dir(&directory.to_string_lossy()) {
+            return Err(GetflakeError::InvalidInput(format!(
+                "the {0} folder already exists and isn't empty",
+                directory.display()
+            )));
+        }
 
-        command.args(args);
+        // The answers to the first project's questions are reused for the others
+        let mut answers = match &args.answers {
+            Some(path) => Answers::load(path)?,
+            None => Answers::default(),
+        };
+        for member in &members {
+            say!(
+                "\n{WORKSPACE} Scaffolding {GREEN}{0}{RESET} with the {1} template",
+                member.name,
+                member.template
+            );
+            let mut args = args.clone();
+            args.template = Some(member.template.clone());
+            args.dir = Some(directory.join(&member.name).to_string_lossy().into_owned());
+            // The workspace is one repository, and one dev shell
+            (args.git, args.no_git) = (false, true);
+            (args.develop, args.no_develop) = (false, true);
+            args.open = None;
+            answers.template = None;
+            answers.name = None;
+            answers.variables.clear();
+
+            let cli = Self::with_answers(
+                sources,
+                config,
+                args,
+                Some(NewOrInit::New),
+                Some(member.name.clone()),
+                answers,
+            )?;
+            answers = cli.answers.clone();
+            cli.run()?;
+        }
 
-        let directory = match &self.new_or_init {
-            NewOrInit::New => self.project_name.clone(),
-            NewOrInit::Init => ".".to_string(),
+        let init_git = match args.init_git().or(config.init_git) {
+            Some(init_git) => init_git,
+            None if args.yes || args.dry_run || !prompt::is_interactive() => true,
+            None => prompt::init_git(true)?,
         };
+        if args.dry_run {
+            say!(
+                "- Write {0}/flake.nix, with the projects as its inputs",
+                directory.display()
+            );
+            if init_git {
+                say!("- Run {GREEN}git -C {0} init{RESET}", directory.display());
+            }
+            return Ok(());
+        }
 
-        if let NewOrInit::New = self.new_or_init {
-            let project_name = self.project_name.as_str();
-            command.arg(project_name);
-            command_string.push_str(format!(" {project_name}").as_str());
+        workspace::write_flake(&directory, name, &members)?;
+        say!(
+            "\n{WORKSPACE} Wrote {GREEN}{0}/flake.nix{RESET}, with the projects as its inputs",
+            directory.display()
+        );
+        if init_git {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(&directory)
+                .arg("init")
+                .run_logged()?;
+            if output.status.success() {
+                say!("{GIT} Initialized Git repository {GREEN}successfully{RESET}");
+            } else {
+                eprintln!("- {ERROR}Failed to initialize the Git repository, run git init yourself from the workspace's folder");
+            }
         }
+        say!(
+            "{DONE} Done! Run {GREEN}nix develop{RESET} in {0} for every project's tools at once",
+            directory.display()
+        );
+        Ok(())
+    }
 
-        println!("❄️ Running {GREEN}{command_string}{RESET} ...");
-        command.output()?;
-        println!("👑 Created project {GREEN}successfully{RESET}\n");
+    /// Asks for the settings most worth having, and writes them to the config file
+    ///
+    /// It's run on the first launch, and by `getflake setup`; the current settings are the
+    /// defaults.
+    pub fn setup(config: &Config) -> Result<()> {
+        say!("{SETUP} Let's set getflake up, the answers become the defaults of every project");
+
+        let mut author = config
+            .author
+            .clone()
+            .or_else(|| git_config("user.name"))
+            .unwrap_or_default();
+        let mut license = config
+            .license
+            .clone()
+            .unwrap_or_else(|| DEFAULT_LICENSE.to_owned());
+        let mut init_git = config.init_git.unwrap_or(true);
+        let mut source = config
+            .source
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SOURCE.to_owned());
+
+        let mut question: usize = 0;
+        while let Some(step) = SETUP_STEPS.get(question) {
+            let answered = match step {
+                SetupStep::Author => prompt::author(&author).map(|answer| author = answer),
+                SetupStep::License => prompt::license(&license).map(|answer| license = answer),
+                SetupStep::Git => prompt::init_git(init_git).map(|answer| init_git = answer),
+                SetupStep::Source => prompt::source(&source).map(|answer| source = answer),
+            };
+            match answered {
+                Ok(()) => question += 1,
+                Err(GetflakeError::Back) => question = question.saturating_sub(1),
+                Err(e) => return Err(e),
+            }
+        }
 
-        println!("🔀 Updating project details with the project name...");
-        self.update_project_names()?;
+        Config::save_setup(
+            &author,
+            &license,
+            init_git,
+            Some(source.as_str()).filter(|source| *source != DEFAULT_SOURCE),
+        )?;
+        if let Some(path) = Config::path() {
+            say!("{OK} Saved the settings to {0}", path.display());
+        }
+        Ok(())
+    }
 
-        println!();
+    /// Takes back the last scaffold, once the user confirms it unless `yes` is set
+    pub fn undo(yes: bool) -> Result<()> {
+        let Some(state) = undo::load() else {
+            say!("{UNDO} There's nothing to undo");
+            return Ok(());
+        };
 
-        if self.init_git {
-            println!("🔧 Initializing Git repository...");
-            Command::new("git")
-                .args(["init", directory.as_str()])
-                .output()?;
-            println!("🔧 Initialized Git repository {GREEN}successfully{RESET}\n");
+        let directory = state.directory.display();
+        if state.created_directory {
+            say!("\n{UNDO} This removes {GREEN}{directory}{RESET} and everything in it");
+        } else {
+            say!("\n{UNDO} This changes {GREEN}{directory}{RESET}:");
+            for path in &state.created {
+                say!("- Remove {0}", path.display());
+            }
+            for path in state.modified.keys() {
+                say!("- Restore {0}", path.display());
+            }
+            if let Some(commit) = &state.commit {
+                say!("- Reset the commit {0}", commit.id);
+            }
         }
-
-        if self.clear_readme {
-            println!("🧹 Clearing README.md file...");
-            let mut file = File::create(format!("{}/README.md", directory.as_str()))?;
-            let content = format!("# {0}\n\nLorem ipsum dolor sit amet", self.project_name);
-            file.write_all(content.as_bytes())?;
-            println!("🧹 Cleared README.md file {GREEN}successfully{RESET}\n");
+        if !yes && !prompt::input_bool("Do you want to undo the last scaffold?", false)? {
+            return Err(GetflakeError::Aborted);
         }
 
-        println!("🎉 Done!");
+        state.undo()?;
+        say!("{DONE} Undid the last scaffold");
+        Ok(())
+    }
 
+    /// Adds, removes or lists the source aliases
+    pub fn source(config: &Config, action: SourceAction) -> Result<()> {
+        let mut registry = config.registry.clone().unwrap_or_default();
+        match action {
+            SourceAction::Add { alias, url } => {
+                let is_alias = alias
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if alias.is_empty() || !is_alias {
+                    return Err(GetflakeError::InvalidInput(format!(
+                        "{alias} can only have letters, digits, - and _"
+                    )));
+                }
+                registry.insert(alias.clone(), url.clone());
+                Config::save_registry(&registry)?;
+                say!("{SOURCE} {GREEN}{alias}{RESET} now stands for {url}");
+            }
+            SourceAction::Remove { alias } => {
+                if registry.remove(&alias).is_none() {
+                    say!("{SOURCE} There's no {GREEN}{alias}{RESET} alias");
+                    return Ok(());
+                }
+                Config::save_registry(&registry)?;
+                say!("{SOURCE} Removed the {GREEN}{alias}{RESET} alias");
+            }
+            SourceAction::List => {
+                if output::is_json() {
+                    return output::print_json(&registry);
+                }
+                if registry.is_empty() {
+                    say!("{SOURCE} No aliases yet, add one with `getflake source add <alias> <flake-ref>`");
+                }
+                for (alias, url) in &registry {
+                    say!("- {GREEN}{alias}{RESET}: {url}");
+                }
+            }
+        }
         Ok(())
     }
 
-    fn print_prompt() -> Result<(), Box<dyn Error>> {
-        print!("> ");
-        io::stdout().flush()?;
+    /// Creates or validates templates
+    ///
+    /// Returns whether validating the template passed, and `true` otherwise.
+    pub fn template(source: &Source, action: TemplateAction) -> Result<bool> {
+        match action {
+            TemplateAction::New {
+                name,
+                path,
+                description,
+            } => {
+                let description = description.unwrap_or_else(|| format!("A {name} project"));
+                new_template(&path, &name, &description)?;
+                Ok(true)
+            }
+            TemplateAction::Validate {
+                template,
+                no_check,
+                strict,
+            } => Ok(match template.split_once('#') {
+                Some((url, template)) => validate(
+                    &Source::new(url.to_owned(), cache::DEFAULT_TTL, false),
+                    template,
+                    !no_check,
+                    strict,
+                ),
+                None => validate(source, &template, !no_check, strict),
+            }),
+        }
+    }
+
+    /// Adds, removes or lists the templates pinned at the top of the picker
+    pub fn favorite(sources: &Sources, config: &Config, action: FavoriteAction) -> Result<()> {
+        let mut favorites = config.favorites.clone().unwrap_or_default();
+        match action {
+            FavoriteAction::Add { template } => {
+                if favorites.contains(&template) {
+                    say!("{FAVORITE} {GREEN}{template}{RESET} is already a favorite");
+                    return Ok(());
+                }
+                // Favorites can still be added while offline
+                if let Ok(templates) = sources.unique_templates() {
+                    if !templates
+                        .iter()
+                        .any(|other| other.names().any(|name| name == template))
+                    {
+                        return Err(GetflakeError::TemplateNotFound(template));
+                    }
+                }
+                favorites.push(template.clone());
+                Config::save_favorites(&favorites)?;
+                say!("{FAVORITE} Added {GREEN}{template}{RESET} to the favorites");
+            }
+            FavoriteAction::Remove { template } => {
+                if !favorites.contains(&template) {
+                    say!("{FAVORITE} {GREEN}{template}{RESET} isn't a favorite");
+                    return Ok(());
+                }
+                favorites.retain(|other| *other != template);
+                Config::save_favorites(&favorites)?;
+                say!("{FAVORITE} Removed {GREEN}{template}{RESET} from the favorites");
+            }
+            FavoriteAction::List => {
+                if output::is_json() {
+                    return output::print_json(&favorites);
+                }
+                if favorites.is_empty() {
+                    say!("{FAVORITE} No favorites yet, add one with `getflake favorite add <template>`");
+                }
+                for template in &favorites {
+                    say!("- {GREEN}{template}{RESET}");
+                }
+            }
+        }
         Ok(())
     }
 
-    fn get_template(templates: &Templates) -> Result<String, Box<dyn Error>> {
-        println!("📦 What {GREEN}template{RESET} do you want to use? ");
+    pub fn info(sources: &Sources, config: &Config, name: &str) -> Result<()> {
+        let (source, name) = sources.resolve(&config.template_name(name))?;
+        let name = name.as_str();
+        let templates = source.templates()?;
 
-        (1..templates.len() + 1).for_each(|i| {
-            let template_str = &templates[i - 1].print_str;
-            println!("  {BLUE}{i}){RESET} {template_str}");
-        });
-        print!("👆 Pick a number or enter the code for the template: ");
-        io::stdout().flush()?;
+        let Some(template) = templates.iter().find(|template| template.name == name) else {
+            return Err(GetflakeError::TemplateNotFound(name.to_owned()));
+        };
 
-        let mut template_input = String::new();
-        io::stdin().read_line(&mut template_input)?;
-        let template_input: usize = template_input
-            .trim()
-            .parse()
-            .expect("Please enter a {GREEN}number{RESET} within that range");
+        let details = source.template_details(&template.name)?;
 
-        Ok(templates[template_input - 1].name.to_owned())
-    }
+        say!("\n{TEMPLATE} Template: {GREEN}{0}{RESET}", template.name);
+        say!("- Description: {0}", details.description);
+        say!("- Source: {GREEN}{0}#{1}{RESET}", source.url, template.name);
 
-    fn get_new_or_init() -> Result<NewOrInit, Box<dyn Error>> {
-        println!("🤔 Do you want to create a {GREEN}new{RESET} project or {GREEN}init{RESET}ialize one in this folder?");
-        Self::print_prompt()?;
+        if let Some(welcome_text) = &details.welcome_text {
+            say!("\n{WELCOME} Welcome text:");
+            colors::print_markdown(welcome_text);
+        }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        match input.trim().to_lowercase().as_str() {
-            "new" | "n" => Ok(NewOrInit::New),
-            "init" | "i" => Ok(NewOrInit::Init),
-            _ => Err(Box::from("❌Invalid input: enter 'new' to create a new project; 'init' to initialize one in this folder")),
+        say!("\n{FOLDER} Files:");
+        for line in tree::file_tree(&details.path)? {
+            say!("  {line}");
         }
-    }
 
-    fn get_project_name() -> Result<String, Box<dyn Error>> {
-        println!("📝 What do you want to name your project?");
-        Self::input_string()
+        Ok(())
     }
 
-    fn get_init_git() -> Result<bool, Box<dyn Error>> {
-        println!("💾Do you want to initialize a Git repository (using git init)?");
-        Self::input_bool()
-    }
+    /// Prints the completion script for `shell`, with the template names known at the time
+    pub fn completions(sources: &Sources, shell: Shell) {
+        let mut command = Args::command();
+
+        // Completing template names mustn't hit the network, so only use the cache
+        if let Some(templates) = sources.cached_templates() {
+            let names: Vec<String> = templates
+                .iter()
+                .flat_map(|template| template.names().map(str::to_owned))
+                .collect();
+            let with_names = |arg: Arg| arg.value_parser(PossibleValuesParser::new(names.clone()));
+            command = command
+                .mut_arg("template", with_names)
+                .mut_subcommand("new", |new| new.mut_arg("template", with_names))
+                .mut_subcommand("init", |init| init.mut_arg("template", with_names))
+                .mut_subcommand("info", |info| info.mut_arg("template", with_names));
+        }
 
-    fn get_clear_readme() -> Result<bool, Box<dyn Error>> {
-        println!("📄Do you want to clear the README.md file?");
-        Self::input_bool()
+        clap_complete::generate(shell, &mut command, "getflake", &mut io::stdout());
     }
 
-    fn input_bool() -> Result<bool, Box<dyn Error>> {
-        Self::print_prompt()?;
-        let mut input_string = String::new();
-        io::stdin().read_line(&mut input_string)?;
+    pub fn run(&self) -> Result<()> {
+        if !self.confirmed {
+            self.scaffolder.print_summary();
+        }
 
-        match input_string.trim().to_lowercase().as_str() {
-        "y" | "yes" | "true" => Ok(true),
-        "n" | "no" | "false" => Ok(false),
-        _ => Err(Box::from("❌Invalid input: enter 'y', 'yes', or 'true' to agree; 'n', 'no', or 'false' to disagree")),
+        if self.dry_run {
+            return self.scaffolder.print_plan();
         }
-    }
 
-    fn input_string() -> Result<String, Box<dyn Error>> {
-        Self::print_prompt()?;
-        let mut input_string = String::new();
-        io::stdin().read_line(&mut input_string)?;
-        Ok(input_string.trim().to_owned())
-    }
+        let report = self.scaffolder.run()?;
+        // Only the picker's order depends on it
+        let _ = recent::record(&report.source, &report.template);
+        if let Err(e) = history::record(&report, &self.answers) {
+            eprintln!("{WARNING} Unable to add the project to the history\n{e}");
+        }
+        if output::is_json() {
+            output::print_json(&report)?;
+        }
 
-    fn update_project_names(&self) -> Result<(), Box<dyn Error>> {
-        let directory = (match &self.new_or_init {
-            NewOrInit::New => self.project_name.clone(),
-            NewOrInit::Init => ".".to_string(),
-        }) + "/";
-
-        // Rename all files containing "project_name" with &self.project_name
-        if let Ok(output) = Command::new("grep")
-            .args(["-rl", "project_name", directory.as_str()])
-            .output()
-        {
-            let file_names = String::from_utf8_lossy(&output.stdout);
-            for file_name in file_names.lines() {
-                if let Ok(content) = fs::read_to_string(file_name) {
-                    if content.contains("project_name") {
-                        let new_content = content.replace("project_name", &self.project_name);
-                        if fs::write(file_name, new_content).is_ok() {
-                            println!(
-                                "- ✔️ Replaced 'project_name' placeholder with {0} in file {1}",
-                                &self.project_name, &file_name
-                            );
-                        } else {
-                            eprintln!("- ❌Failed to write to file: {file_name}");
-                        }
-                    }
-                } else {
-                    eprintln!("- ❌Failed to read file: {file_name}");
-                }
+        if let Some(editor) = &self.editor {
+            // The project is there either way
+            if let Err(e) = open_in_editor(editor, &report.path) {
+                eprintln!("- {ERROR}Failed to open the project\n{e}");
             }
-        } else {
-            eprintln!("Unable to run 'grep' to find all instances of 'project_name' within the flake directory. ")
         }
 
-        // Rename all files and folders containing "project_name" with &self.project_name
-        if let Ok(output) = Command::new("find")
-            .args([directory.as_str(), "-name", "*project_name*"])
-            .output()
-        {
-            let paths = String::from_utf8_lossy(&output.stdout);
-            for path in paths.lines() {
-                let new_path = path.replace("project_name", &self.project_name);
-                if fs::rename(path, &new_path).is_ok() {
-                    println!(
-                        "- ✔️ Renamed {0} containing 'project_name' to {1}",
-                        &path, &self.project_name
-                    );
-                } else {
-                    eprintln!("- ❌Failed to rename file or folder: {path}");
-                }
-            }
-        } else {
-            eprintln!("- ❌Failed to find files or folders containing 'project_name'.");
+        // The shell would mix its output into the JSON
+        let develop = match self.develop {
+            _ if output::is_json() || !self.scaffolder.can_develop() => false,
+            Some(develop) => develop,
+            None if prompt::is_interactive() => prompt::develop()?,
+            None => false,
+        };
+        if develop {
+            self.scaffolder.develop(&report)?;
         }
+
         Ok(())
     }
 }
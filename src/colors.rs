@@ -0,0 +1,86 @@
+//! ANSI escape codes used to highlight output
+//!
+//! They print as nothing when colors are off: with `--color never`, when `NO_COLOR` is set, or
+//! when the output isn't a terminal.
+
+use std::{
+    env, fmt,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use termimad::MadSkin;
+
+use crate::output;
+
+/// An escape code, printed only when colors are on
+pub struct Color(&'static str);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if enabled() {
+            f.write_str(self.0)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub const BLUE: Color = Color("\x1b[0;34m");
+pub const GREEN: Color = Color("\x1B[0;32m");
+pub const RED: Color = Color("\x1B[0;31m");
+pub const RESET: Color = Color("\x1B[0m");
+
+const UNDECIDED: u8 = 0;
+const ON: u8 = 1;
+const OFF: u8 = 2;
+
+static ENABLED: AtomicU8 = AtomicU8::new(UNDECIDED);
+
+/// Turns colors on or off, instead of deciding with [`auto`]
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(if enabled { ON } else { OFF }, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    match ENABLED.load(Ordering::Relaxed) {
+        ON => true,
+        OFF => false,
+        _ => {
+            let enabled = auto();
+            set_enabled(enabled);
+            enabled
+        }
+    }
+}
+
+/// Whether colors should be on, following <https://no-color.org>
+pub fn auto() -> bool {
+    if env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty()) {
+        return false;
+    }
+    if output::is_json() {
+        io::stderr().is_terminal()
+    } else {
+        io::stdout().is_terminal()
+    }
+}
+
+/// The theme of the arrow-key menus
+pub fn theme() -> Box<dyn Theme> {
+    if enabled() {
+        Box::new(ColorfulTheme::default())
+    } else {
+        Box::new(SimpleTheme)
+    }
+}
+
+/// Prints markdown, like the welcome text of templates
+pub fn print_markdown(text: &str) {
+    if enabled() {
+        termimad::print_text(text);
+    } else {
+        MadSkin::no_style().print_text(text);
+    }
+}
@@ -0,0 +1,93 @@
+//! `getflake add`: adding a piece of a template, like its justfile or CI workflows, to a project
+//! that already exists
+
+use std::{fs, path::Path};
+
+use crate::{
+    cache,
+    colors::{GREEN, RESET},
+    error::{GetflakeError, Result},
+    icons::{DONE, GENERATE, OK, PLAN},
+    provenance::{self, Provenance},
+    render::{self, render},
+    say,
+    scaffold::{add_files, Conflict},
+    templates::Source,
+};
+
+/// Adds the file or folder `component` of `template` to the project in `directory`
+///
+/// Without `template`, it's taken from the one the project was generated from, as its
+/// `.getflake.toml` says, and the placeholders are replaced with the values recorded there.
+pub fn add(
+    source: &Source,
+    template: Option<&str>,
+    component: &str,
+    directory: &Path,
+    on_conflict: Option<Conflict>,
+    dry_run: bool,
+) -> Result<()> {
+    let provenance = Provenance::load(directory).ok();
+    let (source, template, variables) = match (template, provenance) {
+        (Some(template), provenance) => (
+            source.clone(),
+            template.to_owned(),
+            provenance.map(|provenance| provenance.variables),
+        ),
+        (None, Some(provenance)) => (
+            // The latest version of the template, not the one the project was generated from
+            Source::new(provenance.source, cache::DEFAULT_TTL, false),
+            provenance.template,
+            Some(provenance.variables),
+        ),
+        (None, None) => {
+            return Err(GetflakeError::InvalidInput(format!(
+                "there's no {0} saying which template the project was generated from, so pass --template",
+                provenance::FILE_NAME
+            )))
+        }
+    };
+
+    say!(
+        "{GENERATE} Generating {GREEN}{template}{RESET} from {0}...",
+        source.url
+    );
+    let rendered = render(&source, &template, &variables.unwrap_or_default())?;
+    let from = rendered.path().join(component.trim_end_matches('/'));
+    if !from.exists() {
+        let mut entries: Vec<String> = fs::read_dir(rendered.path())?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        entries.sort();
+        return Err(GetflakeError::InvalidInput(format!(
+            "the {template} template has no {component}, only {0}",
+            entries.join(", ")
+        )));
+    }
+
+    if dry_run {
+        say!("\n{PLAN} Dry run, nothing will be changed. getflake would:");
+        for file in render::files(rendered.path()) {
+            if !rendered.path().join(&file).starts_with(&from) {
+                continue;
+            }
+            if directory.join(&file).exists() {
+                say!(
+                    "- Merge {0} into the project's, or ask what to do",
+                    file.display()
+                );
+            } else {
+                say!("- Add {0}", file.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let written = add_files(&template, rendered.path(), &from, directory, on_conflict)?;
+    for path in &written {
+        say!("- {OK} Wrote {0}", path.to_string_lossy());
+    }
+    say!("{DONE} Added {GREEN}{component}{RESET} from the {template} template");
+    Ok(())
+}
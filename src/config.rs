@@ -0,0 +1,419 @@
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf};
+
+use serde::Deserialize;
+use toml::{Table, Value};
+use toml_edit::{value, Array, DocumentMut, Item};
+
+use crate::{
+    error::{GetflakeError, Result},
+    hooks::{self, Hooks},
+    icons::WARNING,
+    scaffold::{Ci, Readme},
+};
+
+/// Prefix of the environment variables that override the config file, e.g. `GETFLAKE_TEMPLATE`
+const ENV_PREFIX: &str = "GETFLAKE_";
+
+/// User settings, read from `$XDG_CONFIG_HOME/getflake/config.toml`, and from the
+/// `GETFLAKE_*` environment variables, which take precedence over it
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Flake reference of the template repository
+    pub source: Option<String>,
+
+    /// Several template repositories listed together, by the names their templates are prefixed
+    /// with (e.g. `work/rust`), instead of `source`
+    pub sources: Option<BTreeMap<String, String>>,
+
+    /// Names that stand for flake references wherever a source is expected, managed with
+    /// `getflake source`
+    pub registry: Option<BTreeMap<String, String>>,
+
+    /// Tokens private repositories are fetched with, by host (e.g. `github.com`), passed to nix
+    /// as `access-tokens`
+    pub access_tokens: Option<BTreeMap<String, String>>,
+
+    /// How long the template list is cached for, in seconds
+    pub cache_ttl: Option<u64>,
+
+    /// How many times fetching a source is tried in all when the network fails
+    pub retry_attempts: Option<u32>,
+
+    /// How long to wait before fetching again, in seconds, doubled on each retry
+    pub retry_backoff: Option<u64>,
+
+    /// How long to keep retrying a fetch for, in seconds
+    pub retry_timeout: Option<u64>,
+
+    /// How long nix, git and the other commands getflake runs may take, in seconds
+    pub command_timeout: Option<u64>,
+
+    /// Template selected by default
+    pub template: Option<String>,
+
+    /// Names that stand for templates, e.g. `web = "typescript-vite"`
+    pub alias: Option<BTreeMap<String, String>>,
+
+    /// Whether to show the files of the picked template, and confirm it before going on
+    pub preview: Option<bool>,
+
+    /// Templates pinned at the top of the picker, managed with `getflake favorite`
+    pub favorites: Option<Vec<String>>,
+
+    /// Whether to initialize a Git repository by default
+    pub init_git: Option<bool>,
+
+    /// What to do with the template's README.md by default: `keep` it, `retitle` it, or
+    /// `replace` it (`clear_readme = true` still means `replace`)
+    #[serde(alias = "clear_readme")]
+    pub readme: Option<Readme>,
+
+    /// Whether to open new projects in the editor when they're created
+    pub open: Option<bool>,
+
+    /// Command new projects are opened with, instead of `$VISUAL`, `$EDITOR` or VS Code
+    pub editor: Option<String>,
+
+    /// Whether to run `nix fmt` on new projects, which is the default
+    pub format: Option<bool>,
+
+    /// Whether to set up direnv by default, instead of only when it's installed
+    pub direnv: Option<bool>,
+
+    /// Which forge to write a CI workflow for by default: `github`, `gitlab`, or `skip`
+    pub ci: Option<Ci>,
+
+    /// Whether to write a devcontainer.json by default
+    pub devcontainer: Option<bool>,
+
+    /// Whether to install a pre-commit hook by default
+    pub pre_commit: Option<bool>,
+
+    /// Whether to write an .editorconfig suited to the template's language
+    pub editorconfig: Option<bool>,
+
+    /// Whether to write a .gitattributes suited to the template's language
+    pub gitattributes: Option<bool>,
+
+    /// How many times a question is asked before giving up on invalid answers
+    pub max_attempts: Option<u32>,
+
+    /// Whether messages start with emoji, rather than ASCII tags like `[git]`
+    pub emoji: Option<bool>,
+
+    /// Name of the author of new projects, instead of `user.name` from the Git config
+    pub author: Option<String>,
+
+    /// Email address of the author of new projects, instead of `user.email` from the Git config
+    pub email: Option<String>,
+
+    /// GitHub username the remote repository URL is suggested with
+    pub github_user: Option<String>,
+
+    /// GitLab username the remote repository URL is suggested with, when there's no GitHub one
+    pub gitlab_user: Option<String>,
+
+    /// URL the remote repository is suggested with, where `{name}` stands for the project's name,
+    /// instead of a GitHub or GitLab one
+    pub remote_url: Option<String>,
+
+    /// License of new projects
+    pub license: Option<String>,
+
+    /// Glob patterns of the files and folders placeholders are never replaced in
+    pub ignore: Option<Vec<String>>,
+
+    /// Commands run in new projects' folders around scaffolding them, under `[hooks]`
+    pub hooks: Option<Hooks>,
+
+    /// Defaults for single templates, by name, which take precedence over the ones above
+    pub templates: Option<BTreeMap<String, TemplateDefaults>>,
+
+    /// Settings used together, by name, which take precedence over the ones above when the
+    /// profile is picked with `--profile`
+    pub profiles: Option<BTreeMap<String, Table>>,
+
+    /// Profile used when `--profile` isn't passed
+    pub profile: Option<String>,
+}
+
+/// Answers the questions start out with when a template is picked, under `[templates.<name>]`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateDefaults {
+    pub author: Option<String>,
+
+    pub email: Option<String>,
+
+    pub license: Option<String>,
+
+    pub init_git: Option<bool>,
+
+    #[serde(alias = "clear_readme")]
+    pub readme: Option<Readme>,
+
+    pub direnv: Option<bool>,
+
+    pub ci: Option<Ci>,
+
+    pub devcontainer: Option<bool>,
+
+    pub pre_commit: Option<bool>,
+
+    /// Hooks run instead of the ones of the `[hooks]` section
+    pub hooks: Option<Hooks>,
+
+    /// Values of the template's variables, or of extra placeholders to replace
+    pub extra_vars: Option<BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// Reads the settings, with the ones of `profile` (or else of the `profile` setting) taking
+    /// precedence over the rest of the file
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let path = Self::path();
+        let invalid = |e: toml::de::Error| GetflakeError::InvalidConfig {
+            path: path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            message: e.to_string(),
+        };
+
+        let mut settings = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => toml::from_str::<Table>(&content).map_err(invalid)?,
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => Table::new(),
+        };
+        let overrides = env_overrides()?;
+
+        let profile = profile.map(str::to_owned).or_else(|| {
+            [&overrides, &settings]
+                .into_iter()
+                .find_map(|table| table.get("profile")?.as_str().map(str::to_owned))
+        });
+        if let Some(profile) = profile {
+            let profiles = [&overrides, &settings]
+                .into_iter()
+                .find_map(|table| table.get("profiles")?.as_table());
+            let Some(Value::Table(bundled)) = profiles.and_then(|profiles| profiles.get(&profile))
+            else {
+                return Err(GetflakeError::InvalidInput(format!(
+                    "there's no {profile} profile under [profiles] in the config file"
+                )));
+            };
+            settings.extend(bundled.clone());
+        }
+
+        // Each variable was checked on its own, so what's left to fail is the file
+        settings.extend(overrides);
+        Value::Table(settings).try_into().map_err(invalid)
+    }
+
+    /// Replaces the favorites in the config file with `favorites`, creating the file if needed
+    ///
+    /// The rest of the file is written back as it was, comments included.
+    pub fn save_favorites(favorites: &[String]) -> Result<()> {
+        Self::save([(
+            "favorites",
+            value(favorites.iter().map(String::as_str).collect::<Array>()),
+        )])
+    }
+
+    /// Replaces the source aliases in the config file with `registry`, like
+    /// [`Config::save_favorites`]
+    pub fn save_registry(registry: &BTreeMap<String, String>) -> Result<()> {
+        let mut table = toml_edit::Table::new();
+        for (alias, url) in registry {
+            table.insert(alias, value(url.as_str()));
+        }
+        Self::save([("registry", Item::Table(table))])
+    }
+
+    /// Writes the answers of the first-run setup to the config file, like
+    /// [`Config::save_favorites`]
+    ///
+    /// The source is only written when it isn't getflake's default one.
+    pub fn save_setup(
+        author: &str,
+        license: &str,
+        init_git: bool,
+        source: Option<&str>,
+    ) -> Result<()> {
+        let mut items = vec![
+            ("author", value(author)),
+            ("license", value(license)),
+            ("init_git", value(init_git)),
+        ];
+        if let Some(source) = source {
+            items.push(("source", value(source)));
+        }
+        Self::save(items)
+    }
+
+    /// Whether there's a config file yet
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|path| path.exists())
+    }
+
+    /// Sets each key to its item in the config file, creating the file if needed
+    fn save<'k>(items: impl IntoIterator<Item = (&'k str, Item)>) -> Result<()> {
+        let Some(path) = Self::path() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "there's no config folder, as neither XDG_CONFIG_HOME nor HOME is set",
+            )
+            .into());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut document: DocumentMut =
+            content
+                .parse()
+                .map_err(|e: toml_edit::TomlError| GetflakeError::InvalidConfig {
+                    path: path.to_string_lossy().into_owned(),
+                    message: e.to_string(),
+                })?;
+        for (key, item) in items {
+            document[key] = item;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, document.to_string())?;
+        Ok(())
+    }
+
+    /// The defaults of the questions for `template`, from its section and then from the rest of
+    /// the config file
+    pub fn defaults(&self, template: Option<&str>) -> TemplateDefaults {
+        let section = template
+            .and_then(|template| self.templates.as_ref()?.get(template))
+            .cloned()
+            .unwrap_or_default();
+        TemplateDefaults {
+            author: section.author.or_else(|| self.author.clone()),
+            email: section.email.or_else(|| self.email.clone()),
+            license: section.license.or_else(|| self.license.clone()),
+            init_git: section.init_git.or(self.init_git),
+            readme: section.readme.or(self.readme),
+            direnv: section.direnv.or(self.direnv),
+            ci: section.ci.or(self.ci),
+            devcontainer: section.devcontainer.or(self.devcontainer),
+            hooks: section.hooks.or_else(|| self.hooks.clone()),
+            pre_commit: section.pre_commit.or(self.pre_commit),
+            extra_vars: section.extra_vars,
+        }
+    }
+
+    /// The template `template` stands for if it's an alias, or else `template` itself
+    pub fn template_name(&self, template: &str) -> String {
+        self.alias
+            .as_ref()
+            .and_then(|alias| alias.get(template))
+            .map_or(template, String::as_str)
+            .to_owned()
+    }
+
+    /// Whether any template has defaults of its own
+    pub fn has_template_defaults(&self) -> bool {
+        self.templates
+            .as_ref()
+            .is_some_and(|templates| !templates.is_empty())
+    }
+
+    /// The URL of a new repository named `project_name`, from `remote_url` or under the configured
+    /// GitHub or GitLab user
+    pub fn suggested_remote(&self, project_name: &str) -> Option<String> {
+        if let Some(url) = &self.remote_url {
+            Some(url.replace("{name}", project_name))
+        } else if let Some(user) = &self.github_user {
+            Some(format!("git@github.com:{user}/{project_name}.git"))
+        } else {
+            self.gitlab_user
+                .as_ref()
+                .map(|user| format!("git@gitlab.com:{user}/{project_name}.git"))
+        }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.toml"))
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+        .map(|dir| dir.join("getflake"))
+}
+
+/// The settings given as `GETFLAKE_*` environment variables, by their name in the config file
+///
+/// Values are read as TOML (`true`, `3`, `["rust", "go"]`, `{ work = "github:me/templates" }`),
+/// or as they are for settings that are strings, so `GETFLAKE_TEMPLATE=rust` needs no quotes.
+fn env_overrides() -> Result<Table> {
+    let mut overrides = Table::new();
+    for (name, raw) in env::vars_os() {
+        let (Some(name), Some(raw)) = (name.to_str(), raw.to_str()) else {
+            continue;
+        };
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        // A getflake run from a hook sees the hook's variables too
+        if name.starts_with(hooks::ENV_PREFIX) {
+            continue;
+        }
+        let key = key.to_lowercase();
+
+        let parsed = toml::from_str::<Table>(&format!("value = {raw}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"));
+        let mut error = None;
+        let value = parsed
+            .into_iter()
+            .chain([Value::String(raw.to_owned())])
+            .find(|value| {
+                let setting = Table::from_iter([(key.clone(), value.clone())]);
+                match Value::Table(setting).try_into::<Config>() {
+                    Ok(_) => true,
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        false
+                    }
+                }
+            });
+        let Some(value) = value else {
+            // Other tools' variables may well share the prefix, so they're not worth failing for
+            if error
+                .as_ref()
+                .is_some_and(|e| e.message().starts_with("unknown field"))
+            {
+                eprintln!("{WARNING} Ignoring {name}, since getflake has no such setting");
+                continue;
+            }
+            return Err(GetflakeError::InvalidEnvironment {
+                name: name.to_owned(),
+                message: error.map(|e| e.to_string()).unwrap_or_default(),
+            });
+        };
+        overrides.insert(key, value);
+    }
+    Ok(overrides)
+}
@@ -0,0 +1,80 @@
+//! Comparing a project with the template it was generated from
+
+use std::{fs, io, path::Path};
+
+use similar::TextDiff;
+
+use crate::{
+    cache,
+    colors::{GREEN, RED, RESET},
+    error::Result,
+    icons::{GENERATE, UP_TO_DATE},
+    provenance::Provenance,
+    render::{self, render},
+    say,
+    templates::{pin, Source},
+};
+
+/// Prints a unified diff from the template the project in `directory` was generated from to the
+/// project, showing how it was customized
+///
+/// Only the template's files are compared, since build outputs and the like would drown out the
+/// rest. Returns whether there are any differences.
+pub fn diff(directory: &Path) -> Result<bool> {
+    let provenance = Provenance::load(directory)?;
+    let url = match &provenance.rev {
+        Some(rev) => pin(&provenance.source, rev),
+        None => provenance.source.clone(),
+    };
+
+    say!(
+        "{GENERATE} Generating {GREEN}{0}{RESET} from {url}...",
+        provenance.template
+    );
+    let source = Source::new(url, cache::DEFAULT_TTL, false);
+    let template = render(&source, &provenance.template, &provenance.variables)?;
+
+    let mut changed = false;
+    for file in render::files(template.path()) {
+        let file_name = file.to_string_lossy();
+        let template_file = template.path().join(&file);
+        let project_file = directory.join(&file);
+
+        let old = fs::read(&template_file)?;
+        let (new, new_name) = match fs::read(&project_file) {
+            Ok(new) => (new, format!("b/{file_name}")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (Vec::new(), "/dev/null".to_owned()),
+            Err(e) => return Err(e.into()),
+        };
+        if old == new {
+            continue;
+        }
+        changed = true;
+        print_unified(&format!("a/{file_name}"), &new_name, old, new);
+    }
+
+    if !changed {
+        say!("{UP_TO_DATE} The project has the same files as the template");
+    }
+
+    Ok(changed)
+}
+
+/// Prints a unified diff from `old` to `new`, which are named `old_name` and `new_name` in its
+/// header (`/dev/null` for a file that doesn't exist)
+pub fn print_unified(old_name: &str, new_name: &str, old: Vec<u8>, new: Vec<u8>) {
+    println!("--- {old_name}");
+    println!("+++ {new_name}");
+    let (Ok(old), Ok(new)) = (String::from_utf8(old), String::from_utf8(new)) else {
+        println!("Binary files differ");
+        return;
+    };
+    let text_diff = TextDiff::from_lines(&old, &new);
+    for line in text_diff.unified_diff().to_string().lines() {
+        match line.chars().next() {
+            Some('+') => println!("{GREEN}{line}{RESET}"),
+            Some('-') => println!("{RED}{line}{RESET}"),
+            _ => println!("{line}"),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Checking that everything getflake relies on is in place
+
+use std::{
+    fs,
+    path::Path,
+    process::{self, Command},
+};
+
+use crate::{
+    colors::{GREEN, RESET},
+    error::INSTALL_NIX,
+    icons::{DOCTOR, DONE, ERROR, HINT, OK, WARNING},
+    output::RunLogged,
+    plugin, say,
+    templates::Source,
+};
+
+/// Checks the environment getflake runs in, printing how to fix what's wrong
+///
+/// Returns whether every check passed.
+pub fn doctor(source: &Source, directory: &Path) -> bool {
+    say!("\n{DOCTOR} Checking your environment...");
+
+    let checks = [
+        check_nix(),
+        check_flakes(),
+        check_git(),
+        check_source(source),
+        check_writable(directory),
+    ];
+    let healthy = checks.iter().all(|passed| *passed);
+
+    let plugins = plugin::list();
+    if !plugins.is_empty() {
+        say!("- {OK} Plugins on the PATH: {0}", plugins.join(", "));
+    }
+
+    if healthy {
+        say!("\n{DONE} Everything is {GREEN}ready{RESET}");
+    } else {
+        say!("\n{WARNING} Some checks failed, see above for how to fix them");
+    }
+
+    healthy
+}
+
+fn check_nix() -> bool {
+    match version("nix") {
+        Some(version) => {
+            say!("- {OK} Nix is installed ({version})");
+            true
+        }
+        None => {
+            eprintln!("- {ERROR}Nix is not installed, or it isn't in PATH");
+            eprintln!("  {HINT} {INSTALL_NIX}");
+            false
+        }
+    }
+}
+
+fn check_flakes() -> bool {
+    let output = Command::new("nix")
+        .args([
+            "--extra-experimental-features",
+            "nix-command",
+            "config",
+            "show",
+            "experimental-features",
+        ])
+        .run_logged();
+    let features = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => {
+            eprintln!("- {WARNING} Unable to tell whether flakes are enabled");
+            return true;
+        }
+    };
+
+    let enabled = |feature: &str| features.split_whitespace().any(|f| f == feature);
+    if enabled("nix-command") && enabled("flakes") {
+        say!("- {OK} Flakes are enabled");
+    } else {
+        // getflake enables them for its own commands, so this only matters for the projects
+        eprintln!("- {WARNING} Flakes aren't enabled, so nix won't build the generated projects");
+        eprintln!("  {HINT} Add {GREEN}experimental-features = nix-command flakes{RESET} to ~/.config/nix/nix.conf");
+    }
+    true
+}
+
+fn check_git() -> bool {
+    match version("git") {
+        Some(version) => {
+            say!("- {OK} Git is installed ({version})");
+            true
+        }
+        None => {
+            eprintln!("- {ERROR}Git is not installed, or it isn't in PATH");
+            eprintln!(
+                "  {HINT} Install it with your distribution's package manager, or pass --no-git"
+            );
+            false
+        }
+    }
+}
+
+fn check_source(source: &Source) -> bool {
+    match source.resolved_rev() {
+        Ok(_) => {
+            say!("- {OK} The templates at {0} can be fetched", source.url);
+            true
+        }
+        Err(e) => {
+            eprintln!(
+                "- {ERROR}Unable to fetch the templates at {0}\n{e}",
+                source.url
+            );
+            eprintln!("  {HINT} Check your network connection, and the flake reference passed to --source or in the config file");
+            false
+        }
+    }
+}
+
+fn check_writable(directory: &Path) -> bool {
+    let path = directory.join(format!(".getflake-doctor-{0}", process::id()));
+    match fs::write(&path, "") {
+        Ok(()) => {
+            let _ = fs::remove_file(&path);
+            say!("- {OK} Projects can be created in {0}", directory.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("- {ERROR}Unable to write to {0}: {e}", directory.display());
+            eprintln!("  {HINT} Run getflake from a folder you have write permission to");
+            false
+        }
+    }
+}
+
+/// Returns the output of `program --version`, if it can be run
+fn version(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").run_logged().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
@@ -0,0 +1,49 @@
+use crate::templates::is_language;
+
+/// Settings for every file, and for the files whose conventions differ
+const COMMON: &[&str] = &[
+    "root = true",
+    "",
+    "[*]",
+    "charset = utf-8",
+    "end_of_line = lf",
+    "insert_final_newline = true",
+    "trim_trailing_whitespace = true",
+    "indent_style = space",
+    "indent_size = 2",
+    "",
+    "[*.md]",
+    "trim_trailing_whitespace = false",
+    "",
+    "[Makefile]",
+    "indent_style = tab",
+];
+
+/// Language-specific sections, keyed by template name (`rust` also covers `rust-*` variants)
+///
+/// The languages indenting with 2 spaces, like JavaScript and Nix, go by the `[*]` section.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["[*.rs]", "indent_size = 4"]),
+    ("python", &["[*.py]", "indent_size = 4"]),
+    ("go", &["[*.go]", "indent_style = tab"]),
+    ("c", &["[*.{c,h}]", "indent_size = 4"]),
+    ("cpp", &["[*.{cpp,hpp,cc,h}]", "indent_size = 4"]),
+    ("java", &["[*.java]", "indent_size = 4"]),
+    ("kotlin", &["[*.{kt,kts}]", "indent_size = 4"]),
+    ("zig", &["[*.zig]", "indent_size = 4"]),
+];
+
+/// Returns the contents of a `.editorconfig` suited to the language of `template`
+pub fn for_template(template: &str) -> String {
+    let mut lines = COMMON.to_vec();
+
+    if let Some((_, section)) = LANGUAGES
+        .iter()
+        .find(|(language, _)| is_language(template, language))
+    {
+        lines.push("");
+        lines.extend_from_slice(section);
+    }
+
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,187 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::{icons::HINT, interrupt, retry};
+
+/// How to install nix, shown wherever it's missing
+pub const INSTALL_NIX: &str = "Install it with the Determinate Systems installer:
+     curl --proto '=https' --tlsv1.2 -sSf -L https://install.determinate.systems/nix | sh -s -- install
+   Or with your distribution's package, e.g. `apt install nix-bin`, `dnf install nix` or `pacman -S nix`,
+   then open a new shell so nix is in PATH";
+
+/// How to get private repositories fetched, shown when they refuse to be
+const AUTHENTICATION_HINT: &str =
+    "For git+ssh:// sources, check that your SSH key is loaded with `ssh-add -l`.
+   For private GitHub or GitLab repositories, set a token for the host in `access_tokens` in the
+   config file, or in nix's own `access-tokens` setting";
+
+#[derive(Error, Debug)]
+pub enum GetflakeError {
+    #[error("Nix is not installed, or it isn't in PATH\n{} {}", HINT, INSTALL_NIX)]
+    NixNotFound,
+
+    #[error("Failed to fetch templates from {url}: {message}")]
+    FlakeShowFailed { url: String, message: String },
+
+    #[error(
+        "{url} needs authentication: {message}\n{} {}",
+        HINT,
+        AUTHENTICATION_HINT
+    )]
+    AuthenticationRequired { url: String, message: String },
+
+    #[error("This needs nix, which --no-nix turned off")]
+    NixDisabled,
+
+    #[error(
+        "{what} isn't available offline, as it wasn't downloaded before\n{} Run getflake without --offline once to download it",
+        HINT
+    )]
+    NotAvailableOffline { what: String },
+
+    #[error("Only GitHub repositories can be fetched without nix, not {0}")]
+    NotOnGitHub(String),
+
+    #[error("Unknown template: {0}")]
+    TemplateNotFound(String),
+
+    #[error("Aborted")]
+    Aborted,
+
+    /// Asked to go back to the previous question, which the wizard handles
+    #[error("There's no question to go back to")]
+    Back,
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("`{command}` failed: {message}")]
+    CommandFailed { command: String, message: String },
+
+    #[error(
+        "Failed to create the project from the {template} template, `{command}` failed:\n{message}"
+    )]
+    ScaffoldFailed {
+        template: String,
+        command: String,
+        message: String,
+    },
+
+    #[error("Failed to finish setting up the project: {0}")]
+    PostProcessFailed(#[source] Box<GetflakeError>),
+
+    #[error("Failed to render {path}: {message}")]
+    RenderFailed { path: String, message: String },
+
+    #[error("The script {path} failed: {message}")]
+    ScriptFailed { path: String, message: String },
+
+    #[error("Invalid config file {path}: {message}")]
+    InvalidConfig { path: String, message: String },
+
+    #[error("Invalid environment variable {name}: {message}")]
+    InvalidEnvironment { name: String, message: String },
+
+    #[error("Invalid answers file {path}: {message}")]
+    InvalidAnswers { path: String, message: String },
+
+    #[error("Invalid template manifest {path}: {message}")]
+    InvalidManifest { path: String, message: String },
+
+    #[error("Invalid {path}: {message}")]
+    InvalidProvenance { path: String, message: String },
+
+    #[error("Unknown revision of {0}, so the project can't be upgraded")]
+    UnknownRevision(String),
+
+    #[error("{0}")]
+    Io(#[from] io::Error),
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Prompt(#[from] dialoguer::Error),
+}
+
+impl GetflakeError {
+    /// The exit code of the process when it fails with this error
+    ///
+    /// | Code | Failure                                         |
+    /// | ---- | ----------------------------------------------- |
+    /// | 1    | Anything else                                   |
+    /// | 2    | Invalid input: arguments, answers, config files |
+    /// | 3    | Nix is not installed                            |
+    /// | 4    | Fetching the templates failed                   |
+    /// | 5    | Creating the project from the template failed   |
+    /// | 6    | Setting up the project afterwards failed        |
+    /// | 130  | Interrupted with Ctrl-C                         |
+    pub fn exit_code(&self) -> u8 {
+        if self.is_interrupted() {
+            return interrupt::EXIT_CODE;
+        }
+        match self {
+            Self::InvalidInput(_)
+            | Self::InvalidConfig { .. }
+            | Self::InvalidEnvironment { .. }
+            | Self::InvalidAnswers { .. }
+            | Self::InvalidManifest { .. }
+            | Self::InvalidProvenance { .. }
+            | Self::TemplateNotFound(_)
+            | Self::NixDisabled
+            | Self::NotOnGitHub(_) => 2,
+            Self::NixNotFound => 3,
+            Self::FlakeShowFailed { .. }
+            | Self::AuthenticationRequired { .. }
+            | Self::NotAvailableOffline { .. }
+            | Self::UnknownRevision(_) => 4,
+            Self::ScaffoldFailed { .. } => 5,
+            Self::PostProcessFailed(_) => 6,
+            Self::Aborted
+            | Self::Back
+            | Self::CommandFailed { .. }
+            | Self::RenderFailed { .. }
+            | Self::ScriptFailed { .. }
+            | Self::Io(_)
+            | Self::Json(_)
+            | Self::Prompt(_) => 1,
+        }
+    }
+
+    /// Whether Ctrl-C was pressed at a prompt or in the `--tui` screen, which read it as a key
+    /// rather than a signal
+    pub fn is_interrupted(&self) -> bool {
+        matches!(
+            self,
+            Self::Prompt(dialoguer::Error::IO(e)) | Self::Io(e)
+                if e.kind() == io::ErrorKind::Interrupted
+        )
+    }
+
+    /// Whether trying again may succeed, because the failure came from the network
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::FlakeShowFailed { message, .. } | Self::CommandFailed { message, .. } => {
+                retry::is_transient_failure(message)
+            }
+            Self::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+
+    /// Tells apart nix not being installed from other failures to run it
+    pub fn from_nix_io(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::NixNotFound,
+            _ => Self::Io(e),
+        }
+    }
+}
+
+pub type Result<T, E = GetflakeError> = std::result::Result<T, E>;
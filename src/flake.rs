@@ -0,0 +1,368 @@
+//! Merging the flake of a template into the one a project already has
+//!
+//! Nix isn't parsed here, only scanned: strings and comments are skipped and brackets matched,
+//! which is enough to find the `inputs` and `outputs` of a flake. The template's inputs the
+//! project lacks are added to its own, and both `outputs` functions are called and their results
+//! merged, the project's winning where both define the same output.
+
+use std::ops::Range;
+
+/// A binding of an attribute set, like `nixpkgs.url = "github:NixOS/nixpkgs";`
+#[derive(Debug)]
+struct Binding {
+    /// The attribute path, as it's written
+    path: String,
+    /// From the start of the path to the `;`, included
+    range: Range<usize>,
+    /// The value, without the `;`
+    value: Range<usize>,
+}
+
+/// Returns `project` with the inputs and outputs of `template` merged into it, or `None` if
+/// either doesn't look like a flake
+///
+/// `template_name` is only used in the comment saying what was merged.
+pub fn merge(project: &str, template: &str, template_name: &str) -> Option<String> {
+    let (project_bindings, _) = bindings(project, top_level(project)?)?;
+    let (template_bindings, _) = bindings(template, top_level(template)?)?;
+
+    let project_outputs = project_bindings
+        .iter()
+        .find(|binding| binding.path == "outputs")?;
+    let template_outputs = template_bindings
+        .iter()
+        .find(|binding| binding.path == "outputs")?;
+
+    // The edits are made from the end, so the earlier ranges still hold
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+
+    let project_inputs = inputs(project, &project_bindings)?;
+    let missing: Vec<String> = inputs(template, &template_bindings)?
+        .into_iter()
+        .filter(|(name, _)| !project_inputs.iter().any(|(other, _)| other == name))
+        .map(|(_, text)| text)
+        .collect();
+    if !missing.is_empty() {
+        match project_bindings
+            .iter()
+            .find(|binding| binding.path == "inputs")
+        {
+            // Added at the end of the `inputs = { ... };` set
+            Some(binding) => {
+                let (_, close) = bindings(project, attrset_start(project, &binding.value)?)?;
+                let added: String = missing
+                    .iter()
+                    .map(|text| format!("  {text};\n  "))
+                    .collect();
+                edits.push((close..close, added));
+            }
+            // Or as `inputs.<name>` bindings, before the outputs
+            None => {
+                let added: String = missing
+                    .iter()
+                    .map(|text| format!("inputs.{text};\n  "))
+                    .collect();
+                edits.push((
+                    project_outputs.range.start..project_outputs.range.start,
+                    added,
+                ));
+            }
+        }
+    }
+
+    let call = |name: &str, outputs: &str| {
+        if accepts_all_inputs(outputs) {
+            format!("{name} inputs")
+        } else {
+            format!("{name} (builtins.intersectAttrs (builtins.functionArgs {name}) inputs)")
+        }
+    };
+    let project_value = &project[project_outputs.value.clone()];
+    let template_value = &template[template_outputs.value.clone()];
+    let merged = format!(
+        "outputs =
+    inputs:
+    let
+      # Merged by getflake: the outputs of the {template_name} template are added to the
+      # project's own, which win where both have the same one
+      merge =
+        ours: theirs:
+        theirs
+        // builtins.mapAttrs (
+          name: value:
+          if builtins.isAttrs value && builtins.isAttrs (theirs.${{name}} or null) && !(value ? type) then
+            merge value theirs.${{name}}
+          else
+            value
+        ) ours;
+      project = {0};
+      template = {1};
+    in
+    merge ({2}) ({3});",
+        project_value.trim(),
+        template_value.trim(),
+        call("project", project_value),
+        call("template", template_value),
+    );
+    edits.push((project_outputs.range.clone(), merged));
+
+    // The inputs added before the outputs start where they do, so the outputs go first
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse((range.start, range.end)));
+    let mut merged = project.to_owned();
+    for (range, text) in edits {
+        merged.replace_range(range, &text);
+    }
+    Some(merged)
+}
+
+/// The inputs a flake declares, by name, with their bindings as they'd be written inside
+/// `inputs = { ... };`
+fn inputs(source: &str, top_level: &[Binding]) -> Option<Vec<(String, String)>> {
+    let mut inputs = Vec::new();
+    for binding in top_level {
+        if binding.path == "inputs" {
+            let (nested, _) = bindings(source, attrset_start(source, &binding.value)?)?;
+            for input in nested {
+                let name = input.path.split('.').next().unwrap_or_default().to_owned();
+                let text = source[input.range.start..input.range.end - 1]
+                    .trim()
+                    .to_owned();
+                inputs.push((name, text));
+            }
+        } else if let Some(path) = binding.path.strip_prefix("inputs.") {
+            let name = path.split('.').next().unwrap_or_default().to_owned();
+            let text = format!("{path} = {0}", source[binding.value.clone()].trim());
+            inputs.push((name, text));
+        }
+    }
+    Some(inputs)
+}
+
+/// Whether the `outputs` function takes any argument, rather than only the inputs it names
+fn accepts_all_inputs(outputs: &str) -> bool {
+    let outputs = outputs.trim_start();
+    let pattern_start = match outputs.find(['{', ':']) {
+        Some(index) if outputs.as_bytes()[index] == b'{' => index,
+        // `inputs: ...`
+        _ => return true,
+    };
+    match scan_to(outputs, pattern_start + 1, b'}') {
+        Some(close) => outputs[pattern_start..close].contains("..."),
+        None => false,
+    }
+}
+
+/// Index of the `{` the flake's attribute set starts with
+fn top_level(source: &str) -> Option<usize> {
+    let start = skip_trivia(source, 0);
+    (source.as_bytes().get(start) == Some(&b'{')).then_some(start)
+}
+
+/// Index of the `{` of the attribute set `value` is, if it's one
+fn attrset_start(source: &str, value: &Range<usize>) -> Option<usize> {
+    let start = skip_trivia(source, value.start);
+    (source.as_bytes().get(start) == Some(&b'{')).then_some(start)
+}
+
+/// The bindings of the attribute set whose `{` is at `open`, and the index of its `}`
+fn bindings(source: &str, open: usize) -> Option<(Vec<Binding>, usize)> {
+    let bytes = source.as_bytes();
+    let mut bindings = Vec::new();
+    let mut index = open + 1;
+    loop {
+        index = skip_trivia(source, index);
+        match bytes.get(index)? {
+            b'}' => return Some((bindings, index)),
+            _ => {
+                let start = index;
+                if source[start..].starts_with("inherit") {
+                    index = scan_to(source, start, b';')? + 1;
+                    continue;
+                }
+                let equals = scan_to(source, start, b'=')?;
+                let end = scan_to(source, equals + 1, b';')?;
+                bindings.push(Binding {
+                    path: source[start..equals].split_whitespace().collect::<String>(),
+                    range: start..end + 1,
+                    value: equals + 1..end,
+                });
+                index = end + 1;
+            }
+        }
+    }
+}
+
+/// Skips whitespace and comments from `index`
+fn skip_trivia(source: &str, mut index: usize) -> usize {
+    let bytes = source.as_bytes();
+    loop {
+        match bytes.get(index) {
+            Some(byte) if byte.is_ascii_whitespace() => index += 1,
+            Some(b'#') => {
+                index = source[index..]
+                    .find('\n')
+                    .map_or(source.len(), |newline| index + newline + 1)
+            }
+            Some(b'/') if bytes.get(index + 1) == Some(&b'*') => {
+                index = source[index + 2..]
+                    .find("*/")
+                    .map_or(source.len(), |end| index + 2 + end + 2)
+            }
+            _ => return index,
+        }
+    }
+}
+
+/// Returns the index of the first `stop` from `index` that isn't nested in brackets, a string or
+/// a comment, nor a `;` ending the bindings of a `let` or a `with` or `assert`
+fn scan_to(source: &str, mut index: usize, stop: u8) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut lets = 0usize;
+    let mut semicolons_owed = 0usize;
+    while let Some(&byte) = bytes.get(index) {
+        if depth == 0 && byte == stop {
+            match (stop, semicolons_owed, lets) {
+                (b';', 1.., _) => semicolons_owed -= 1,
+                (b';', 0, 1..) => {}
+                _ => return Some(index),
+            }
+            index += 1;
+            continue;
+        }
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                let length = source[index..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                    .unwrap_or(source.len() - index);
+                match (depth, &source[index..index + length]) {
+                    (0, "let") => lets += 1,
+                    (0, "in") => lets = lets.saturating_sub(1),
+                    (0, "with" | "assert") => semicolons_owed += 1,
+                    _ => {}
+                }
+                index += length;
+                continue;
+            }
+            b'{' | b'[' | b'(' => depth += 1,
+            b'}' | b']' | b')' => depth = depth.checked_sub(1)?,
+            b'"' => index = skip_string(source, index + 1)?,
+            b'\'' if bytes.get(index + 1) == Some(&b'\'') => {
+                index = skip_indented_string(source, index + 2)?
+            }
+            b'#' | b'/' => {
+                let after = skip_trivia(source, index);
+                if after > index {
+                    index = after;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Returns the index of the `"` closing the string whose contents start at `index`
+fn skip_string(source: &str, mut index: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    loop {
+        match bytes.get(index)? {
+            b'\\' => index += 2,
+            b'"' => return Some(index),
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                index = scan_to(source, index + 2, b'}')? + 1
+            }
+            _ => index += 1,
+        }
+    }
+}
+
+/// Returns the index of the last `'` closing the `''` string whose contents start at `index`
+fn skip_indented_string(source: &str, mut index: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    loop {
+        match bytes.get(index)? {
+            b'\'' if bytes.get(index + 1) == Some(&b'\'') => {
+                // `'''`, `''$` and `''\` are escapes
+                match bytes.get(index + 2) {
+                    Some(b'\'' | b'$') => index += 3,
+                    Some(b'\\') => index += 4,
+                    _ => return Some(index + 1),
+                }
+            }
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                index = scan_to(source, index + 2, b'}')? + 1
+            }
+            _ => index += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"{
+  description = "A project";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+  };
+
+  outputs = { self, nixpkgs }: {
+    packages.x86_64-linux.default = nixpkgs.legacyPackages.x86_64-linux.hello;
+  };
+}
+"#;
+
+    const TEMPLATE: &str = r#"{
+  description = "A template";
+
+  inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+  inputs.flake-utils.url = "github:numtide/flake-utils";
+
+  outputs = { nixpkgs, flake-utils, ... }: {
+    # "outputs = {" in a comment isn't a binding
+    devShells.x86_64-linux.default = { };
+  };
+}
+"#;
+
+    #[test]
+    fn merge_adds_the_missing_inputs() {
+        let merged = merge(PROJECT, TEMPLATE, "rust").unwrap();
+        assert!(merged.contains(r#"flake-utils.url = "github:numtide/flake-utils";"#));
+        // The project's own nixpkgs wins
+        assert!(merged.contains("nixos-unstable"));
+        assert!(!merged.contains("nixos-24.05"));
+    }
+
+    #[test]
+    fn merge_calls_both_outputs() {
+        let merged = merge(PROJECT, TEMPLATE, "rust").unwrap();
+        assert!(merged.contains("outputs of the rust template"));
+        assert!(merged.contains("packages.x86_64-linux.default"));
+        assert!(merged.contains("devShells.x86_64-linux.default"));
+        // The project's outputs only name some inputs, the template's take them all
+        assert!(merged.contains(
+            "merge (project (builtins.intersectAttrs (builtins.functionArgs project) inputs)) (template inputs);"
+        ));
+        assert!(merged.starts_with("{\n  description = \"A project\";"));
+    }
+
+    #[test]
+    fn merge_adds_inputs_without_an_inputs_set() {
+        let project = "{\n  outputs = { self }: { };\n}\n";
+        let merged = merge(project, TEMPLATE, "rust").unwrap();
+        assert!(merged.contains(r#"inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";"#));
+        assert!(merged.contains(r#"inputs.flake-utils.url = "github:numtide/flake-utils";"#));
+    }
+
+    #[test]
+    fn merge_needs_outputs() {
+        assert_eq!(merge("{ description = \"x\"; }", TEMPLATE, "rust"), None);
+        assert_eq!(merge("not a flake", TEMPLATE, "rust"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::templates::is_language;
+
+/// Attributes every flake project wants
+const NIX: &[&str] = &[
+    "# Line endings are normalized to LF",
+    "* text=auto eol=lf",
+    "",
+    "# Lock files are collapsed in diffs, and left out of GitHub's language stats",
+    "flake.lock linguist-generated=true -diff",
+];
+
+/// Language-specific attributes, keyed by template name (`rust` also covers `rust-*` variants)
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["Cargo.lock linguist-generated=true -diff"]),
+    (
+        "python",
+        &[
+            "poetry.lock linguist-generated=true -diff",
+            "uv.lock linguist-generated=true -diff",
+        ],
+    ),
+    ("go", &["go.sum linguist-generated=true -diff"]),
+    (
+        "java",
+        &[
+            "gradlew text eol=lf",
+            "*.bat text eol=crlf",
+            "gradle/wrapper/** linguist-vendored",
+        ],
+    ),
+    (
+        "kotlin",
+        &[
+            "gradlew text eol=lf",
+            "*.bat text eol=crlf",
+            "gradle/wrapper/** linguist-vendored",
+        ],
+    ),
+    (
+        "node",
+        &[
+            "package-lock.json linguist-generated=true -diff",
+            "pnpm-lock.yaml linguist-generated=true -diff",
+            "yarn.lock linguist-generated=true -diff",
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            "package-lock.json linguist-generated=true -diff",
+            "pnpm-lock.yaml linguist-generated=true -diff",
+            "yarn.lock linguist-generated=true -diff",
+        ],
+    ),
+    (
+        "typescript",
+        &[
+            "package-lock.json linguist-generated=true -diff",
+            "pnpm-lock.yaml linguist-generated=true -diff",
+            "yarn.lock linguist-generated=true -diff",
+        ],
+    ),
+];
+
+/// Returns the contents of a `.gitattributes` suited to the language of `template`
+pub fn for_template(template: &str) -> String {
+    let mut lines = NIX.to_vec();
+
+    if let Some((_, entries)) = LANGUAGES
+        .iter()
+        .find(|(language, _)| is_language(template, language))
+    {
+        lines.extend_from_slice(entries);
+    }
+
+    lines.join("\n") + "\n"
+}
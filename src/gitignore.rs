@@ -0,0 +1,41 @@
+use crate::templates::is_language;
+
+/// Entries every flake project wants ignored
+const NIX: &[&str] = &["# Nix", "result", "result-*", ".direnv/"];
+
+/// Language-specific entries, keyed by template name (`rust` also covers `rust-*` variants)
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["# Rust", "target/"]),
+    (
+        "python",
+        &["# Python", "__pycache__/", "*.py[cod]", ".venv/", "dist/"],
+    ),
+    ("go", &["# Go", "/bin/", "*.test"]),
+    ("c", &["# C", "build/", "*.o"]),
+    ("cpp", &["# C++", "build/", "*.o"]),
+    (
+        "java",
+        &["# Java", "build/", "target/", ".gradle/", "*.class"],
+    ),
+    ("kotlin", &["# Kotlin", "build/", ".gradle/", "*.class"]),
+    ("node", &["# Node", "node_modules/", "dist/"]),
+    ("javascript", &["# JavaScript", "node_modules/", "dist/"]),
+    ("typescript", &["# TypeScript", "node_modules/", "dist/"]),
+    ("haskell", &["# Haskell", "dist-newstyle/"]),
+    ("zig", &["# Zig", ".zig-cache/", "zig-cache/", "zig-out/"]),
+];
+
+/// Returns the contents of a `.gitignore` suited to the language of `template`
+pub fn for_template(template: &str) -> String {
+    let mut lines = NIX.to_vec();
+
+    if let Some((_, entries)) = LANGUAGES
+        .iter()
+        .find(|(language, _)| is_language(template, language))
+    {
+        lines.push("");
+        lines.extend_from_slice(entries);
+    }
+
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,93 @@
+//! The projects scaffolded so far, so `getflake history rerun` can scaffold one again
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    answers::Answers, config::cache_dir, error::Result, postprocess::civil_date, scaffold::Report,
+};
+
+/// A project getflake scaffolded successfully
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    /// Number the entry is rerun with, counting from 1
+    pub id: u32,
+    /// When the project was created, in seconds since 1970-01-01
+    pub timestamp: u64,
+    /// Flake reference of the template repository
+    pub source: String,
+    pub template: String,
+    /// Absolute path of the folder the project was created in
+    pub directory: PathBuf,
+    /// Everything the project was scaffolded with, name and variables included
+    pub answers: Answers,
+}
+
+impl Entry {
+    /// The day the project was created, as `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_date(self.timestamp);
+        format!("{year}-{month:02}-{day:02}")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryFile {
+    entries: Vec<Entry>,
+}
+
+pub fn path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("history.json"))
+}
+
+/// Returns every entry, oldest first
+pub fn load() -> Vec<Entry> {
+    read().unwrap_or_default().entries
+}
+
+/// Returns the entry numbered `id`
+pub fn find(id: u32) -> Option<Entry> {
+    load().into_iter().find(|entry| entry.id == id)
+}
+
+/// Records the project described by `report`, scaffolded with `answers`, and returns its id
+pub fn record(report: &Report, answers: &Answers) -> Result<u32> {
+    let Some(path) = path() else {
+        return Ok(0);
+    };
+
+    let mut history_file = read().unwrap_or_default();
+    let id = history_file.entries.last().map_or(1, |entry| entry.id + 1);
+    history_file.entries.push(Entry {
+        id,
+        timestamp: now(),
+        source: report.source.clone(),
+        template: report.template.clone(),
+        directory: fs::canonicalize(&report.path).unwrap_or_else(|_| report.path.clone()),
+        answers: answers.clone(),
+    });
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&history_file)?)?;
+
+    Ok(id)
+}
+
+fn read() -> Option<HistoryFile> {
+    let content = fs::read_to_string(path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
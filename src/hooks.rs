@@ -0,0 +1,75 @@
+//! Hooks: commands run around scaffolding a project, declared in the config file or in a
+//! template's `getflake.toml`
+//!
+//! ```toml
+//! [hooks]
+//! pre = ["./check-name.sh"]
+//! post = ["cargo generate-lockfile", "npm install"]
+//! ```
+//!
+//! Hooks get the project's details as environment variables: each placeholder in upper case,
+//! prefixed with `GETFLAKE_HOOK_` (e.g. `GETFLAKE_HOOK_PROJECT_NAME`), along with
+//! `GETFLAKE_HOOK_TEMPLATE` and `GETFLAKE_HOOK_DIRECTORY`. The prefix isn't just `GETFLAKE_`, as
+//! those variables override the config file of a getflake run from the hook.
+
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{GetflakeError, Result},
+    output::RunLogged,
+    progress,
+};
+
+/// Prefix of the environment variables hooks get, e.g. `GETFLAKE_HOOK_PROJECT_NAME`
+pub const ENV_PREFIX: &str = "GETFLAKE_HOOK_";
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// Commands run in the current folder before the template is copied, which stop getflake
+    /// when they fail
+    pub pre: Vec<String>,
+
+    /// Commands run in the new project's folder once it's scaffolded, before the initial commit
+    pub post: Vec<String>,
+}
+
+/// Runs `hook` with the shell in `directory`, with `variables` in its environment as
+/// `GETFLAKE_HOOK_<NAME>`
+pub fn run(hook: &str, directory: &Path, variables: &BTreeMap<String, String>) -> Result<()> {
+    let mut command = shell(hook);
+    command.current_dir(directory).envs(
+        variables
+            .iter()
+            .map(|(name, value)| (format!("{ENV_PREFIX}{0}", name.to_uppercase()), value)),
+    );
+    let output = progress::spin(&format!("Running {hook}"), || command.run_logged())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Err(GetflakeError::CommandFailed {
+            command: hook.to_owned(),
+            message: if stderr.is_empty() {
+                output.status.to_string()
+            } else {
+                stderr
+            },
+        });
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook);
+    command
+}
+
+#[cfg(not(unix))]
+fn shell(hook: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(hook);
+    command
+}
@@ -0,0 +1,98 @@
+//! The emoji messages start with
+//!
+//! They print as plain ASCII tags (e.g. `[git]`) with `--no-emoji`, for terminals without emoji
+//! and for log parsers.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub struct Icon {
+    emoji: &'static str,
+    ascii: &'static str,
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if enabled() { self.emoji } else { self.ascii })
+    }
+}
+
+const fn icon(emoji: &'static str, ascii: &'static str) -> Icon {
+    Icon { emoji, ascii }
+}
+
+// Errors are directly followed by their message
+pub const ERROR: Icon = icon("❌", "[error] ");
+pub const OK: Icon = icon("✔️", "[ok]");
+pub const HINT: Icon = icon("👉", "[hint]");
+pub const WARNING: Icon = icon("⚠️", "[warning]");
+pub const DONE: Icon = icon("🎉", "[done]");
+pub const UP_TO_DATE: Icon = icon("✅", "[done]");
+
+pub const START: Icon = icon("🚀", "[start]");
+pub const SUMMARY: Icon = icon("🫵", "[summary]");
+pub const PLAN: Icon = icon("📋", "[plan]");
+pub const FETCH: Icon = icon("📥", "[fetch]");
+pub const PIN: Icon = icon("📌", "[rev]");
+pub const NIX: Icon = icon("❄️", "[nix]");
+pub const CREATED: Icon = icon("👑", "[created]");
+pub const REMOVE: Icon = icon("🗑️", "[remove]");
+pub const UPDATE: Icon = icon("🔀", "[update]");
+pub const GENERATE: Icon = icon("🧩", "[generate]");
+pub const GIT: Icon = icon("🔧", "[git]");
+pub const FORMAT: Icon = icon("🎨", "[format]");
+pub const CLEAN: Icon = icon("🧹", "[clean]");
+pub const COMMIT: Icon = icon("📸", "[commit]");
+pub const PUSH: Icon = icon("⬆️", "[push]");
+pub const DIRENV: Icon = icon("🌱", "[direnv]");
+pub const CI: Icon = icon("🤖", "[ci]");
+pub const CONTAINER: Icon = icon("🐳", "[devcontainer]");
+pub const HOOK: Icon = icon("🪝", "[hook]");
+pub const PROVENANCE: Icon = icon("📜", "[provenance]");
+pub const RECORD: Icon = icon("📼", "[record]");
+pub const WELCOME: Icon = icon("👋", "[welcome]");
+pub const OPEN: Icon = icon("🖥️", "[open]");
+pub const FAVORITE: Icon = icon("⭐", "[favorite]");
+pub const SOURCE: Icon = icon("🔗", "[source]");
+pub const HISTORY: Icon = icon("📜", "[history]");
+pub const UNDO: Icon = icon("↩️", "[undo]");
+pub const DOCTOR: Icon = icon("🩺", "[doctor]");
+pub const TEMPLATE: Icon = icon("📦", "[template]");
+pub const FOLDER: Icon = icon("📂", "[folder]");
+pub const SETUP: Icon = icon("⚙️", "[setup]");
+pub const MERGE: Icon = icon("🧬", "[merge]");
+pub const WORKSPACE: Icon = icon("🏘️", "[workspace]");
+
+// Questions
+pub const PICK: Icon = icon("👆", "[?]");
+pub const NEW_OR_INIT: Icon = icon("🤔", "[?]");
+pub const NAME: Icon = icon("📝", "[?]");
+pub const AUTHOR: Icon = icon("👤", "[?]");
+pub const EMAIL: Icon = icon("📧", "[?]");
+pub const DESCRIPTION: Icon = icon("💬", "[?]");
+pub const LICENSE: Icon = icon("⚖️", "[?]");
+pub const INIT_GIT: Icon = icon("💾", "[?]");
+pub const COMMIT_MESSAGE: Icon = icon("✏️", "[?]");
+pub const REMOTE: Icon = icon("🌐", "[?]");
+pub const README: Icon = icon("📄", "[?]");
+pub const ENVRC: Icon = icon("🌿", "[?]");
+pub const WORKFLOW: Icon = icon("🔁", "[?]");
+pub const DEVCONTAINER: Icon = icon("🚢", "[?]");
+pub const PRE_COMMIT: Icon = icon("🪝", "[?]");
+pub const VARIABLE: Icon = icon("🏷️", "[?]");
+pub const CONFIRM: Icon = icon("✅", "[?]");
+pub const TEMPLATES: Icon = icon("📚", "[?]");
+pub const MERGE_FLAKE: Icon = icon("🪢", "[?]");
+pub const CONFLICT: Icon = icon("🆚", "[?]");
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
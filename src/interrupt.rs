@@ -0,0 +1,99 @@
+//! What happens on Ctrl-C: the commands getflake is waiting on are killed, the project being
+//! generated is removed, and the process exits with status 130 like shells expect
+//!
+//! While a command the user interacts with runs in the foreground, like `nix develop` or their
+//! editor, Ctrl-C is left to it instead.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{self, Child, ExitStatus},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+};
+
+/// Exit status of a process interrupted with Ctrl-C (128 + SIGINT)
+pub const EXIT_CODE: u8 = 130;
+
+/// Commands started with [`output::RunLogged`](crate::output::RunLogged) that are still running
+static CHILDREN: Mutex<Vec<Child>> = Mutex::new(Vec::new());
+
+/// The folder being generated, which is only a partial project until it's moved into place
+static PARTIAL_OUTPUT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// How many commands the user interacts with are running, see [`in_foreground`]
+static FOREGROUND: AtomicUsize = AtomicUsize::new(0);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Installs the Ctrl-C handler, once at startup
+pub fn install() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        // The terminal sends SIGINT to the foreground command too, which decides what it means
+        if FOREGROUND.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        for child in lock(&CHILDREN).iter_mut() {
+            let _ = child.kill();
+        }
+        if let Some(directory) = lock(&PARTIAL_OUTPUT).take() {
+            let _ = fs::remove_dir_all(directory);
+        }
+        restore_terminal();
+        process::exit(EXIT_CODE.into());
+    })
+}
+
+/// Shows the cursor again, which prompts and spinners hide, and moves past their line
+pub fn restore_terminal() {
+    eprintln!("\x1b[?25h");
+}
+
+/// Keeps track of `child` until [`wait`] says it exited, so Ctrl-C kills it
+pub fn track(child: Child) -> u32 {
+    let id = child.id();
+    lock(&CHILDREN).push(child);
+    id
+}
+
+/// Checks whether the tracked child `id` exited, forgetting it if so
+pub fn try_wait(id: u32) -> std::io::Result<Option<ExitStatus>> {
+    let mut children = lock(&CHILDREN);
+    let Some(index) = children.iter().position(|child| child.id() == id) else {
+        return Ok(None);
+    };
+    let status = children[index].try_wait()?;
+    if status.is_some() {
+        // Already reaped by try_wait, so this returns right away
+        let _ = children.remove(index).wait();
+    }
+    Ok(status)
+}
+
+/// Kills the tracked child `id` and forgets it
+pub fn kill(id: u32) {
+    let mut children = lock(&CHILDREN);
+    if let Some(index) = children.iter().position(|child| child.id() == id) {
+        let mut child = children.remove(index);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Runs `command`, which starts a program the user interacts with (e.g. a shell or an editor),
+/// leaving Ctrl-C to that program until it returns
+pub fn in_foreground<T>(command: impl FnOnce() -> T) -> T {
+    FOREGROUND.fetch_add(1, Ordering::SeqCst);
+    let result = command();
+    FOREGROUND.fetch_sub(1, Ordering::SeqCst);
+    result
+}
+
+/// Sets the folder removed on Ctrl-C, or `None` once it's a complete project
+pub fn set_partial_output(directory: Option<PathBuf>) {
+    *lock(&PARTIAL_OUTPUT) = directory;
+}
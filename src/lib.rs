@@ -0,0 +1,64 @@
+//! Fetch Nix Flake Templates and scaffold projects from them
+//!
+//! The `getflake` binary is a wizard around this crate, which other tools can embed instead of
+//! shelling out to it:
+//!
+//! ```no_run
+//! use getflake::{cache, templates::Source, NewOrInit, Scaffolder};
+//!
+//! let source = Source::new(
+//!     "github:nulladmin1/nix-flake-templates".to_owned(),
+//!     cache::DEFAULT_TTL,
+//!     false,
+//! );
+//!
+//! Scaffolder::new(source, "rust", "my-project")
+//!     .new_or_init(NewOrInit::New)
+//!     .author("Jane Doe")
+//!     .init_git(true)
+//!     .run()?;
+//! # Ok::<(), getflake::GetflakeError>(())
+//! ```
+
+pub mod answers;
+pub mod authoring;
+pub mod cache;
+pub mod colors;
+pub mod component;
+pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod editorconfig;
+pub mod error;
+pub mod flake;
+pub mod gitattributes;
+pub mod gitignore;
+pub mod history;
+pub mod hooks;
+pub mod icons;
+pub mod interrupt;
+pub mod license;
+pub mod manifest;
+pub mod output;
+pub mod plugin;
+pub mod postprocess;
+pub mod progress;
+pub mod prompt;
+pub mod provenance;
+pub mod recent;
+pub mod render;
+pub mod retry;
+pub mod scaffold;
+pub mod script;
+pub mod tarball;
+pub mod templates;
+pub mod tree;
+pub mod undo;
+pub mod upgrade;
+pub mod validate;
+pub mod workspace;
+
+pub use error::{GetflakeError, Result};
+pub use scaffold::{
+    Ci, Conflict, ExistingDirectory, GitHubRepo, NewOrInit, Readme, Report, Scaffolder,
+};
@@ -0,0 +1,202 @@
+//! The licenses a project can be put under, and the files that mention them
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    error::{GetflakeError, Result},
+    output::RunLogged,
+    postprocess, templates,
+};
+
+/// Where the texts of the licenses not bundled with getflake are downloaded from
+const SPDX_TEXT_URL: &str = "https://raw.githubusercontent.com/spdx/license-list-data/main/text";
+
+/// A license getflake can write a `LICENSE` file for
+#[derive(Debug)]
+pub struct License {
+    /// SPDX identifier (e.g. `Apache-2.0`)
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Attribute of `lib.licenses` in nixpkgs
+    pub nix: &'static str,
+    /// Text with `<year>` and `<copyright holders>` to fill in, or `None` to download it
+    text: Option<&'static str>,
+}
+
+pub const LICENSES: &[License] = &[
+    License {
+        id: "MIT",
+        name: "MIT License",
+        nix: "mit",
+        text: Some(include_str!("../licenses/MIT")),
+    },
+    License {
+        id: "Apache-2.0",
+        name: "Apache License 2.0",
+        nix: "asl20",
+        text: None,
+    },
+    License {
+        id: "GPL-3.0-only",
+        name: "GNU General Public License v3.0 only",
+        nix: "gpl3Only",
+        text: None,
+    },
+    License {
+        id: "GPL-3.0-or-later",
+        name: "GNU General Public License v3.0 or later",
+        nix: "gpl3Plus",
+        text: None,
+    },
+    License {
+        id: "LGPL-3.0-only",
+        name: "GNU Lesser General Public License v3.0 only",
+        nix: "lgpl3Only",
+        text: None,
+    },
+    License {
+        id: "AGPL-3.0-only",
+        name: "GNU Affero General Public License v3.0 only",
+        nix: "agpl3Only",
+        text: None,
+    },
+    License {
+        id: "MPL-2.0",
+        name: "Mozilla Public License 2.0",
+        nix: "mpl20",
+        text: None,
+    },
+    License {
+        id: "BSD-2-Clause",
+        name: "BSD 2-Clause \"Simplified\" License",
+        nix: "bsd2",
+        text: Some(include_str!("../licenses/BSD-2-Clause")),
+    },
+    License {
+        id: "BSD-3-Clause",
+        name: "BSD 3-Clause \"New\" or \"Revised\" License",
+        nix: "bsd3",
+        text: Some(include_str!("../licenses/BSD-3-Clause")),
+    },
+    License {
+        id: "ISC",
+        name: "ISC License",
+        nix: "isc",
+        text: Some(include_str!("../licenses/ISC")),
+    },
+    License {
+        id: "Unlicense",
+        name: "The Unlicense",
+        nix: "unlicense",
+        text: Some(include_str!("../licenses/Unlicense")),
+    },
+];
+
+/// Finds a license by its SPDX identifier, ignoring case
+///
+/// The deprecated `GPL-3.0`-style identifiers stand for their `-only` variant.
+pub fn find(id: &str) -> Option<&'static License> {
+    let id = id.trim();
+    LICENSES.iter().find(|license| {
+        license.id.eq_ignore_ascii_case(id)
+            || license
+                .id
+                .strip_suffix("-only")
+                .is_some_and(|deprecated| deprecated.eq_ignore_ascii_case(id))
+    })
+}
+
+impl License {
+    /// The text of the license, copyrighted to `author` in `year`
+    pub fn text(&self, author: &str, year: i64) -> Result<String> {
+        let text = match self.text {
+            Some(text) => text.to_owned(),
+            None => self.download()?,
+        };
+        Ok(text
+            .replace("<year>", &year.to_string())
+            .replace("<copyright holders>", author))
+    }
+
+    fn download(&self) -> Result<String> {
+        if templates::is_offline() {
+            return Err(GetflakeError::NotAvailableOffline {
+                what: format!("The text of the {0} license", self.id),
+            });
+        }
+        let url = format!("{SPDX_TEXT_URL}/{0}.txt", self.id);
+        let output = Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location", &url])
+            .run_logged()?;
+        if !output.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: format!("curl {url}"),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Writes the `LICENSE` file under `directory`, replacing the template's
+pub fn write_license(directory: &Path, license: &License, author: &str, year: i64) -> Result<()> {
+    postprocess::write_file(&directory.join("LICENSE"), license.text(author, year)?)?;
+    Ok(())
+}
+
+/// Sets the license in the `Cargo.toml`, `pyproject.toml` and `flake.nix` files under `directory`
+///
+/// Returns the paths of the files that were changed.
+pub fn set_license_fields(directory: &Path, license: &License) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for file_name in ["Cargo.toml", "pyproject.toml", "flake.nix"] {
+        let path = directory.join(file_name);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let new_content = match file_name {
+            "Cargo.toml" => {
+                postprocess::set_toml_string(&content, "package", "license", license.id)
+            }
+            "pyproject.toml" => {
+                postprocess::set_toml_string(&content, "project", "license", license.id)
+            }
+            _ => set_nix_license(&content, license.nix),
+        };
+        if let Some(new_content) = new_content.filter(|new_content| *new_content != content) {
+            postprocess::write_file(&path, new_content)?;
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// Replaces the nixpkgs license of the packages (e.g. `licenses.mit`) with `nix`
+///
+/// Returns `None` if the flake doesn't mention one.
+fn set_nix_license(content: &str, nix: &str) -> Option<String> {
+    const PREFIX: &str = "licenses.";
+    if !content.contains(PREFIX) {
+        return None;
+    }
+
+    let mut new_content = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(index) = rest.find(PREFIX) {
+        let (before, after) = rest.split_at(index + PREFIX.len());
+        new_content.push_str(before);
+        let name_length = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after.len());
+        if name_length > 0 {
+            new_content.push_str(nix);
+        }
+        rest = &after[name_length..];
+    }
+    new_content.push_str(rest);
+    Some(new_content)
+}
@@ -1,11 +1,197 @@
-use std::error::Error;
+use std::{path::Path, process::ExitCode, time::Duration};
 
+use clap::Parser;
+use getflake::{
+    cache, colors,
+    component::add,
+    config::Config,
+    diff::diff,
+    doctor::doctor,
+    icons::{self, ERROR, WARNING},
+    interrupt,
+    output::{self, Verbosity},
+    plugin, prompt,
+    retry::{self, RetryPolicy},
+    templates::{self, pin, resolve_alias, Source, Sources, DEFAULT_SOURCE},
+    upgrade::upgrade,
+    NewOrInit, Result,
+};
+
+pub mod args;
 pub mod cli;
+pub mod tui;
+pub mod wizard;
+use args::{Args, ColorChoice, Commands, OutputFormat};
 use cli::Cli;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cli = Cli::init()?;
-    cli.run()?;
+fn main() -> ExitCode {
+    match run() {
+        Ok(exit_code) => exit_code,
+        Err(e) if e.is_interrupted() => {
+            interrupt::restore_terminal();
+            ExitCode::from(e.exit_code())
+        }
+        Err(e) => {
+            eprintln!("{ERROR}{e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<ExitCode> {
+    let args = Args::parse();
+    if let Err(e) = interrupt::install() {
+        eprintln!(
+            "{WARNING} Unable to handle Ctrl-C, so it may leave a partial project behind\n{e}"
+        );
+    }
+    output::set_json(args.output == OutputFormat::Json);
+    output::set_verbosity(match (args.quiet, args.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (_, 0) => Verbosity::Normal,
+        (_, 1) => Verbosity::Verbose,
+        _ => Verbosity::Trace,
+    });
+    colors::set_enabled(match args.color {
+        ColorChoice::Auto => colors::auto(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    });
+    let mut config = Config::load(args.profile.as_deref())?;
+    if is_first_run(&args) {
+        Cli::setup(&config)?;
+        config = Config::load(args.profile.as_deref())?;
+    }
+    icons::set_enabled(!args.no_emoji && config.emoji.unwrap_or(true));
+
+    let registry = config.registry.clone().unwrap_or_default();
+    let mut url = args.source.clone().or(config.source.clone()).map_or_else(
+        || DEFAULT_SOURCE.to_owned(),
+        |url| resolve_alias(&url, &registry),
+    );
+    if let Some(git_ref) = args.source_rev.as_ref().or(args.source_branch.as_ref()) {
+        url = pin(&url, git_ref);
+    }
+    output::set_command_timeout(
+        args.timeout
+            .or(config.command_timeout)
+            .map(Duration::from_secs),
+    );
+    templates::set_offline(args.offline);
+    let default_policy = RetryPolicy::default();
+    retry::set_policy(RetryPolicy {
+        attempts: config.retry_attempts.unwrap_or(default_policy.attempts),
+        backoff: config
+            .retry_backoff
+            .map_or(default_policy.backoff, Duration::from_secs),
+        timeout: config
+            .retry_timeout
+            .map_or(default_policy.timeout, Duration::from_secs),
+    });
+    templates::set_access_tokens(config.access_tokens.clone().unwrap_or_default());
+    let cache_ttl = config
+        .cache_ttl
+        .map_or(cache::DEFAULT_TTL, Duration::from_secs);
+    let source = Source::new(url, cache_ttl, args.refresh).use_nix(!args.no_nix);
+    // The configured sources are listed together, unless --source picks one
+    let sources = match &config.sources {
+        Some(configured) if args.source.is_none() && !configured.is_empty() => Sources::new(
+            configured
+                .iter()
+                .map(|(name, url)| {
+                    let source =
+                        Source::new(resolve_alias(url, &registry), cache_ttl, args.refresh)
+                            .use_nix(!args.no_nix);
+                    (name.clone(), source)
+                })
+                .collect(),
+        ),
+        _ => Sources::single(source),
+    };
+
+    match args.command {
+        Some(Commands::New { name, scaffold }) => Cli::init(
+            &sources,
+            &config,
+            scaffold,
+            Some(NewOrInit::New),
+            Some(name),
+        )?
+        .run()?,
+        Some(Commands::Init { name, scaffold }) => {
+            Cli::init(&sources, &config, scaffold, Some(NewOrInit::Init), name)?.run()?
+        }
+        Some(Commands::Workspace {
+            name,
+            members,
+            scaffold,
+        }) => Cli::workspace(&sources, &config, &name, members, scaffold)?,
+        Some(Commands::Add {
+            component,
+            template,
+            on_conflict,
+            dry_run,
+        }) => add(
+            sources.first(),
+            template.as_deref(),
+            &component,
+            Path::new("."),
+            on_conflict.as_deref().map(str::parse).transpose()?,
+            dry_run,
+        )?,
+        Some(Commands::List) => Cli::list(&sources)?,
+        Some(Commands::Info { template }) => Cli::info(&sources, &config, &template)?,
+        Some(Commands::Upgrade { dry_run }) => upgrade(Path::new("."), dry_run)?,
+        Some(Commands::Diff) => {
+            diff(Path::new("."))?;
+        }
+        Some(Commands::Setup) => Cli::setup(&config)?,
+        Some(Commands::Doctor) => {
+            if !doctor(sources.first(), Path::new(".")) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Some(Commands::History { action }) => {
+            Cli::history(sources.first().clone(), &config, action)?
+        }
+        Some(Commands::Undo { yes }) => Cli::undo(yes)?,
+        Some(Commands::Source { action }) => Cli::source(&config, action)?,
+        Some(Commands::Favorite { action }) => Cli::favorite(&sources, &config, action)?,
+        Some(Commands::Template { action }) => {
+            if !Cli::template(sources.first(), action)? {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Some(Commands::Completions { shell }) => Cli::completions(&sources, shell),
+        Some(Commands::External(args)) => {
+            let (name, args) = args.split_first().expect("clap passes the command's name");
+            let context = plugin::Context::new(&sources.first().url)?;
+            return Ok(ExitCode::from(plugin::run(name, args, &context)?));
+        }
+        None => {
+            let new_or_init = args.new_or_init();
+            Cli::init(&sources, &config, args.scaffold, new_or_init, args.name)?.run()?
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
 
-    Ok(())
+/// Whether getflake was never set up, and is about to ask questions anyway: without a config file,
+/// the first-run setup is run before scaffolding
+fn is_first_run(args: &Args) -> bool {
+    let scaffold = match &args.command {
+        None => &args.scaffold,
+        Some(
+            Commands::New { scaffold, .. }
+            | Commands::Init { scaffold, .. }
+            | Commands::Workspace { scaffold, .. },
+        ) => scaffold,
+        Some(_) => return false,
+    };
+    !Config::exists()
+        && !scaffold.yes
+        && !scaffold.dry_run
+        && scaffold.answers.is_none()
+        && prompt::is_interactive()
 }
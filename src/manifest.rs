@@ -0,0 +1,228 @@
+//! The `getflake.toml` file a template can declare its own variables in
+//!
+//! ```toml
+//! ignore = ["vendor/**"]
+//!
+//! [[variables]]
+//! name = "project_port"
+//! type = "integer"
+//! default = 8080
+//! prompt = "What port does the server listen on?"
+//! validate = "^[0-9]{2,5}$"
+//!
+//! [[files]]
+//! path = ".github/workflows/ci.yml"
+//! when = "project_ci"
+//!
+//! [hooks]
+//! post = ["cargo generate-lockfile"]
+//! ```
+//!
+//! Each variable is asked for like the built-in questions, and replaced like any placeholder.
+//! Files and folders listed under `files` are only kept when their condition holds, and the ones
+//! matching `ignore` are copied as they are, and the `hooks` are run once the project is
+//! scaffolded.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use globset::Glob;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    error::{GetflakeError, Result},
+    hooks::Hooks,
+};
+
+pub const FILE_NAME: &str = "getflake.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub variables: Vec<Variable>,
+    pub files: Vec<ConditionalFile>,
+
+    /// Glob patterns of the files and folders placeholders aren't replaced in
+    pub ignore: Vec<String>,
+
+    /// Commands run in the project's folder around scaffolding it, before the config file's
+    pub hooks: Hooks,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Variable {
+    /// Placeholder replaced with the value
+    pub name: String,
+
+    #[serde(rename = "type", default)]
+    pub kind: VariableKind,
+
+    pub default: Option<toml::Value>,
+
+    /// Question asked for the value, instead of the variable's name
+    pub prompt: Option<String>,
+
+    /// Regular expression the value must match
+    #[serde(rename = "validate")]
+    pub pattern: Option<String>,
+}
+
+/// A file or folder of the template that is only kept when `when` holds
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionalFile {
+    /// Relative to the template's root, before placeholders are replaced
+    pub path: PathBuf,
+
+    /// `name` (the variable is `true`), `!name`, `name == value` or `name != value`
+    pub when: String,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableKind {
+    #[default]
+    String,
+    Bool,
+    Integer,
+}
+
+impl Manifest {
+    /// Reads the `getflake.toml` file of the template in `directory`, if it has one
+    pub fn load(directory: &Path) -> Result<Self> {
+        let path = directory.join(FILE_NAME);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let invalid = |message: String| GetflakeError::InvalidManifest {
+            path: path.to_string_lossy().into_owned(),
+            message,
+        };
+
+        let manifest: Self = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        for variable in &manifest.variables {
+            if let Some(pattern) = &variable.pattern {
+                Regex::new(pattern).map_err(|e| invalid(format!("{0}: {e}", variable.name)))?;
+            }
+        }
+        if !manifest.hooks.pre.is_empty() {
+            // The manifest is only read once the template is copied, after they'd have run
+            return Err(invalid(
+                "pre hooks can only be set in the config file".to_owned(),
+            ));
+        }
+        for pattern in &manifest.ignore {
+            Glob::new(pattern).map_err(|e| invalid(format!("ignore pattern {pattern}: {e}")))?;
+        }
+        for file in &manifest.files {
+            let is_relative = file
+                .path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !is_relative {
+                return Err(invalid(format!(
+                    "{0} must be relative to the template, without ..",
+                    file.path.display()
+                )));
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Removes the files and folders of the template in `directory` whose conditions don't hold for
+    /// `variables`, then the template's `getflake.toml`, since it's not part of the project
+    ///
+    /// Returns the paths that were removed, apart from `getflake.toml`.
+    pub fn apply(
+        &self,
+        directory: &Path,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<Vec<PathBuf>> {
+        let mut removed = Vec::new();
+        for file in &self.files {
+            let path = directory.join(&file.path);
+            if condition_holds(&file.when, variables) || !path.exists() {
+                continue;
+            }
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+            removed.push(path);
+        }
+
+        let path = directory.join(FILE_NAME);
+        if path.is_file() {
+            fs::remove_file(path)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Evaluates a `when` condition, with variables that aren't set being empty
+fn condition_holds(when: &str, variables: &BTreeMap<String, String>) -> bool {
+    let value = |name: &str| {
+        variables
+            .get(name.trim())
+            .map_or("", String::as_str)
+            .to_owned()
+    };
+    let literal = |value: &str| value.trim().trim_matches('"').to_owned();
+
+    if let Some((name, expected)) = when.split_once("!=") {
+        value(name) != literal(expected)
+    } else if let Some((name, expected)) = when.split_once("==") {
+        value(name) == literal(expected)
+    } else if let Some(name) = when.trim().strip_prefix('!') {
+        value(name) != "true"
+    } else {
+        value(when) == "true"
+    }
+}
+
+impl Variable {
+    /// The question asked for the value
+    pub fn prompt(&self) -> String {
+        self.prompt
+            .clone()
+            .unwrap_or_else(|| format!("What should {0} be?", self.name))
+    }
+
+    /// The default value as it's substituted, with TOML strings unquoted
+    pub fn default_value(&self) -> Option<String> {
+        self.default.as_ref().map(|default| match default {
+            toml::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        })
+    }
+
+    /// Checks that `value` is of the variable's type and matches its pattern
+    pub fn validate(&self, value: &str) -> Result<()> {
+        let invalid = |message: String| Err(GetflakeError::InvalidInput(message));
+
+        match self.kind {
+            VariableKind::String => {}
+            VariableKind::Bool if value == "true" || value == "false" => {}
+            VariableKind::Bool => return invalid(format!("{0} must be true or false", self.name)),
+            VariableKind::Integer if value.parse::<i64>().is_ok() => {}
+            VariableKind::Integer => return invalid(format!("{0} must be a number", self.name)),
+        }
+
+        // The pattern was checked when the manifest was loaded
+        if let Some(pattern) = self.pattern.as_deref().and_then(|p| Regex::new(p).ok()) {
+            if !pattern.is_match(value) {
+                return invalid(format!("{0} must match {pattern}", self.name));
+            }
+        }
+
+        Ok(())
+    }
+}
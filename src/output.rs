@@ -0,0 +1,242 @@
+//! Where messages are printed, and how many
+//!
+//! Messages go to stdout, unless it's reserved for JSON with [`set_json`], in which case they go to
+//! stderr so scripts can parse stdout. Errors always go to stderr.
+
+use std::{
+    cell::Cell,
+    io::{self, Read, Write},
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{error::Result, interrupt, progress};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// How much is printed, from `-q` to `-vv`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors
+    Quiet,
+    /// What getflake is doing
+    Normal,
+    /// Every command run, with its arguments
+    Verbose,
+    /// Every command run, with its arguments and output
+    Trace,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+thread_local! {
+    /// Whether this thread works in the background, and mustn't print over the prompts
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `step` without printing anything but errors, like with `-q`, for background work
+pub fn quietly<T>(step: impl FnOnce() -> T) -> T {
+    QUIET.with(|quiet| quiet.set(true));
+    let result = step();
+    QUIET.with(|quiet| quiet.set(false));
+    result
+}
+
+/// Whether this thread runs in [`quietly`]
+pub fn is_quieted() -> bool {
+    QUIET.with(Cell::get)
+}
+
+pub fn verbosity() -> Verbosity {
+    if is_quieted() {
+        return Verbosity::Quiet;
+    }
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Trace,
+    }
+}
+
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints `value` as JSON on stdout
+pub fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints `message` to stderr when verbose
+pub fn log(message: &str) {
+    if verbosity() >= Verbosity::Verbose {
+        progress::suspend(|| eprintln!("{message}"));
+    }
+}
+
+/// Prints `command` with its arguments when verbose
+pub fn log_command(command: &Command) {
+    if verbosity() >= Verbosity::Verbose {
+        let line = command_line(command);
+        progress::suspend(|| eprintln!("$ {line}"));
+    }
+}
+
+/// `command` as it would be typed in a shell
+pub fn command_line(command: &Command) -> String {
+    let mut line = command.get_program().to_string_lossy().into_owned();
+    for arg in command.get_args() {
+        line.push(' ');
+        line.push_str(&arg.to_string_lossy());
+    }
+    if let Some(directory) = command.get_current_dir() {
+        line = format!("cd {0} && {line}", directory.display());
+    }
+    line
+}
+
+/// How long commands may run for before they're stopped, in seconds, or 0 for no limit
+static COMMAND_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// Sets how long commands run with [`RunLogged`] may take, so a hung network call doesn't freeze
+/// getflake
+pub fn set_command_timeout(timeout: Option<Duration>) {
+    COMMAND_TIMEOUT.store(timeout.map_or(0, |t| t.as_secs().max(1)), Ordering::Relaxed);
+}
+
+pub fn command_timeout() -> Option<Duration> {
+    match COMMAND_TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    }
+}
+
+/// Runs commands like [`Command::output`], printing them and their output when verbose
+///
+/// Commands that take longer than [`command_timeout`] are killed, failing with
+/// [`io::ErrorKind::TimedOut`].
+pub trait RunLogged {
+    fn run_logged(&mut self) -> io::Result<Output>;
+}
+
+impl RunLogged for Command {
+    fn run_logged(&mut self) -> io::Result<Output> {
+        log_command(self);
+        let output = output_within(self, command_timeout())?;
+
+        if verbosity() >= Verbosity::Trace {
+            progress::suspend(|| {
+                for stream in [&output.stdout, &output.stderr] {
+                    let text = String::from_utf8_lossy(stream);
+                    if !text.trim().is_empty() {
+                        eprintln!("{0}", text.trim_end());
+                    }
+                }
+                eprintln!("$ # {0}", output.status);
+            });
+        }
+
+        Ok(output)
+    }
+}
+
+/// Runs `command` like [`Command::output`], killing it if it takes longer than `timeout`
+///
+/// The command is tracked while it runs, so Ctrl-C kills it too.
+fn output_within(command: &mut Command, timeout: Option<Duration>) -> io::Result<Output> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read as it's written, so the command doesn't block on a full pipe
+    let read = |stream: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut stream) = stream {
+                let _ = stream.read_to_end(&mut buffer);
+            }
+            buffer
+        })
+    };
+    let stdout = read(child.stdout.take().map(|s| Box::new(s) as _));
+    let stderr = read(child.stderr.take().map(|s| Box::new(s) as _));
+    let id = interrupt::track(child);
+
+    let start = Instant::now();
+    let status = loop {
+        match interrupt::try_wait(id) {
+            Ok(Some(status)) => break status,
+            Ok(None) => {}
+            Err(e) => {
+                interrupt::kill(id);
+                return Err(e);
+            }
+        }
+        if let Some(timeout) = timeout.filter(|timeout| start.elapsed() >= *timeout) {
+            interrupt::kill(id);
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "`{0}` was stopped after {1}s without finishing",
+                    command_line(command),
+                    timeout.as_secs()
+                ),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+pub fn flush() -> io::Result<()> {
+    io::stdout().flush()?;
+    io::stderr().flush()
+}
+
+/// Prints a message and a newline, like `println!`, unless quiet
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::verbosity() > $crate::output::Verbosity::Quiet {
+            $crate::progress::suspend(|| {
+                if $crate::output::is_json() {
+                    eprintln!($($arg)*)
+                } else {
+                    println!($($arg)*)
+                }
+            })
+        }
+    };
+}
+
+/// Prints a question, like `print!`, even when quiet
+#[macro_export]
+macro_rules! ask {
+    ($($arg:tt)*) => {
+        if $crate::output::is_json() {
+            eprint!($($arg)*)
+        } else {
+            print!($($arg)*)
+        }
+    };
+}
@@ -0,0 +1,144 @@
+//! Plugins: `getflake-<name>` executables on the PATH, run as `getflake <name>`, the way git and
+//! cargo run theirs
+//!
+//! A plugin gets the arguments after its name as they are, and a JSON [`Context`] on stdin with
+//! what getflake knows about the settings and the project in the current folder, so it doesn't
+//! have to find them again.
+
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::Serialize;
+
+use crate::{
+    answers::Answers,
+    config::{cache_dir, config_dir, Config},
+    error::{GetflakeError, Result},
+    history, interrupt, output,
+    provenance::Provenance,
+};
+
+/// Prefix of the executables run as plugins
+pub const PREFIX: &str = "getflake-";
+
+/// What a plugin is told on stdin
+#[derive(Serialize, Debug)]
+pub struct Context {
+    /// Version of getflake
+    pub version: &'static str,
+    /// Folder getflake was run in
+    pub current_dir: PathBuf,
+    /// Flake reference of the template repository
+    pub source: String,
+    pub config_file: Option<PathBuf>,
+    pub config_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    /// How the project in the current folder was generated, if getflake generated it
+    pub provenance: Option<Provenance>,
+    /// What the last project getflake scaffolded was scaffolded with
+    pub last_answers: Option<Answers>,
+}
+
+impl Context {
+    pub fn new(source: &str) -> Result<Self> {
+        let current_dir = env::current_dir()?;
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION"),
+            provenance: Provenance::load(&current_dir).ok(),
+            current_dir,
+            source: source.to_owned(),
+            config_file: Config::path().filter(|path| path.exists()),
+            config_dir: config_dir(),
+            cache_dir: cache_dir(),
+            last_answers: history::load().pop().map(|entry| entry.answers),
+        })
+    }
+}
+
+/// Returns the path of the `getflake-<name>` executable on the PATH, if there's one
+pub fn find(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{PREFIX}{name}{0}", env::consts::EXE_SUFFIX);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))
+}
+
+/// The names of the plugins on the PATH, sorted
+pub fn list() -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = env::split_paths(&path)
+        .filter_map(|dir| dir.read_dir().ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        // Checking the prefix first spares statting every other executable on the PATH
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(PREFIX))
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = file_name.strip_prefix(PREFIX)?;
+            Some(
+                name.strip_suffix(env::consts::EXE_SUFFIX)
+                    .unwrap_or(name)
+                    .to_owned(),
+            )
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Runs the `getflake-<name>` plugin with `args`, giving it `context` on stdin
+///
+/// Returns the plugin's exit code, which getflake exits with.
+pub fn run(name: &str, args: &[String], context: &Context) -> Result<u8> {
+    let Some(path) = find(name) else {
+        return Err(GetflakeError::InvalidInput(format!(
+            "there's no {name} command, nor a {PREFIX}{name} plugin on the PATH"
+        )));
+    };
+
+    let mut command = Command::new(&path);
+    command.args(args).stdin(Stdio::piped());
+    output::log_command(&command);
+    interrupt::in_foreground(|| wait_for(&mut command, context))
+}
+
+/// Starts the plugin, gives it `context`, and waits for it
+fn wait_for(command: &mut Command, context: &Context) -> Result<u8> {
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let json = serde_json::to_string(context)?;
+        // A plugin that doesn't read its context may well have closed stdin already
+        if let Err(e) = writeln!(stdin, "{json}") {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                drop(stdin);
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e.into());
+            }
+        }
+    }
+    let status = child.wait()?;
+    Ok(status
+        .code()
+        .map_or(1, |code| u8::try_from(code).unwrap_or(1)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,1047 @@
+//! Everything done to the project after nix has copied the template
+
+use std::{
+    collections::BTreeMap,
+    fs, io, mem,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use tera::{Context, Tera};
+use walkdir::WalkDir;
+
+use crate::{
+    config::config_dir,
+    editorconfig,
+    error::{GetflakeError, Result},
+    gitattributes, gitignore,
+    icons::{ERROR, OK},
+    output::{self, Verbosity},
+    say,
+    scaffold::Ci,
+};
+
+/// Values substituted for the placeholders (e.g. `project_name`) in the generated files and their names
+#[derive(Debug, Default, Clone)]
+pub struct Placeholders {
+    values: Vec<(String, String)>,
+    ignore_patterns: Vec<String>,
+    ignored: GlobSet,
+}
+
+impl Placeholders {
+    /// Placeholders for `variables`, plus the case variants of `project_name` (e.g. `ProjectName`)
+    pub fn from_variables(variables: &BTreeMap<String, String>) -> Self {
+        let mut placeholders = Self::default();
+        if let Some(project_name) = variables.get("project_name") {
+            placeholders = placeholders
+                .with("ProjectName", &pascal_case(project_name))
+                .with("PROJECT_NAME", &screaming_snake_case(project_name))
+                .with("project-name", &kebab_case(project_name));
+        }
+
+        variables
+            .iter()
+            .fold(placeholders, |placeholders, (name, value)| {
+                placeholders.with(name, value)
+            })
+    }
+
+    /// Adds `placeholder`, unless it's empty, since it would match everywhere
+    pub fn with(mut self, placeholder: &str, value: &str) -> Self {
+        if !placeholder.is_empty() {
+            self.values.push((placeholder.to_owned(), value.to_owned()));
+        }
+        self
+    }
+
+    /// Leaves the files and folders matching the glob `patterns` alone, besides Git's own files
+    /// and `flake.lock`, which always are
+    ///
+    /// Patterns without a `/` match at any depth, like in a `.gitignore`.
+    pub fn ignoring(mut self, patterns: &[String]) -> Result<Self> {
+        self.ignore_patterns.extend_from_slice(patterns);
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.ignore_patterns {
+            let anchored = if pattern.contains('/') {
+                pattern.trim_start_matches('/').to_owned()
+            } else {
+                format!("**/{pattern}")
+            };
+            let glob = GlobBuilder::new(&anchored)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| {
+                    GetflakeError::InvalidInput(format!("invalid ignore pattern {pattern}: {e}"))
+                })?;
+            builder.add(glob);
+        }
+        self.ignored = builder
+            .build()
+            .map_err(|e| GetflakeError::InvalidInput(e.to_string()))?;
+
+        Ok(self)
+    }
+
+    /// Whether `path`, under `directory`, is left alone
+    fn is_ignored(&self, directory: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(directory) else {
+            return false;
+        };
+        relative.starts_with(".git")
+            || relative
+                .file_name()
+                .is_some_and(|name| name == "flake.lock")
+            || self.ignored.is_match(relative)
+    }
+
+    pub fn contains_any(&self, text: &str) -> bool {
+        self.values
+            .iter()
+            .any(|(placeholder, _)| text.contains(placeholder.as_str()))
+    }
+
+    /// Replaces every placeholder in `text` in a single pass, so values are never substituted into again
+    pub fn replace(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let placeholder = self
+                .values
+                .iter()
+                .filter(|(placeholder, _)| rest.starts_with(placeholder.as_str()))
+                .max_by_key(|(placeholder, _)| placeholder.len());
+
+            match placeholder {
+                Some((placeholder, value)) => {
+                    result.push_str(value);
+                    rest = &rest[placeholder.len()..];
+                }
+                None => {
+                    let mut chars = rest.chars();
+                    result.extend(chars.next());
+                    rest = chars.as_str();
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Returns all files under `directory` placeholders are replaced in, whether they contain any
+    /// or not, and the files and folders ignored, apart from `.git`
+    fn files(&self, directory: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut ignored = Vec::new();
+        let files = WalkDir::new(directory)
+            .into_iter()
+            .filter_entry(|entry| {
+                let is_ignored = self.is_ignored(directory, entry.path());
+                if is_ignored && entry.file_name() != ".git" {
+                    ignored.push(entry.path().to_path_buf());
+                }
+                !is_ignored
+            })
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect();
+        (files, ignored)
+    }
+
+    /// Replaces the placeholders within the file at `path`
+    fn replace_in_file(&self, path: &Path) -> FileOutcome {
+        let content = match read_text(path) {
+            Ok(content) => content,
+            Err(outcome) => return outcome,
+        };
+        if !self.contains_any(&content) {
+            return FileOutcome::Unchanged;
+        }
+        match write_file(path, self.replace(&content)) {
+            Ok(()) => FileOutcome::Replaced,
+            Err(_) => FileOutcome::WriteFailed,
+        }
+    }
+
+    /// Returns all files under `directory` whose contents contain a placeholder
+    pub fn files_containing(&self, directory: &Path) -> Vec<PathBuf> {
+        self.files(directory)
+            .0
+            .into_par_iter()
+            .filter(|path| read_text(path).is_ok_and(|content| self.contains_any(&content)))
+            .collect()
+    }
+
+    /// Returns all files and folders under `directory` whose names contain a placeholder, with the
+    /// contents of folders before the folders themselves
+    pub fn paths_containing(&self, directory: &Path) -> Vec<PathBuf> {
+        WalkDir::new(directory)
+            .min_depth(1)
+            .contents_first(true)
+            .into_iter()
+            .filter_entry(|entry| !self.is_ignored(directory, entry.path()))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| self.contains_any(&entry.file_name().to_string_lossy()))
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+}
+
+/// What replacing the placeholders within a file came to
+enum FileOutcome {
+    Replaced,
+    /// There were no placeholders in it
+    Unchanged,
+    Binary,
+    NotUtf8,
+    ReadFailed,
+    WriteFailed,
+}
+
+/// Reads the file at `path` as text, unless it's binary or not UTF-8
+fn read_text(path: &Path) -> std::result::Result<String, FileOutcome> {
+    match fs::read(path) {
+        // Rewriting it as text would corrupt it
+        Ok(content) if is_binary(&content) => Err(FileOutcome::Binary),
+        Ok(content) => String::from_utf8(content).map_err(|_| FileOutcome::NotUtf8),
+        Err(_) => Err(FileOutcome::ReadFailed),
+    }
+}
+
+/// Replaces the placeholders within the files under `directory`, and in their names
+///
+/// Returns the paths of the files that were changed or renamed.
+pub fn replace_placeholders(directory: &Path, placeholders: &Placeholders) -> Vec<PathBuf> {
+    replace_placeholders_in(directory, placeholders, true)
+}
+
+/// Like [`replace_placeholders`], without printing each file
+pub fn replace_placeholders_quietly(directory: &Path, placeholders: &Placeholders) -> Vec<PathBuf> {
+    replace_placeholders_in(directory, placeholders, false)
+}
+
+fn replace_placeholders_in(
+    directory: &Path,
+    placeholders: &Placeholders,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    let mut replacements = Replacements::default();
+
+    // Where a path ends up once the folders above it are renamed too
+    let final_path = |path: &Path| match path.strip_prefix(directory) {
+        Ok(relative) => directory.join(placeholders.replace(&relative.to_string_lossy())),
+        Err(_) => path.to_path_buf(),
+    };
+
+    // Replace the placeholders within all files containing them, several at a time, then go
+    // through the outcomes in the order the files were found
+    let (files, ignored) = placeholders.files(directory);
+    replacements.skipped = ignored;
+    let outcomes: Vec<(PathBuf, FileOutcome)> = files
+        .into_par_iter()
+        .map(|file| {
+            let outcome = placeholders.replace_in_file(&file);
+            (file, outcome)
+        })
+        .collect();
+    let shown = |path: &Path| {
+        path.strip_prefix(directory)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    for (file, outcome) in outcomes {
+        let file_name = shown(&file);
+        match outcome {
+            FileOutcome::Replaced => replacements.modified.push(final_path(&file)),
+            FileOutcome::Unchanged => {}
+            FileOutcome::Binary | FileOutcome::NotUtf8 => replacements.skipped.push(file),
+            FileOutcome::ReadFailed => eprintln!("- {ERROR}Failed to read file: {file_name}"),
+            FileOutcome::WriteFailed => eprintln!("- {ERROR}Failed to write to file: {file_name}"),
+        }
+    }
+
+    // Rename all files and folders containing placeholders, deepest first, so that the paths of
+    // the ones left to rename stay valid
+    for path in placeholders.paths_containing(directory) {
+        let path_name = shown(&path);
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let new_path = path.with_file_name(placeholders.replace(&file_name.to_string_lossy()));
+        if fs::rename(&path, &new_path).is_ok() {
+            replacements
+                .renamed
+                .push((path.clone(), final_path(&new_path)));
+        } else {
+            eprintln!("- {ERROR}Failed to rename file or folder: {path_name}");
+        }
+    }
+
+    if verbose {
+        replacements.print(directory);
+    }
+    replacements
+        .modified
+        .into_iter()
+        .chain(
+            replacements
+                .renamed
+                .into_iter()
+                .map(|(_, new_path)| new_path),
+        )
+        .collect()
+}
+
+/// What replacing the placeholders under a folder did
+#[derive(Debug, Default)]
+struct Replacements {
+    /// Files whose contents changed, at the paths they end up at
+    modified: Vec<PathBuf>,
+    /// Files and folders whose names changed, with the paths they end up at
+    renamed: Vec<(PathBuf, PathBuf)>,
+    /// Files left alone, as binary, not UTF-8 or ignored
+    skipped: Vec<PathBuf>,
+}
+
+impl Replacements {
+    /// Prints how many paths were changed, and which ones with `--verbose`, relative to the
+    /// project in `directory`
+    fn print(&self, directory: &Path) {
+        let shown = |path: &Path| {
+            path.strip_prefix(directory)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        if self.modified.is_empty() && self.renamed.is_empty() && self.skipped.is_empty() {
+            return;
+        }
+        let listed = output::verbosity() >= Verbosity::Verbose;
+
+        say!("- {OK} Replaced placeholders:");
+        say!(
+            "    {0:>4} {1} modified",
+            self.modified.len(),
+            plural(self.modified.len(), "file")
+        );
+        if listed {
+            for path in &self.modified {
+                say!("         {0}", shown(path));
+            }
+        }
+        say!(
+            "    {0:>4} {1} renamed",
+            self.renamed.len(),
+            plural(self.renamed.len(), "path")
+        );
+        if listed {
+            for (path, new_path) in &self.renamed {
+                say!("         {0} -> {1}", shown(path), shown(new_path));
+            }
+        }
+        say!(
+            "    {0:>4} {1} skipped (binary or ignored)",
+            self.skipped.len(),
+            plural(self.skipped.len(), "file")
+        );
+        if listed {
+            for path in &self.skipped {
+                say!("         {0}", shown(path));
+            }
+        }
+    }
+}
+
+/// `noun`, with an `s` unless there's one of it
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        noun.to_owned()
+    } else {
+        format!("{noun}s")
+    }
+}
+
+/// Writes `content` to the file at `path`, keeping the permissions of the file already there
+///
+/// Scripts stay executable, and read-only files (e.g. copied from the nix store) are written to
+/// anyway, and stay read-only.
+pub fn write_file(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let Ok(permissions) = fs::metadata(path).map(|metadata| metadata.permissions()) else {
+        return fs::write(path, content);
+    };
+
+    if permissions.readonly() {
+        let mut writable = permissions.clone();
+        // Only for as long as it's written to
+        #[allow(clippy::permissions_set_readonly_false)]
+        writable.set_readonly(false);
+        fs::set_permissions(path, writable)?;
+    }
+    let result = fs::write(path, content);
+    fs::set_permissions(path, permissions)?;
+    result
+}
+
+/// Whether `content` is binary rather than text, going by a null byte near its start like Git
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|&byte| byte == 0)
+}
+
+/// Suffix of the files rendered with [Tera](https://keats.github.io/tera/docs/)
+pub const TERA_SUFFIX: &str = ".tera";
+
+/// Renders the files under `directory` ending in `.tera` with `variables`, and strips the suffix
+///
+/// Values that are `true`, `false` or a number are passed as such, so that `{% if project_ci %}`
+/// works. Returns the paths of the rendered files.
+pub fn render_tera(directory: &Path, variables: &BTreeMap<String, String>) -> Result<Vec<PathBuf>> {
+    let context = tera_context(variables);
+
+    let files: Vec<PathBuf> = WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.to_string_lossy().ends_with(TERA_SUFFIX))
+        .collect();
+
+    let mut rendered = Vec::new();
+    for file in files {
+        let file_name = file.to_string_lossy();
+        let content = fs::read_to_string(&file)?;
+        let output =
+            Tera::one_off(&content, &context, false).map_err(|e| render_failed(&file_name, e))?;
+
+        let target = PathBuf::from(file_name.strip_suffix(TERA_SUFFIX).unwrap_or(&file_name));
+        fs::write(&target, output)?;
+        fs::set_permissions(&target, fs::metadata(&file)?.permissions())?;
+        fs::remove_file(&file)?;
+        rendered.push(target);
+    }
+
+    Ok(rendered)
+}
+
+/// The variables, as Tera sees them: booleans and numbers are parsed, so they can be tested and
+/// compared
+fn tera_context(variables: &BTreeMap<String, String>) -> Context {
+    let mut context = Context::new();
+    for (name, value) in variables {
+        if let Ok(value) = value.parse::<bool>() {
+            context.insert(name, &value);
+        } else if let Ok(value) = value.parse::<i64>() {
+            context.insert(name, &value);
+        } else {
+            context.insert(name, value);
+        }
+    }
+    context
+}
+
+fn render_failed(path: &str, e: tera::Error) -> GetflakeError {
+    // The cause says what's wrong with the template, the error itself only where
+    let message = std::error::Error::source(&e)
+        .map_or_else(|| e.to_string(), |source| format!("{e}: {source}"));
+    GetflakeError::RenderFailed {
+        path: path.to_owned(),
+        message,
+    }
+}
+
+/// Name of the README template in getflake's config folder, used instead of [`README_TEMPLATE`]
+pub const README_TEMPLATE_FILE: &str = "README.md.tera";
+
+/// What a cleared README.md says, rendered with Tera like the templates' `.tera` files
+pub const README_TEMPLATE: &str = r#"# {{ project_name }}
+
+{% if project_description %}{{ project_description }}{% else %}A project built with Nix flakes.{% endif %}
+
+## Development
+
+Enter a shell with everything the project needs to be worked on:
+
+```shell
+nix develop
+```
+
+## Building
+
+```shell
+nix build
+```
+
+The result is linked to `./result`.
+{% if project_license %}
+## License
+
+Licensed under {{ project_license }}{% if project_author %} by {{ project_author }}{% endif %}.
+{% endif %}"#;
+
+/// Replaces the README.md file under `directory` with the README template rendered with
+/// `variables` (the project's name, description, license and so on)
+///
+/// The template is `README.md.tera` in getflake's config folder if there's one, and else
+/// [`README_TEMPLATE`].
+pub fn clear_readme(directory: &Path, variables: &BTreeMap<String, String>) -> Result<()> {
+    let custom = config_dir()
+        .map(|dir| dir.join(README_TEMPLATE_FILE))
+        .filter(|path| path.is_file());
+    let (template, name) = match custom {
+        Some(path) => (
+            fs::read_to_string(&path)?,
+            path.to_string_lossy().into_owned(),
+        ),
+        None => (README_TEMPLATE.to_owned(), "README template".to_owned()),
+    };
+    let content = Tera::one_off(&template, &tera_context(variables), false)
+        .map_err(|e| render_failed(&name, e))?;
+    write_file(&directory.join("README.md"), content)?;
+    Ok(())
+}
+
+/// Gives the README.md file under `directory` the project's name as its title and its description
+/// below, keeping the rest of what the template wrote
+///
+/// Wherever else the template's title appears, it's replaced too (unless it's a single word, like
+/// "Rust", which may well mean something else), and "this template" becomes "this project".
+/// Returns whether there was a README.md.
+pub fn retitle_readme(directory: &Path, project_name: &str, description: &str) -> Result<bool> {
+    let path = directory.join("README.md");
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(false);
+    };
+    let mut new_content = retitle(&content, project_name);
+    if !description.is_empty() {
+        new_content = set_readme_description(&new_content, description).unwrap_or(new_content);
+    }
+    write_file(&path, new_content)?;
+    Ok(true)
+}
+
+/// Returns `content` with `project_name` as its `# Title`, see [`retitle_readme`]
+fn retitle(content: &str, project_name: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_owned).collect();
+    let title = format!("# {project_name}");
+    let first = lines.iter().position(|line| !line.trim().is_empty());
+    let is_underline = |line: &String| {
+        let line = line.trim();
+        !line.is_empty() && line.chars().all(|c| c == '=')
+    };
+    let (title_index, old_title) = match first {
+        Some(index) if lines[index].starts_with("# ") => {
+            let old_title = lines[index][2..]
+                .trim()
+                .trim_end_matches('#')
+                .trim()
+                .to_owned();
+            lines[index] = title;
+            (index, Some(old_title))
+        }
+        // `Title` underlined with `===`
+        Some(index) if lines.get(index + 1).is_some_and(is_underline) => {
+            let old_title = lines[index].trim().to_owned();
+            lines.splice(index..index + 2, [title]);
+            (index, Some(old_title))
+        }
+        _ => {
+            lines.splice(0..0, [title, String::new()]);
+            (0, None)
+        }
+    };
+
+    let old_title = old_title.filter(|old_title| old_title.split_whitespace().count() > 1);
+    for (index, line) in lines.iter_mut().enumerate() {
+        if index == title_index {
+            continue;
+        }
+        if let Some(old_title) = &old_title {
+            *line = line.replace(old_title.as_str(), project_name);
+        }
+        *line = line
+            .replace("this template", "this project")
+            .replace("This template", "This project");
+    }
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        new_content.push('\n');
+    }
+    new_content
+}
+
+/// Sets the description in the `flake.nix`, `Cargo.toml`, `pyproject.toml` and `README.md` files
+/// under `directory`, replacing the template's
+///
+/// Returns the paths of the files that were changed.
+pub fn set_description(directory: &Path, description: &str) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for file_name in ["flake.nix", "Cargo.toml", "pyproject.toml", "README.md"] {
+        let path = directory.join(file_name);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let new_content = match file_name {
+            "flake.nix" => set_flake_description(&content, description),
+            "Cargo.toml" => set_toml_string(&content, "package", "description", description),
+            "pyproject.toml" => set_toml_string(&content, "project", "description", description),
+            _ => set_readme_description(&content, description),
+        };
+        if let Some(new_content) = new_content.filter(|new_content| *new_content != content) {
+            write_file(&path, new_content)?;
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// Replaces the first single-line `description = "...";` of a flake
+fn set_flake_description(content: &str, description: &str) -> Option<String> {
+    let description = description
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    let mut lines: Vec<String> = content.lines().map(str::to_owned).collect();
+    let line = lines.iter_mut().find(|line| {
+        line.trim_end().ends_with(';')
+            && line
+                .split_once('=')
+                .is_some_and(|(name, _)| name.trim() == "description")
+    })?;
+    let indent = &line[..line.len() - line.trim_start().len()];
+    *line = format!("{indent}description = \"{description}\";");
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Some(new_content)
+}
+
+/// Replaces the paragraph right below the title of a README, or adds one if the title is followed
+/// by something else (e.g. a heading, badges or code)
+///
+/// Returns `None` if the README doesn't start with a `# Title`.
+fn set_readme_description(content: &str, description: &str) -> Option<String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let title = lines
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .filter(|&index| lines[index].starts_with("# "))?;
+
+    let start = lines[title + 1..]
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .map_or(lines.len(), |index| title + 1 + index);
+    let is_paragraph = lines.get(start).is_some_and(|line| {
+        !line.starts_with(['#', '`', '-', '*', '|', '!', '<', '[', '>', '=', ' '])
+    });
+    if is_paragraph {
+        let end = lines[start..]
+            .iter()
+            .position(|line| line.trim().is_empty())
+            .map_or(lines.len(), |index| start + index);
+        lines.splice(start..end, [description]);
+    } else {
+        lines.splice(title + 1..title + 1, ["", description]);
+    }
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Some(new_content)
+}
+
+/// Writes a `.gitignore` suited to `template` under `directory`, unless it already has one
+///
+/// Returns whether the file was written.
+pub fn write_gitignore(directory: &Path, template: &str) -> Result<bool> {
+    let path = directory.join(".gitignore");
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(path, gitignore::for_template(template))?;
+    Ok(true)
+}
+
+/// Writes a `.editorconfig` suited to `template` under `directory`, unless it already has one
+///
+/// Returns whether the file was written.
+pub fn write_editorconfig(directory: &Path, template: &str) -> Result<bool> {
+    let path = directory.join(".editorconfig");
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(path, editorconfig::for_template(template))?;
+    Ok(true)
+}
+
+/// Writes a `.gitattributes` suited to `template` under `directory`, unless it already has one
+///
+/// Returns whether the file was written.
+pub fn write_gitattributes(directory: &Path, template: &str) -> Result<bool> {
+    let path = directory.join(".gitattributes");
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(path, gitattributes::for_template(template))?;
+    Ok(true)
+}
+
+/// Sets `key` to the string `value` in the `[section]` table of a TOML file, replacing the one
+/// already there
+///
+/// Returns `None` if the file has no such table.
+pub fn set_toml_string(content: &str, section: &str, key: &str, value: &str) -> Option<String> {
+    let header = format!("[{section}]");
+    let mut lines: Vec<String> = content.lines().map(str::to_owned).collect();
+    let start = lines.iter().position(|line| line.trim() == header)? + 1;
+    let end = lines[start..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |end| start + end);
+
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    let key_line = format!("{key} = \"{value}\"");
+    let existing = lines[start..end].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(name, _)| name.trim() == key)
+    });
+    match existing {
+        Some(index) => lines[start + index] = key_line,
+        None => {
+            // After the last key of the table, rather than after the blank lines separating it
+            // from the next one
+            let last_key = lines[start..end]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map_or(start, |index| start + index + 1);
+            lines.insert(last_key, key_line);
+        }
+    }
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Some(new_content)
+}
+
+/// Writes a `.envrc` loading the flake's dev shell under `directory`, unless it already has one
+///
+/// Returns whether the file was written.
+pub fn write_envrc(directory: &Path) -> Result<bool> {
+    let path = directory.join(".envrc");
+    if path.exists() {
+        return Ok(false);
+    }
+    fs::write(path, "use flake\n")?;
+    Ok(true)
+}
+
+/// The workflow file of `ci`, relative to the project's folder
+pub fn ci_file(ci: Ci) -> Option<&'static str> {
+    match ci {
+        Ci::Skip => None,
+        Ci::GitHub => Some(".github/workflows/nix.yml"),
+        Ci::GitLab => Some(".gitlab-ci.yml"),
+    }
+}
+
+/// Writes a CI workflow for `ci` checking and building the flake under `directory`, unless it
+/// already has one
+///
+/// Returns the path of the file written.
+pub fn write_ci(directory: &Path, ci: Ci, project_name: &str) -> Result<Option<PathBuf>> {
+    let Some(file) = ci_file(ci) else {
+        return Ok(None);
+    };
+    let path = directory.join(file);
+    if path.exists() {
+        return Ok(None);
+    }
+    let content = match ci {
+        Ci::GitHub => format!(
+            "name: {project_name}
+
+on:
+  push:
+  pull_request:
+
+jobs:
+  check:
+    name: Check and build {project_name}
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: cachix/install-nix-action@v30
+      - run: nix flake check
+      - run: nix build
+"
+        ),
+        Ci::GitLab => format!(
+            "# Checks and builds {project_name}
+check:
+  image: nixos/nix:latest
+  variables:
+    NIX_CONFIG: \"experimental-features = nix-command flakes\"
+  script:
+    - nix flake check
+    - nix build
+"
+        ),
+        Ci::Skip => return Ok(None),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    Ok(Some(path))
+}
+
+/// Writes a `.devcontainer/devcontainer.json` under `directory`, with Nix installed and
+/// `nix develop` as the terminal's shell, unless it already has one
+///
+/// Returns whether the file was written.
+pub fn write_devcontainer(directory: &Path, project_name: &str) -> Result<bool> {
+    let path = directory.join(".devcontainer").join("devcontainer.json");
+    if path.exists() {
+        return Ok(false);
+    }
+    let devcontainer = serde_json::json!({
+        "name": project_name,
+        "image": "mcr.microsoft.com/devcontainers/base:ubuntu",
+        "features": {
+            "ghcr.io/devcontainers/features/nix:1": {
+                "extraNixConfig": "experimental-features = nix-command flakes"
+            }
+        },
+        // Builds the dev shell once, so opening a terminal doesn't
+        "postCreateCommand": "nix develop --command true",
+        "customizations": {
+            "vscode": {
+                "settings": {
+                    "terminal.integrated.defaultProfile.linux": "nix develop",
+                    "terminal.integrated.profiles.linux": {
+                        "nix develop": { "path": "nix", "args": ["develop"] }
+                    }
+                }
+            }
+        }
+    });
+    fs::create_dir_all(directory.join(".devcontainer"))?;
+    let content = serde_json::to_string_pretty(&devcontainer).map_err(io::Error::from)?;
+    fs::write(path, content + "\n")?;
+    Ok(true)
+}
+
+/// The pre-commit hook getflake installs when the template has no `.pre-commit-config.yaml`
+const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Checks the flake before every commit, installed by getflake
+# Skip it once with `git commit --no-verify`
+set -e
+
+# Flakes formatting with treefmt can tell whether anything is left unformatted
+if [ -f treefmt.toml ] || [ -f .treefmt.toml ]; then
+  nix fmt -- --fail-on-change
+fi
+
+nix flake check
+";
+
+/// Writes a pre-commit hook running `nix flake check` into the Git repository under
+/// `directory`, unless it already has one
+///
+/// Returns the path of the hook written.
+pub fn write_pre_commit_hook(directory: &Path) -> Result<Option<PathBuf>> {
+    let hooks = directory.join(".git").join("hooks");
+    let path = hooks.join("pre-commit");
+    if path.exists() {
+        return Ok(None);
+    }
+    fs::create_dir_all(&hooks)?;
+    fs::write(&path, PRE_COMMIT_HOOK)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(Some(path))
+}
+
+/// `my-project` → `MyProject`
+pub fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// `my-project` → `MY_PROJECT`
+pub fn screaming_snake_case(name: &str) -> String {
+    words(name).join("_").to_uppercase()
+}
+
+/// `MyProject` → `my-project`
+pub fn kebab_case(name: &str) -> String {
+    words(name).join("-")
+}
+
+// Splits a name into lowercase words at separators and camelCase boundaries
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(mem::take(&mut word));
+            }
+        } else {
+            let camel_case_boundary = c.is_uppercase()
+                && previous
+                    .is_some_and(|previous| previous.is_lowercase() || previous.is_numeric());
+            if camel_case_boundary && !word.is_empty() {
+                words.push(mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        previous = Some(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+pub fn current_year() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    civil_date(now).0
+}
+
+/// Returns the year, month and day (in UTC) of `timestamp`, in seconds since 1970-01-01
+pub fn civil_date(timestamp: u64) -> (i64, i64, i64) {
+    let days = (timestamp / 86_400) as i64;
+
+    // Converts days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_prefers_the_longest_placeholder() {
+        let placeholders = Placeholders::default()
+            .with("project", "a")
+            .with("project_name", "b");
+        assert_eq!(placeholders.replace("project_name project"), "b a");
+    }
+
+    #[test]
+    fn replace_never_substitutes_into_values() {
+        let placeholders = Placeholders::default()
+            .with("project_name", "project_author")
+            .with("project_author", "Jane Doe");
+        assert_eq!(
+            placeholders.replace("project_name, by project_author"),
+            "project_author, by Jane Doe"
+        );
+    }
+
+    #[test]
+    fn replace_ignores_empty_placeholders() {
+        let placeholders = Placeholders::default()
+            .with("", "x")
+            .with("project_name", "my-app");
+        assert_eq!(placeholders.replace("# project_name"), "# my-app");
+        assert!(!placeholders.contains_any("nothing to see"));
+    }
+
+    #[test]
+    fn from_variables_adds_the_case_variants() {
+        let variables = BTreeMap::from([("project_name".to_owned(), "my-app".to_owned())]);
+        let placeholders = Placeholders::from_variables(&variables);
+        assert_eq!(
+            placeholders.replace("ProjectName PROJECT_NAME project-name project_name"),
+            "MyApp MY_APP my-app my-app"
+        );
+    }
+
+    #[test]
+    fn case_conversions() {
+        assert_eq!(pascal_case("my-project"), "MyProject");
+        assert_eq!(pascal_case("my_cool project"), "MyCoolProject");
+        assert_eq!(screaming_snake_case("myProject"), "MY_PROJECT");
+        assert_eq!(screaming_snake_case("my-project"), "MY_PROJECT");
+        assert_eq!(kebab_case("MyProject"), "my-project");
+        assert_eq!(kebab_case("my2Go"), "my2-go");
+        assert_eq!(kebab_case("--"), "");
+    }
+
+    #[test]
+    fn retitle_replaces_the_title_and_its_mentions() {
+        let content = "# Rust Template\n\nA Rust Template, made from this template.\n";
+        assert_eq!(
+            retitle(content, "my-app"),
+            "# my-app\n\nA my-app, made from this project.\n"
+        );
+    }
+
+    #[test]
+    fn retitle_keeps_single_word_titles_elsewhere() {
+        assert_eq!(
+            retitle("# Rust\n\nWritten in Rust.\n", "my-app"),
+            "# my-app\n\nWritten in Rust.\n"
+        );
+    }
+
+    #[test]
+    fn retitle_handles_underlined_titles() {
+        assert_eq!(
+            retitle("Rust Template\n=============\n\nText\n", "my-app"),
+            "# my-app\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn retitle_adds_a_missing_title() {
+        assert_eq!(retitle("Some text", "my-app"), "# my-app\n\nSome text");
+        assert_eq!(retitle("", "my-app"), "# my-app\n\n");
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_825_600), (2000, 2, 29));
+        assert_eq!(civil_date(1_735_689_599), (2024, 12, 31));
+        assert_eq!(civil_date(1_735_689_600), (2025, 1, 1));
+    }
+}
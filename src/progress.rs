@@ -0,0 +1,51 @@
+//! Spinners shown while long steps run
+//!
+//! They are drawn on stderr, and only when it's a terminal and getflake isn't quiet. Messages printed with [`say!`] while
+//! a spinner runs are printed above it.
+//!
+//! [`say!`]: crate::say
+
+use std::{
+    io::{self, IsTerminal},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::output::{self, Verbosity};
+
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Runs `step` while showing a spinner with `message`
+pub fn spin<T>(message: &str, step: impl FnOnce() -> T) -> T {
+    if !io::stderr().is_terminal() || output::verbosity() == Verbosity::Quiet {
+        return step();
+    }
+
+    let spinner = ProgressBar::new_spinner().with_message(message.to_owned());
+    if let Ok(style) = ProgressStyle::with_template("{spinner} {msg}...") {
+        spinner.set_style(style);
+    }
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let previous = active().replace(spinner.clone());
+    let result = step();
+    *active() = previous;
+
+    spinner.finish_and_clear();
+    result
+}
+
+/// Runs `print` with the spinner hidden, so what it prints doesn't mix with it
+pub fn suspend<T>(print: impl FnOnce() -> T) -> T {
+    let spinner = active().clone();
+    match spinner {
+        Some(spinner) => spinner.suspend(print),
+        None => print(),
+    }
+}
+
+fn active() -> MutexGuard<'static, Option<ProgressBar>> {
+    ACTIVE.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,622 @@
+//! The questions the wizard asks
+//!
+//! They are arrow-key menus when running in a terminal, and plain line input otherwise.
+
+use std::io::{self, IsTerminal};
+
+use dialoguer::{Confirm, FuzzySelect, Input, Select};
+
+use crate::{
+    answers::Question,
+    ask,
+    colors::{self, BLUE, GREEN, RESET},
+    error::{GetflakeError, Result},
+    icons::{
+        AUTHOR, COMMIT, COMMIT_MESSAGE, CONFIRM, CONFLICT, DESCRIPTION, DEVCONTAINER, EMAIL, ENVRC,
+        ERROR, FOLDER, HINT, INIT_GIT, LICENSE, MERGE_FLAKE, NAME, NEW_OR_INIT, NIX, PICK,
+        PRE_COMMIT, README, REMOTE, TEMPLATE, TEMPLATES, VARIABLE, WORKFLOW,
+    },
+    license::{self, LICENSES},
+    manifest::{Variable, VariableKind},
+    output, say,
+    scaffold::{
+        Ci, Conflict, ExistingDirectory, GitHubRepo, NewOrInit, Readme, DEFAULT_COMMIT_MESSAGE,
+    },
+    templates::{self, find_template, Template, Templates},
+    validate,
+};
+
+/// Headings of the favorite templates and of the ones used lately in the template picker
+const FAVORITES_CATEGORY: &str = "Favorites";
+const RECENT_CATEGORY: &str = "Recent";
+
+/// How many times a question is asked before giving up when the config doesn't say otherwise
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+// Arrow-key menus need a terminal, so fall back to plain line input when piped
+pub fn is_interactive() -> bool {
+    let output_is_terminal = if output::is_json() {
+        io::stderr().is_terminal()
+    } else {
+        io::stdout().is_terminal()
+    };
+    io::stdin().is_terminal() && output_is_terminal
+}
+
+fn print_prompt() -> Result<()> {
+    ask!("> ");
+    output::flush()?;
+    Ok(())
+}
+
+fn read_line() -> Result<String> {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    back_or(input.trim().to_owned())
+}
+
+/// What's typed to go back to the previous question
+const BACK: &str = "back";
+
+/// Returns `input`, unless it asks to go back to the previous question
+fn back_or(input: String) -> Result<String> {
+    if input.eq_ignore_ascii_case(BACK) {
+        Err(GetflakeError::Back)
+    } else {
+        Ok(input)
+    }
+}
+
+/// Asks again when the answer is invalid, up to `max_attempts` times
+pub fn retry<T>(max_attempts: u32, mut prompt: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match prompt() {
+            Err(GetflakeError::InvalidInput(message)) if attempt < max_attempts => {
+                eprintln!("{ERROR}Invalid input: {message}");
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Asks for the template, listing the `favorites`, then the `recent` ones (see
+/// [`crate::recent`]) first
+pub fn template(
+    templates: &Templates,
+    default: Option<&str>,
+    favorites: &[String],
+    recent: &[String],
+    max_attempts: u32,
+) -> Result<String> {
+    let mut groups = templates::by_category(templates);
+    // Pinned templates are also listed in their category, where they'd be looked for
+    let pinned = |names: &[String]| {
+        names
+            .iter()
+            .filter_map(|name| {
+                templates
+                    .iter()
+                    .find(|template| template.names().any(|other| other == name))
+            })
+            .collect::<Vec<_>>()
+    };
+    for (category, names) in [(RECENT_CATEGORY, recent), (FAVORITES_CATEGORY, favorites)] {
+        let pinned = pinned(names);
+        if !pinned.is_empty() {
+            groups.insert(0, (category, pinned));
+        }
+    }
+
+    // Listed, and numbered, category by category
+    let listed: Vec<(&str, &Template)> = groups
+        .iter()
+        .flat_map(|(category, templates)| templates.iter().map(|&template| (*category, template)))
+        .collect();
+
+    if is_interactive() {
+        // Every alias can be picked, next to the template it shares a description with
+        let names: Vec<(&str, &str, &Template)> = listed
+            .iter()
+            .flat_map(|&(category, template)| {
+                template.names().map(move |name| (category, name, template))
+            })
+            .collect();
+        let default = default
+            .and_then(|default| names.iter().position(|(_, name, _)| *name == default))
+            .unwrap_or(0);
+        // Typing filters by the name and category as well as the description
+        let items: Vec<String> = names
+            .iter()
+            .map(|(category, name, template)| {
+                if template.print_str == *name {
+                    format!("{category} / {name}")
+                } else {
+                    format!("{category} / {0} ({name})", template.print_str)
+                }
+            })
+            .collect();
+        let index = FuzzySelect::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{TEMPLATE} What template do you want to use? (type to filter)"
+            ))
+            .items(&items)
+            .default(default)
+            .highlight_matches(true)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return Ok(names[index].1.to_owned());
+    }
+
+    ask!("{TEMPLATE} What {GREEN}template{RESET} do you want to use?\n");
+
+    let mut i = 0;
+    for (category, templates) in &groups {
+        ask!("  {category}\n");
+        for template in templates {
+            i += 1;
+            if template.aliases.is_empty() {
+                ask!("    {BLUE}{i}){RESET} {0}\n", template.print_str);
+            } else {
+                let aliases = template.aliases.join(", ");
+                ask!(
+                    "    {BLUE}{i}){RESET} {0} (also {aliases})\n",
+                    template.print_str
+                );
+            }
+        }
+    }
+
+    retry(max_attempts, || {
+        ask!("{PICK} Pick a number or enter the code for the template: ");
+        output::flush()?;
+
+        let template_input = read_line()?;
+        let name = match template_input.parse::<usize>() {
+            Ok(i) => i
+                .checked_sub(1)
+                .and_then(|i| listed.get(i))
+                .map(|(_, template)| template.name.as_str()),
+            Err(_) => find_template(templates, &template_input),
+        };
+
+        match name {
+            Some(name) => {
+                if name != template_input {
+                    say!("{HINT} Using {GREEN}{name}{RESET}");
+                }
+                Ok(name.to_owned())
+            }
+            None => Err(GetflakeError::InvalidInput(format!(
+                "enter a number between 1 and {0}, or the name of a template",
+                listed.len()
+            ))),
+        }
+    })
+}
+
+pub fn new_or_init() -> Result<NewOrInit> {
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{NEW_OR_INIT} Do you want to create a new project or initialize one in this folder?"
+            ))
+            .items(&[
+                "new: create the project in a new folder",
+                "init: initialize the project in this folder",
+            ])
+            .default(0)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return Ok(if index == 0 {
+            NewOrInit::New
+        } else {
+            NewOrInit::Init
+        });
+    }
+
+    ask!("{NEW_OR_INIT} Do you want to create a {GREEN}new{RESET} project or {GREEN}init{RESET}ialize one in this folder?\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "new" | "n" => Ok(NewOrInit::New),
+        "init" | "i" => Ok(NewOrInit::Init),
+        _ => Err(GetflakeError::InvalidInput(
+            "enter 'new' to create a new project; 'init' to initialize one in this folder"
+                .to_owned(),
+        )),
+    }
+}
+
+pub fn project_name(template: &str) -> Result<String> {
+    let project_name = input_string(
+        &format!("{NAME} What do you want to name your project?"),
+        None,
+    )?;
+    validate::project_name(template, &project_name)?;
+    Ok(project_name)
+}
+
+pub fn existing_directory(directory: &str) -> Result<ExistingDirectory> {
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{FOLDER} The {directory} folder already exists and isn't empty. What do you want to do?"
+            ))
+            .items(&[
+                "overwrite: delete the folder and start over",
+                "merge: add the template's files to the folder",
+                "abort",
+            ])
+            .default(2)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return match index {
+            0 => Ok(ExistingDirectory::Overwrite),
+            1 => Ok(ExistingDirectory::Merge),
+            _ => Err(GetflakeError::Aborted),
+        };
+    }
+
+    ask!("{FOLDER} The {GREEN}{directory}{RESET} folder already exists and isn't empty. Do you want to {GREEN}overwrite{RESET} it, {GREEN}merge{RESET} into it, or {GREEN}abort{RESET}?\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "overwrite" | "o" => Ok(ExistingDirectory::Overwrite),
+        "merge" | "m" => Ok(ExistingDirectory::Merge),
+        "abort" | "a" => Err(GetflakeError::Aborted),
+        _ => Err(GetflakeError::InvalidInput(
+            "enter 'overwrite', 'merge', or 'abort'".to_owned(),
+        )),
+    }
+}
+
+pub fn author(default: &str) -> Result<String> {
+    input_string(
+        &format!("{AUTHOR} Who is the author of the project?"),
+        Some(default),
+    )
+}
+
+pub fn email(default: &str) -> Result<String> {
+    input_string(
+        &format!("{EMAIL} What is the author's email address?"),
+        Some(default),
+    )
+}
+
+pub fn description() -> Result<String> {
+    input_string(
+        &format!("{DESCRIPTION} How would you describe the project?"),
+        Some(""),
+    )
+}
+
+pub fn license(default: &str) -> Result<String> {
+    if is_interactive() {
+        let mut items: Vec<String> = LICENSES
+            .iter()
+            .map(|license| format!("{0}: {1}", license.id, license.name))
+            .collect();
+        items.push("Other".to_owned());
+        // A license getflake doesn't know is typed in under Other
+        let default_index = match license::find(default) {
+            Some(license) => LICENSES
+                .iter()
+                .position(|known| known.id == license.id)
+                .unwrap_or(0),
+            None => LICENSES.len(),
+        };
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!("{LICENSE} What license is the project under?"))
+            .items(&items)
+            .default(default_index)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return match LICENSES.get(index) {
+            Some(license) => Ok(license.id.to_owned()),
+            None => input_string(
+                &format!("{LICENSE} What is the SPDX identifier of the license?"),
+                license::find(default).map_or(Some(default), |_| None),
+            ),
+        };
+    }
+
+    let ids: Vec<&str> = LICENSES.iter().map(|license| license.id).collect();
+    input_string(
+        &format!(
+            "{LICENSE} What license is the project under? (e.g. {0})",
+            ids.join(", ")
+        ),
+        Some(default),
+    )
+}
+
+pub fn init_git(default: bool) -> Result<bool> {
+    input_bool(
+        &format!("{INIT_GIT} Do you want to initialize a Git repository (using git init)?"),
+        default,
+    )
+}
+
+pub fn commit() -> Result<bool> {
+    input_bool(
+        &format!("{COMMIT} Do you want to commit the generated files?"),
+        true,
+    )
+}
+
+pub fn commit_message() -> Result<String> {
+    input_string(
+        &format!("{COMMIT_MESSAGE} What should the commit message be?"),
+        Some(DEFAULT_COMMIT_MESSAGE),
+    )
+}
+
+pub fn github_repo() -> Result<GitHubRepo> {
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{REMOTE} Do you want to create a GitHub repository for the project (using gh)?"
+            ))
+            .items(&[
+                "private: create a private repository",
+                "public: create a public repository",
+                "skip: don't create a repository",
+            ])
+            .default(2)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return Ok([GitHubRepo::Private, GitHubRepo::Public, GitHubRepo::Skip][index]);
+    }
+
+    ask!("{REMOTE} Do you want to create a {GREEN}private{RESET} or {GREEN}public{RESET} GitHub repository for the project (using gh), or {GREEN}skip{RESET} it?\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "" | "skip" | "s" => Ok(GitHubRepo::Skip),
+        "private" => Ok(GitHubRepo::Private),
+        "public" => Ok(GitHubRepo::Public),
+        _ => Err(GetflakeError::InvalidInput(
+            "enter 'private', 'public', or 'skip'".to_owned(),
+        )),
+    }
+}
+
+/// Asks for the URL of the remote repository, where an empty answer means none
+pub fn remote(default: &str) -> Result<Option<String>> {
+    let remote = input_string(
+        &format!("{REMOTE} What is the URL of the remote repository? (leave empty for none)"),
+        Some(default),
+    )?;
+    Ok(Some(remote).filter(|remote| !remote.is_empty()))
+}
+
+/// Asks whether to use `template` once its files are shown, or to pick another one
+pub fn use_template(template: &str) -> Result<bool> {
+    input_bool(
+        &format!("{TEMPLATE} Use {template}? (no picks another one)"),
+        true,
+    )
+}
+
+/// Asks for the flake reference templates are fetched from, during the first-run setup
+pub fn source(default: &str) -> Result<String> {
+    input_string(
+        &format!("{TEMPLATES} Which template repository should getflake use?"),
+        Some(default),
+    )
+}
+
+pub fn push() -> Result<bool> {
+    input_bool(
+        &format!("{REMOTE} Do you want to push the initial commit?"),
+        true,
+    )
+}
+
+pub fn readme(default: Readme) -> Result<Readme> {
+    let choices = [Readme::Keep, Readme::Retitle, Readme::Replace];
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{README} What do you want to do with the README.md file?"
+            ))
+            .items(&[
+                "keep: leave it as the template has it",
+                "retitle: keep its content, with the project's name and description",
+                "replace: write a new one with the project's name, description and license",
+            ])
+            .default(
+                choices
+                    .iter()
+                    .position(|&readme| readme == default)
+                    .unwrap_or(0),
+            )
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return Ok(choices[index]);
+    }
+
+    ask!("{README} Do you want to {GREEN}keep{RESET}, {GREEN}retitle{RESET} or {GREEN}replace{RESET} the README.md file?\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "" => Ok(default),
+        answer => answer.parse(),
+    }
+}
+
+/// Asks for a variable the template declared in its `getflake.toml`
+/// Asks for the value of `variable`, starting out with `default`
+pub fn variable(variable: &Variable, default: Option<&str>) -> Result<String> {
+    let prompt = format!("{VARIABLE} {0}", variable.prompt());
+    let value = match variable.kind {
+        VariableKind::Bool => input_bool(&prompt, default == Some("true"))?.to_string(),
+        _ => input_string(&prompt, default)?,
+    };
+    variable.validate(&value)?;
+    Ok(value)
+}
+
+pub fn direnv(default: bool) -> Result<bool> {
+    input_bool(
+        &format!("{ENVRC} Do you want to set up direnv (a .envrc with use flake)?"),
+        default,
+    )
+}
+
+/// Asks what to do with the `file` of `template` the project already has
+pub fn conflict(file: &str, template: &str) -> Result<Conflict> {
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{CONFLICT} The project already has a different {file} than the {template} template. What do you want to do?"
+            ))
+            .items(&[
+                "keep: keep the project's".to_owned(),
+                format!("replace: take the {template} template's"),
+                format!("both: keep the project's, and save the template's as {file}.{template}"),
+            ])
+            .default(2)
+            .interact_opt()?
+            .ok_or(GetflakeError::Aborted)?;
+        return Ok(match index {
+            0 => Conflict::Keep,
+            1 => Conflict::Replace,
+            _ => Conflict::Both,
+        });
+    }
+
+    ask!("{CONFLICT} The project already has a different {GREEN}{file}{RESET} than the {template} template. Do you want to {GREEN}keep{RESET} it, {GREEN}replace{RESET} it, or keep {GREEN}both{RESET}?\n");
+    print_prompt()?;
+    read_line()?.to_lowercase().parse()
+}
+
+pub fn merge_flake(directory: &str) -> Result<bool> {
+    input_bool(
+        &format!("{MERGE_FLAKE} {directory} already has a flake.nix, do you want to merge the template's into it?"),
+        true,
+    )
+}
+
+pub fn ci(default: Ci) -> Result<Ci> {
+    let choices = [Ci::GitHub, Ci::GitLab, Ci::Skip];
+    if is_interactive() {
+        let index = Select::with_theme(colors::theme().as_ref())
+            .with_prompt(format!(
+                "{WORKFLOW} Do you want a CI workflow that runs nix flake check and nix build?"
+            ))
+            .items(&[
+                "github: a GitHub Actions workflow",
+                "gitlab: a GitLab CI pipeline",
+                "skip: no CI workflow",
+            ])
+            .default(choices.iter().position(|&ci| ci == default).unwrap_or(2))
+            .interact_opt()?
+            .ok_or(GetflakeError::Back)?;
+        return Ok(choices[index]);
+    }
+
+    ask!("{WORKFLOW} Do you want a {GREEN}github{RESET} or {GREEN}gitlab{RESET} CI workflow that runs nix flake check and nix build, or {GREEN}skip{RESET} it?\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "" => Ok(default),
+        answer => answer.parse(),
+    }
+}
+
+pub fn pre_commit(default: bool) -> Result<bool> {
+    input_bool(
+        &format!("{PRE_COMMIT} Do you want a pre-commit hook that checks the flake?"),
+        default,
+    )
+}
+
+pub fn devcontainer(default: bool) -> Result<bool> {
+    input_bool(
+        &format!("{DEVCONTAINER} Do you want a dev container (a devcontainer.json with Nix, for Codespaces)?"),
+        default,
+    )
+}
+
+/// Asks whether to go ahead with the answers, or which one to change first
+///
+/// Returns `None` to go ahead.
+pub fn confirm() -> Result<Option<Question>> {
+    let items: Vec<String> = ["Yes, go ahead".to_owned()]
+        .into_iter()
+        .chain(
+            Question::ALL
+                .iter()
+                .map(|question| format!("Change {0}", question.subject())),
+        )
+        .collect();
+    let index = Select::with_theme(colors::theme().as_ref())
+        .with_prompt(format!("{CONFIRM} Is this right?"))
+        .items(&items)
+        .default(0)
+        .interact()?;
+    Ok(index.checked_sub(1).map(|index| Question::ALL[index]))
+}
+
+pub fn develop() -> Result<bool> {
+    // There's no question to go back to once the project is created
+    match input_bool(
+        &format!("{NIX} Do you want to enter the project's dev shell (using nix develop)?"),
+        false,
+    ) {
+        Err(GetflakeError::Back) => Ok(false),
+        result => result,
+    }
+}
+
+pub fn input_bool(prompt: &str, default: bool) -> Result<bool> {
+    if is_interactive() {
+        return Confirm::with_theme(colors::theme().as_ref())
+            .with_prompt(prompt)
+            .default(default)
+            .interact_opt()?
+            .ok_or(GetflakeError::Back);
+    }
+
+    ask!("{prompt}\n");
+    print_prompt()?;
+
+    match read_line()?.to_lowercase().as_str() {
+        "" => Ok(default),
+        "y" | "yes" | "true" => Ok(true),
+        "n" | "no" | "false" => Ok(false),
+        _ => Err(GetflakeError::InvalidInput(
+            "enter 'y', 'yes', or 'true' to agree; 'n', 'no', or 'false' to disagree".to_owned(),
+        )),
+    }
+}
+
+pub fn input_string(prompt: &str, default: Option<&str>) -> Result<String> {
+    if is_interactive() {
+        let theme = colors::theme();
+        let mut input = Input::<String>::with_theme(theme.as_ref()).with_prompt(prompt);
+        if let Some(default) = default {
+            input = input
+                .default(default.to_owned())
+                .show_default(!default.is_empty());
+        }
+        return back_or(input.interact_text()?.trim().to_owned());
+    }
+
+    match default {
+        Some(default) if !default.is_empty() => ask!("{prompt} [{default}]\n"),
+        _ => ask!("{prompt}\n"),
+    }
+    print_prompt()?;
+    let input = read_line()?;
+    match default {
+        Some(default) if input.is_empty() => Ok(default.to_owned()),
+        _ => Ok(input),
+    }
+}
@@ -0,0 +1,69 @@
+//! The `.getflake.toml` file recording how a project was generated
+//!
+//! It's what `getflake upgrade` and `getflake diff` generate the template again from.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GetflakeError, Result};
+
+pub const FILE_NAME: &str = ".getflake.toml";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Provenance {
+    /// Version of getflake the project was generated with
+    pub getflake_version: String,
+
+    pub template: String,
+
+    /// Flake reference of the template repository
+    pub source: String,
+
+    /// Commit the template repository was at
+    pub rev: Option<String>,
+
+    /// Placeholders that were replaced, mapped to their values
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    pub fn new(
+        template: &str,
+        source: &str,
+        rev: Option<String>,
+        variables: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            getflake_version: env!("CARGO_PKG_VERSION").to_owned(),
+            template: template.to_owned(),
+            source: source.to_owned(),
+            rev,
+            variables,
+        }
+    }
+
+    /// Reads the `.getflake.toml` file of the project in `directory`
+    pub fn load(directory: &Path) -> Result<Self> {
+        let path = directory.join(FILE_NAME);
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| GetflakeError::InvalidProvenance {
+            path: path.to_string_lossy().into_owned(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Writes the `.getflake.toml` file of the project in `directory`
+    pub fn write(&self, directory: &Path) -> Result<()> {
+        let path = directory.join(FILE_NAME);
+        let content =
+            toml::to_string_pretty(self).map_err(|e| GetflakeError::InvalidProvenance {
+                path: path.to_string_lossy().into_owned(),
+                message: e.to_string(),
+            })?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! The templates used lately, so the picker can put them first
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::cache_dir, error::Result};
+
+/// How many templates the picker shows as recent
+pub const RECENT_COUNT: usize = 3;
+
+#[derive(Serialize, Deserialize, Default)]
+struct RecentFile {
+    /// Uses of each template, by source
+    sources: HashMap<String, HashMap<String, Usage>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Usage {
+    count: u32,
+    last_used: u64,
+}
+
+impl Usage {
+    /// Frequent templates rank higher, but each use counts for less as days go by
+    fn score(&self, now: u64) -> f64 {
+        let days = now.saturating_sub(self.last_used) as f64 / (24.0 * 60.0 * 60.0);
+        f64::from(self.count) / (1.0 + days)
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("recent.json"))
+}
+
+/// Returns the names of the templates of `source` used most recently and most often, best first
+pub fn load(source: &str) -> Vec<String> {
+    let Some(mut recent_file) = read() else {
+        return Vec::new();
+    };
+    let Some(usages) = recent_file.sources.remove(source) else {
+        return Vec::new();
+    };
+
+    let now = now();
+    let mut usages: Vec<(String, Usage)> = usages.into_iter().collect();
+    usages.sort_by(|(_, a), (_, b)| {
+        b.score(now)
+            .partial_cmp(&a.score(now))
+            .unwrap_or(Ordering::Equal)
+            .then(b.last_used.cmp(&a.last_used))
+    });
+    usages
+        .into_iter()
+        .take(RECENT_COUNT)
+        .map(|(template, _)| template)
+        .collect()
+}
+
+/// Records that `template` of `source` was just used
+pub fn record(source: &str, template: &str) -> Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let mut recent_file = read().unwrap_or_default();
+    let usage = recent_file
+        .sources
+        .entry(source.to_owned())
+        .or_default()
+        .entry(template.to_owned())
+        .or_insert(Usage {
+            count: 0,
+            last_used: 0,
+        });
+    usage.count += 1;
+    usage.last_used = now();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&recent_file)?)?;
+
+    Ok(())
+}
+
+fn read() -> Option<RecentFile> {
+    let content = fs::read_to_string(path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,105 @@
+//! Generating a template again, away from the project, to compare the project with it
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    error::Result,
+    manifest::Manifest,
+    postprocess::{self, Placeholders},
+    progress, provenance,
+    templates::Source,
+};
+
+/// A folder in the temporary directory, removed when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos())
+            .unwrap_or_default();
+        let path = env::temp_dir().join(format!("getflake-{0}-{nanos}", process::id()));
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A template generated in a temporary folder, removed when dropped
+pub struct Rendered {
+    // Only kept around to remove the folder
+    _temp_dir: TempDir,
+    path: PathBuf,
+}
+
+impl Rendered {
+    /// The folder the template was generated in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Generates `template` from `source` into a temporary folder, replacing the placeholders with
+/// `variables`
+pub fn render(
+    source: &Source,
+    template: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<Rendered> {
+    let temp_dir = TempDir::new()?;
+    let directory = temp_dir.path().join("template");
+
+    progress::spin(&format!("Generating {template}"), || {
+        source.copy_template(template, &directory)
+    })?;
+    let manifest = Manifest::load(&directory)?;
+    manifest.apply(&directory, variables)?;
+    postprocess::render_tera(&directory, variables)?;
+    let placeholders = Placeholders::from_variables(variables).ignoring(&manifest.ignore)?;
+    postprocess::replace_placeholders_quietly(&directory, &placeholders);
+
+    Ok(Rendered {
+        _temp_dir: temp_dir,
+        path: directory,
+    })
+}
+
+/// Returns the paths of the files under `directory`, relative to it
+///
+/// Git's own files and `.getflake.toml` aren't part of any template, so they are left out.
+pub fn files(directory: &Path) -> BTreeSet<PathBuf> {
+    WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(directory)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .filter(|path| path != Path::new(provenance::FILE_NAME))
+        .collect()
+}
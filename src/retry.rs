@@ -0,0 +1,97 @@
+//! Retrying fetches that fail because of the network, rather than because of the source
+//!
+//! A repository that doesn't exist fails the same way every time, so only failures like a host
+//! that can't be resolved or a server error are retried.
+
+use std::{
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{error::Result, icons::WARNING, templates::is_offline};
+
+/// How fetches are retried
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// How many times a fetch is tried in all
+    pub attempts: u32,
+    /// How long to wait before the first retry, doubled before each of the next ones
+    pub backoff: Duration,
+    /// How long to keep retrying for, after which the last failure is reported
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+static POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Sets how fetches are retried, instead of [`RetryPolicy::default`]
+pub fn set_policy(policy: RetryPolicy) {
+    let _ = POLICY.set(policy);
+}
+
+pub fn policy() -> RetryPolicy {
+    POLICY.get().copied().unwrap_or_default()
+}
+
+/// Runs `fetch` until it succeeds, fails for good, or the policy gives up
+///
+/// `what` says what is being fetched in the warnings about retrying.
+pub fn retry<T>(what: &str, mut fetch: impl FnMut() -> Result<T>) -> Result<T> {
+    let policy = policy();
+    let start = Instant::now();
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        let e = match fetch() {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let out_of_time = start.elapsed() + backoff > policy.timeout;
+        if attempt >= policy.attempts || out_of_time || is_offline() || !e.is_transient() {
+            return Err(e);
+        }
+
+        eprintln!(
+            "{WARNING} Failed to fetch {what}, retrying in {0}s ({attempt}/{1})\n{e}",
+            backoff.as_secs_f32(),
+            policy.attempts - 1
+        );
+        thread::sleep(backoff);
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Whether the output of a failed nix, git or curl command looks like a network hiccup, which
+/// may not happen again
+pub fn is_transient_failure(stderr: &str) -> bool {
+    const PATTERNS: &[&str] = &[
+        "Could not resolve host",
+        "Couldn't resolve host",
+        "Temporary failure in name resolution",
+        "Connection timed out",
+        "Operation timed out",
+        "Connection refused",
+        "Connection reset",
+        "Failed to connect",
+        "Network is unreachable",
+        "SSL connect error",
+        "early EOF",
+        "RPC failed",
+        "HTTP error 5",
+        "returned error: 5",
+        "HTTP error 429",
+        "returned error: 429",
+    ];
+    PATTERNS.iter().any(|pattern| stderr.contains(pattern))
+}
@@ -0,0 +1,1976 @@
+//! Creating a project from a template
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    env, fmt, fs, io,
+    path::{self, Path, PathBuf},
+    process::{self, Command},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{
+    colors::{self, GREEN, RESET},
+    diff::print_unified,
+    error::{GetflakeError, Result},
+    flake, hooks,
+    icons::{
+        CI, CLEAN, COMMIT, CONTAINER, CREATED, DIRENV, DONE, ERROR, FETCH, FORMAT, GIT, HINT, HOOK,
+        MERGE, NIX, OK, OPEN, PIN, PLAN, PROVENANCE, PUSH, REMOVE, START, SUMMARY, UPDATE, WARNING,
+        WELCOME,
+    },
+    interrupt, license,
+    manifest::Manifest,
+    output::{self, RunLogged},
+    postprocess::{self, current_year, Placeholders},
+    progress, prompt,
+    provenance::{self, Provenance},
+    render::{self, TempDir},
+    say,
+    script::Scripts,
+    templates::{is_auth_failure, nix_command, Source, TemplateDetails},
+    undo,
+};
+
+pub const DEFAULT_LICENSE: &str = "MIT";
+
+pub const DEFAULT_COMMIT_MESSAGE: &str = "Initial commit from getflake";
+
+/// The steps scripts can skip with `skip`
+pub const SKIPPABLE_STEPS: &[&str] = &[
+    "license_file",
+    "git",
+    "commit",
+    "github_repo",
+    "push",
+    "pre_commit",
+    "readme",
+    "format",
+    "direnv",
+    "ci",
+    "devcontainer",
+    "editorconfig",
+    "gitattributes",
+    "hooks",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum NewOrInit {
+    New,
+    Init,
+}
+
+impl fmt::Display for NewOrInit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::New => write!(f, "new"),
+            Self::Init => write!(f, "init"),
+        }
+    }
+}
+
+/// What to do when the folder of a new project already has files in it
+pub enum ExistingDirectory {
+    Overwrite,
+    Merge,
+}
+
+impl fmt::Display for ExistingDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Overwrite => write!(f, "overwrite"),
+            Self::Merge => write!(f, "merge"),
+        }
+    }
+}
+
+/// What to do with a file of a template added with [`Scaffolder::extra_templates`] that the
+/// project already has, with other contents
+#[derive(Clone, Copy, Debug)]
+pub enum Conflict {
+    /// Keep the project's file
+    Keep,
+    /// Replace it with the template's
+    Replace,
+    /// Keep the project's, and save the template's next to it as `<file>.<template>`
+    Both,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Keep => write!(f, "keep"),
+            Self::Replace => write!(f, "replace"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
+
+impl FromStr for Conflict {
+    type Err = GetflakeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "replace" => Ok(Self::Replace),
+            "both" => Ok(Self::Both),
+            _ => Err(GetflakeError::InvalidInput(
+                "enter 'keep', 'replace', or 'both'".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Whether to create a repository for the project on GitHub with the `gh` CLI, and who can see it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitHubRepo {
+    Skip,
+    Private,
+    Public,
+}
+
+impl fmt::Display for GitHubRepo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::Private => write!(f, "private"),
+            Self::Public => write!(f, "public"),
+        }
+    }
+}
+
+impl FromStr for GitHubRepo {
+    type Err = GetflakeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "private" => Ok(Self::Private),
+            "public" => Ok(Self::Public),
+            _ => Err(GetflakeError::InvalidInput(
+                "enter 'private', 'public', or 'skip'".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Which forge to write a CI workflow for, which runs `nix flake check` and `nix build`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ci {
+    Skip,
+    GitHub,
+    GitLab,
+}
+
+impl fmt::Display for Ci {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::GitHub => write!(f, "github"),
+            Self::GitLab => write!(f, "gitlab"),
+        }
+    }
+}
+
+impl FromStr for Ci {
+    type Err = GetflakeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "github" => Ok(Self::GitHub),
+            "gitlab" => Ok(Self::GitLab),
+            _ => Err(GetflakeError::InvalidInput(
+                "enter 'github', 'gitlab', or 'skip'".to_owned(),
+            )),
+        }
+    }
+}
+
+/// What to do with the template's README.md
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Readme {
+    /// Leave it as it is
+    Keep,
+    /// Keep its content, with the project's name as its title
+    Retitle,
+    /// Replace it with one rendered from the README template
+    Replace,
+}
+
+impl fmt::Display for Readme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Keep => write!(f, "keep"),
+            Self::Retitle => write!(f, "retitle"),
+            Self::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+impl FromStr for Readme {
+    type Err = GetflakeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "retitle" => Ok(Self::Retitle),
+            "replace" => Ok(Self::Replace),
+            _ => Err(GetflakeError::InvalidInput(
+                "enter 'keep', 'retitle', or 'replace'".to_owned(),
+            )),
+        }
+    }
+}
+
+// `clear_readme = true` and `false`, from before there was a third choice, are still read
+impl<'de> Deserialize<'de> for Readme {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Clear(bool),
+            Name(String),
+        }
+        match Value::deserialize(deserializer)? {
+            Value::Clear(true) => Ok(Self::Replace),
+            Value::Clear(false) => Ok(Self::Keep),
+            Value::Name(name) => name.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// What [`Scaffolder::run`] did, printed by `--output json`
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// Folder the project was created in
+    pub path: PathBuf,
+    pub template: String,
+    /// Flake reference of the template repository
+    pub source: String,
+    /// Commit the template repository was at, so the project can be generated again
+    pub rev: Option<String>,
+    /// Files whose contents or names were changed after nix copied the template
+    pub files_modified: Vec<PathBuf>,
+    pub commands: Vec<String>,
+    pub welcome_text: Option<String>,
+}
+
+/// Creates a project from a template, then fills in its details
+///
+/// Everything but the source, template and project name is optional, and set with the builder
+/// methods before calling [`Scaffolder::run`].
+pub struct Scaffolder {
+    source: Source,
+    template: String,
+    project_name: String,
+    new_or_init: NewOrInit,
+    target_directory: Option<String>,
+    existing_directory: Option<ExistingDirectory>,
+    author: String,
+    email: String,
+    description: String,
+    license: String,
+    license_file: bool,
+    init_git: bool,
+    commit: bool,
+    commit_message: String,
+    remote: Option<String>,
+    github_repo: GitHubRepo,
+    push: bool,
+    readme: Readme,
+    format: bool,
+    direnv: bool,
+    ci: Ci,
+    devcontainer: bool,
+    pre_commit: bool,
+    editorconfig: bool,
+    gitattributes: bool,
+    variables: BTreeMap<String, String>,
+    ignore: Vec<String>,
+    show_diff: bool,
+    merge_flake: bool,
+    extra_templates: Vec<String>,
+    on_conflict: Option<Conflict>,
+    pre_hooks: Vec<String>,
+    post_hooks: Vec<String>,
+    hooks: bool,
+    scripts: Scripts,
+}
+
+impl Scaffolder {
+    pub fn new(source: Source, template: &str, project_name: &str) -> Self {
+        Self {
+            source,
+            template: template.to_owned(),
+            project_name: project_name.to_owned(),
+            new_or_init: NewOrInit::New,
+            target_directory: None,
+            existing_directory: None,
+            author: String::new(),
+            email: String::new(),
+            description: String::new(),
+            license: DEFAULT_LICENSE.to_owned(),
+            license_file: true,
+            init_git: false,
+            commit: false,
+            commit_message: DEFAULT_COMMIT_MESSAGE.to_owned(),
+            remote: None,
+            github_repo: GitHubRepo::Skip,
+            push: false,
+            readme: Readme::Keep,
+            format: false,
+            direnv: false,
+            ci: Ci::Skip,
+            devcontainer: false,
+            pre_commit: false,
+            editorconfig: false,
+            gitattributes: false,
+            variables: BTreeMap::new(),
+            ignore: Vec::new(),
+            show_diff: false,
+            merge_flake: false,
+            extra_templates: Vec::new(),
+            on_conflict: None,
+            pre_hooks: Vec::new(),
+            post_hooks: Vec::new(),
+            hooks: true,
+            scripts: Scripts::default(),
+        }
+    }
+
+    /// Whether to create the project in a new folder named after it, or in the current one
+    pub fn new_or_init(mut self, new_or_init: NewOrInit) -> Self {
+        self.new_or_init = new_or_init;
+        self
+    }
+
+    /// Folder to create the project in instead of the one named after it (or the current one, for
+    /// `init`), with the folders leading to it created as needed
+    pub fn target_directory(mut self, target_directory: Option<String>) -> Self {
+        self.target_directory = target_directory;
+        self
+    }
+
+    /// What to do if the folder of a new project already has files in it
+    pub fn existing_directory(mut self, existing_directory: Option<ExistingDirectory>) -> Self {
+        self.existing_directory = existing_directory;
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = author.to_owned();
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.email = email.to_owned();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_owned();
+        self
+    }
+
+    pub fn license(mut self, license: &str) -> Self {
+        self.license = license.to_owned();
+        self
+    }
+
+    /// Whether to write the `LICENSE` file and set the license in the project's manifests, which
+    /// only happens when [`Scaffolder::license`] is an SPDX identifier getflake knows
+    pub fn license_file(mut self, license_file: bool) -> Self {
+        self.license_file = license_file;
+        self
+    }
+
+    /// Whether to initialize a Git repository, with a `.gitignore` if the template has none
+    pub fn init_git(mut self, init_git: bool) -> Self {
+        self.init_git = init_git;
+        self
+    }
+
+    /// Whether to commit the generated files, which only happens with [`Scaffolder::init_git`]
+    pub fn commit(mut self, commit: bool) -> Self {
+        self.commit = commit;
+        self
+    }
+
+    pub fn commit_message(mut self, commit_message: &str) -> Self {
+        self.commit_message = commit_message.to_owned();
+        self
+    }
+
+    /// URL of the repository added as the `origin` remote, which only happens with
+    /// [`Scaffolder::init_git`]
+    pub fn remote(mut self, remote: Option<String>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// Whether to create the repository on GitHub with `gh repo create`, which then becomes the
+    /// `origin` remote instead of [`Scaffolder::remote`]
+    pub fn github_repo(mut self, github_repo: GitHubRepo) -> Self {
+        self.github_repo = github_repo;
+        self
+    }
+
+    /// Whether to push the initial commit to the remote
+    pub fn push(mut self, push: bool) -> Self {
+        self.push = push;
+        self
+    }
+
+    /// What to do with the template's README.md
+    pub fn readme(mut self, readme: Readme) -> Self {
+        self.readme = readme;
+        self
+    }
+
+    /// Whether to run `nix fmt` once the placeholders are replaced, if the template has a formatter
+    pub fn format(mut self, format: bool) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Whether to write a `.envrc` with `use flake`, and allow it if direnv is installed
+    pub fn direnv(mut self, direnv: bool) -> Self {
+        self.direnv = direnv;
+        self
+    }
+
+    /// Which forge to write a CI workflow for, named after the project
+    pub fn ci(mut self, ci: Ci) -> Self {
+        self.ci = ci;
+        self
+    }
+
+    /// Whether to write a `.devcontainer/devcontainer.json` with Nix, for Codespaces and Dev
+    /// Containers
+    pub fn devcontainer(mut self, devcontainer: bool) -> Self {
+        self.devcontainer = devcontainer;
+        self
+    }
+
+    /// Whether to install a pre-commit hook checking the flake, which only happens with
+    /// [`Scaffolder::init_git`]
+    ///
+    /// It's the template's, with `pre-commit install`, if the template has a
+    /// `.pre-commit-config.yaml` and pre-commit is installed, and otherwise a hook running
+    /// `nix flake check`.
+    pub fn pre_commit(mut self, pre_commit: bool) -> Self {
+        self.pre_commit = pre_commit;
+        self
+    }
+
+    /// Whether to write an `.editorconfig` suited to the template's language, if it has none
+    pub fn editorconfig(mut self, editorconfig: bool) -> Self {
+        self.editorconfig = editorconfig;
+        self
+    }
+
+    /// Whether to write a `.gitattributes` suited to the template's language, if it has none
+    pub fn gitattributes(mut self, gitattributes: bool) -> Self {
+        self.gitattributes = gitattributes;
+        self
+    }
+
+    /// Commands run with the shell in the current folder before the template is copied, the
+    /// first to fail stopping the run
+    pub fn pre_hooks(mut self, pre_hooks: Vec<String>) -> Self {
+        self.pre_hooks = pre_hooks;
+        self
+    }
+
+    /// Commands run with the shell in the project's folder once it's scaffolded, before the
+    /// initial commit, after the ones the template's `getflake.toml` declares
+    pub fn post_hooks(mut self, post_hooks: Vec<String>) -> Self {
+        self.post_hooks = post_hooks;
+        self
+    }
+
+    /// Whether to run the hooks of the config file and the template, which is the default
+    pub fn hooks(mut self, hooks: bool) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Runs the `before` function of `scripts`, adding the variables they set and skipping the
+    /// steps they veto, and keeps them to run their `after` function once the project is
+    /// scaffolded
+    ///
+    /// It's called last, once everything else is set, so the scripts see it all.
+    pub fn scripts(mut self, scripts: Scripts) -> Result<Self> {
+        if scripts.is_empty() {
+            return Ok(self);
+        }
+        let before = scripts.before(&self.hook_variables())?;
+        self.variables.extend(before.variables);
+        for step in &before.skipped {
+            self = self.skip(step)?;
+        }
+        self.scripts = scripts;
+        Ok(self)
+    }
+
+    /// Turns off `step`, as a script's `skip` asks
+    fn skip(mut self, step: &str) -> Result<Self> {
+        match step {
+            "license_file" => self.license_file = false,
+            "git" => self.init_git = false,
+            "commit" => self.commit = false,
+            "github_repo" => self.github_repo = GitHubRepo::Skip,
+            "push" => self.push = false,
+            "pre_commit" => self.pre_commit = false,
+            "readme" => self.readme = Readme::Keep,
+            "format" => self.format = false,
+            "direnv" => self.direnv = false,
+            "ci" => self.ci = Ci::Skip,
+            "devcontainer" => self.devcontainer = false,
+            "editorconfig" => self.editorconfig = false,
+            "gitattributes" => self.gitattributes = false,
+            "hooks" => self.hooks = false,
+            _ => {
+                return Err(GetflakeError::InvalidInput(format!(
+                    "scripts can't skip {step}, only {0}",
+                    SKIPPABLE_STEPS.join(", ")
+                )))
+            }
+        }
+        Ok(self)
+    }
+
+    /// Extra placeholders to replace, mapped to their values
+    pub fn variables(mut self, variables: BTreeMap<String, String>) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Glob patterns of the files and folders placeholders aren't replaced in, on top of the ones
+    /// the template lists
+    pub fn ignore(mut self, ignore: Vec<String>) -> Self {
+        self.ignore = ignore;
+        self
+    }
+
+    /// Whether [`Scaffolder::print_plan`] also prints the files the template generates, with the
+    /// placeholders replaced, as a diff from what's in the folder
+    pub fn show_diff(mut self, show_diff: bool) -> Self {
+        self.show_diff = show_diff;
+        self
+    }
+
+    /// Whether the template's flake.nix is merged into the one the folder already has, instead of
+    /// nix refusing to overwrite it
+    pub fn merge_flake(mut self, merge_flake: bool) -> Self {
+        self.merge_flake = merge_flake;
+        self
+    }
+
+    /// Templates whose files are added to the project after the main template's, for projects
+    /// made of several (e.g. `rust` and `docs-mdbook`)
+    pub fn extra_templates(mut self, extra_templates: Vec<String>) -> Self {
+        self.extra_templates = extra_templates;
+        self
+    }
+
+    /// What to do with the files of the extra templates the project already has, instead of
+    /// asking (or keeping both, when there's no one to ask)
+    pub fn on_conflict(mut self, on_conflict: Option<Conflict>) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// The folder the project is created in
+    pub fn directory(&self) -> String {
+        if let Some(target_directory) = &self.target_directory {
+            return target_directory.clone();
+        }
+        match &self.new_or_init {
+            NewOrInit::New => self.project_name.clone(),
+            NewOrInit::Init => ".".to_string(),
+        }
+    }
+
+    /// The placeholders the user gave values to, as opposed to the ones derived from them
+    fn variables_with_defaults(&self) -> BTreeMap<String, String> {
+        let mut variables = self.variables.clone();
+        variables.insert("project_name".to_owned(), self.project_name.clone());
+        variables.insert("project_author".to_owned(), self.author.clone());
+        variables.insert("project_email".to_owned(), self.email.clone());
+        variables.insert("project_description".to_owned(), self.description.clone());
+        variables.insert("project_license".to_owned(), self.license.clone());
+        variables.insert("project_year".to_owned(), current_year().to_string());
+        variables
+    }
+
+    pub fn placeholders(&self) -> Result<Placeholders> {
+        Placeholders::from_variables(&self.variables_with_defaults()).ignoring(&self.ignore)
+    }
+
+    fn commits(&self) -> bool {
+        self.init_git && self.commit
+    }
+
+    fn creates_github_repo(&self) -> bool {
+        self.init_git && self.github_repo != GitHubRepo::Skip
+    }
+
+    fn remote_url(&self) -> Option<&str> {
+        self.remote
+            .as_deref()
+            .filter(|_| self.init_git && !self.creates_github_repo())
+    }
+
+    fn pushes(&self) -> bool {
+        self.commits() && self.push && (self.remote_url().is_some() || self.creates_github_repo())
+    }
+
+    pub fn print_summary(&self) {
+        say!("\n{SUMMARY} You selected: ");
+        say!("- Template: {GREEN}{0}{RESET}", self.template);
+        if !self.extra_templates.is_empty() {
+            say!(
+                "- Along with: {GREEN}{0}{RESET}",
+                self.extra_templates.join(", ")
+            );
+        }
+        say!("- To {GREEN}{0}{RESET}", self.new_or_init);
+        say!("- Project name: {GREEN}{0}{RESET}", self.project_name);
+        if let Some(target_directory) = &self.target_directory {
+            say!("- Folder: {GREEN}{target_directory}{RESET}");
+        }
+        if let Some(existing_directory) = &self.existing_directory {
+            say!("- Existing folder: {GREEN}{existing_directory}{RESET}");
+        }
+        say!("- Author: {GREEN}{0}{RESET}", self.author);
+        say!("- Email: {GREEN}{0}{RESET}", self.email);
+        say!("- Description: {GREEN}{0}{RESET}", self.description);
+        say!("- License: {GREEN}{0}{RESET}", self.license);
+        say!("- Initialize Git: {GREEN}{0}{RESET}", self.init_git);
+        if self.commits() {
+            say!("- Commit message: {GREEN}{0}{RESET}", self.commit_message);
+        }
+        if self.init_git {
+            say!("- Pre-commit hook: {GREEN}{0}{RESET}", self.pre_commit);
+        }
+        if self.creates_github_repo() {
+            say!("- GitHub repository: {GREEN}{0}{RESET}", self.github_repo);
+        }
+        if let Some(remote) = self.remote_url() {
+            say!("- Remote: {GREEN}{remote}{RESET}");
+        }
+        if self.remote_url().is_some() || self.creates_github_repo() {
+            say!("- Push: {GREEN}{0}{RESET}", self.pushes());
+        }
+        say!("- README.md: {GREEN}{0}{RESET}", self.readme);
+        say!("- Format with nix fmt: {GREEN}{0}{RESET}", self.format);
+        say!("- Set up direnv: {GREEN}{0}{RESET}", self.direnv);
+        say!("- CI workflow: {GREEN}{0}{RESET}", self.ci);
+        say!("- Dev container: {GREEN}{0}{RESET}", self.devcontainer);
+        if !self.hooks {
+            say!("- Run hooks: {GREEN}false{RESET}");
+        } else {
+            if !self.pre_hooks.is_empty() {
+                say!(
+                    "- Pre-scaffold hooks: {GREEN}{0}{RESET}",
+                    self.pre_hooks.join(", ")
+                );
+            }
+            if !self.post_hooks.is_empty() {
+                say!(
+                    "- Post-scaffold hooks: {GREEN}{0}{RESET}",
+                    self.post_hooks.join(", ")
+                );
+            }
+        }
+        for (name, value) in &self.variables {
+            say!("- {name}: {GREEN}{value}{RESET}");
+        }
+    }
+
+    pub fn run(&self) -> Result<Report> {
+        say!("\n{START} Initializing project...");
+
+        let directory = self.directory();
+        let mut report = Report {
+            path: PathBuf::from(&directory),
+            template: self.template.clone(),
+            source: self.source.url.clone(),
+            rev: None,
+            files_modified: Vec::new(),
+            commands: Vec::new(),
+            welcome_text: None,
+        };
+
+        if self.hooks {
+            self.run_pre_hooks(&mut report)?;
+        }
+
+        match progress::spin("Resolving the revision", || self.source.resolved_rev()) {
+            Ok(Some(rev)) => {
+                say!(
+                    "{PIN} Using {0} at revision {GREEN}{rev}{RESET}",
+                    self.source.url
+                );
+                report.rev = Some(rev);
+            }
+            Ok(None) => {}
+            // Nothing else would work either
+            Err(GetflakeError::NixNotFound) => return Err(GetflakeError::NixNotFound),
+            Err(e) => eprintln!("- {ERROR}Failed to resolve the revision of the templates\n{e}"),
+        }
+
+        if let Some(parent) = self.missing_parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let merge = matches!(self.existing_directory, Some(ExistingDirectory::Merge));
+        if let (NewOrInit::New, false) = (&self.new_or_init, merge) {
+            // Generate the project next to its folder, and only move it into place once every
+            // step succeeded, so a failure never leaves a half-initialized project behind
+            let staging = staging_directory(&directory);
+            interrupt::set_partial_output(Some(staging.clone()));
+            let generated = self.generate(&staging, None, &mut report);
+            interrupt::set_partial_output(None);
+            if let Err(e) = generated {
+                eprintln!("{CLEAN} Removing the partially generated project...");
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+
+            if let Some(ExistingDirectory::Overwrite) = self.existing_directory {
+                say!("{REMOVE} Removing the existing {directory} folder...");
+                fs::remove_dir_all(&directory)?;
+            }
+            if let Err(e) = fs::rename(&staging, &directory) {
+                eprintln!(
+                    "{CLEAN} Removing the generated project, which couldn't be moved into place..."
+                );
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e.into());
+            }
+
+            let staging_name = staging.to_string_lossy();
+            report.files_modified = report
+                .files_modified
+                .iter()
+                .map(|file| Path::new(&directory).join(file.strip_prefix(&staging).unwrap_or(file)))
+                .collect();
+            report.commands = report
+                .commands
+                .iter()
+                .map(|command| command.replace(staging_name.as_ref(), &directory))
+                .collect();
+
+            if let Err(e) = undo::save_created(Path::new(&directory)) {
+                eprintln!(
+                    "{WARNING} Unable to save what to undo, so `getflake undo` won't work\n{e}"
+                );
+            }
+        } else {
+            // The folder already has files of its own, so only the ones created here are removed
+            let existing = existing_paths(Path::new(&directory));
+            let backup = undo::back_up(&existing);
+            // The initial commit is only undone apart in a repository that was already there
+            let repository_existed = existing.contains(&Path::new(&directory).join(".git"));
+            let parent = undo::head(Path::new(&directory));
+            if let Err(e) = self.generate(Path::new(&directory), Some(&existing), &mut report) {
+                eprintln!("{CLEAN} Removing the files created before the failure...");
+                remove_new_paths(Path::new(&directory), &existing);
+                undo::clear();
+                return Err(e);
+            }
+
+            let created = new_paths(Path::new(&directory), &existing);
+            let commit = undo::head(Path::new(&directory))
+                .filter(|head| {
+                    repository_existed && self.commits() && Some(head) != parent.as_ref()
+                })
+                .map(|id| undo::Commit { id, parent });
+            let saved =
+                backup.and_then(|backup| backup.save(Path::new(&directory), created, commit));
+            if let Err(e) = saved {
+                eprintln!(
+                    "{WARNING} Unable to save what to undo, so `getflake undo` won't work\n{e}"
+                );
+            }
+        }
+
+        // direnv remembers the absolute path of the .envrc, so it's allowed once it's in place
+        if self.direnv && direnv_installed() {
+            let envrc = Path::new(&directory).join(".envrc");
+            let output = Command::new("direnv").arg("allow").arg(&envrc).run_logged();
+            match output {
+                Ok(output) if output.status.success() => {
+                    say!("{DIRENV} Allowed {GREEN}.envrc{RESET}, the dev shell loads when you cd into the project\n");
+                    report.commands.push(format!("direnv allow {0}", envrc.display()));
+                }
+                _ => eprintln!("- {ERROR}Failed to run direnv allow, run it yourself from the project's folder\n"),
+            }
+        }
+
+        // nix already printed it, but it has scrolled away by now
+        if let Ok(TemplateDetails {
+            welcome_text: Some(welcome_text),
+            ..
+        }) = progress::spin("Reading the welcome text", || {
+            self.source.template_details(&self.template)
+        }) {
+            if !output::is_json() {
+                say!("{WELCOME} Welcome text from the template:");
+                colors::print_markdown(&welcome_text);
+                say!();
+            }
+            report.welcome_text = Some(welcome_text);
+        }
+
+        say!("{DONE} Done!");
+
+        Ok(report)
+    }
+
+    /// Runs every step that creates or changes files, in `directory`
+    ///
+    /// `existing` are the paths that were in `directory` before, when it's not a new folder.
+    fn generate(
+        &self,
+        directory: &Path,
+        existing: Option<&HashSet<PathBuf>>,
+        report: &mut Report,
+    ) -> Result<()> {
+        // Set aside so the template's is written, and merged into it afterwards
+        let flake = directory.join("flake.nix");
+        let project_flake = match self.merge_flake && flake.is_file() {
+            true => Some(fs::read_to_string(&flake)?),
+            false => None,
+        };
+        if project_flake.is_some() {
+            fs::remove_file(&flake)?;
+        }
+
+        let generated = self.copy_template(directory, report).and_then(|()| {
+            if let Some(project_flake) = &project_flake {
+                self.merge_flake_into(directory, project_flake, report)?;
+            }
+            for template in &self.extra_templates {
+                self.add_template(template, directory, report)?;
+            }
+            self.post_process(directory, existing, report)
+                .map_err(|e| GetflakeError::PostProcessFailed(Box::new(e)))
+        });
+        if let (Err(_), Some(project_flake)) = (&generated, &project_flake) {
+            let _ = fs::write(&flake, project_flake);
+        }
+        generated
+    }
+
+    /// Copies the template into `directory`, with nix or from the downloaded templates
+    fn copy_template(&self, directory: &Path, report: &mut Report) -> Result<()> {
+        if self.source.uses_nix() {
+            let (mut command, command_string) = self.nix_command(directory);
+
+            say!("{NIX} Running {GREEN}{command_string}{RESET} ...");
+            // The welcome text it prints is shown once everything is done instead
+            let output = progress::spin("Creating the project", || command.run_logged())
+                .map_err(GetflakeError::from_nix_io)?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(GetflakeError::ScaffoldFailed {
+                    template: self.template.clone(),
+                    command: command_string,
+                    message: nix_failure(&stderr, &output.status.to_string()),
+                });
+            }
+            report.commands.push(command_string);
+        } else if let Some(local_path) = self.source.local_path() {
+            say!(
+                "{FETCH} Copying the {GREEN}{0}{RESET} template from {1}...",
+                self.template,
+                local_path.display()
+            );
+            report.commands.push(progress::spin("Copying", || {
+                self.source.copy_template(&self.template, directory)
+            })?);
+        } else {
+            say!(
+                "{FETCH} Downloading the {GREEN}{0}{RESET} template from GitHub...",
+                self.template
+            );
+            report.commands.push(progress::spin("Downloading", || {
+                self.source.copy_template(&self.template, directory)
+            })?);
+        }
+        say!("{CREATED} Created project {GREEN}successfully{RESET}\n");
+        Ok(())
+    }
+
+    /// Adds the files of `template` to the project in `directory`, merging the ones it already has
+    /// when it can and resolving the other conflicts as [`Scaffolder::on_conflict`] says
+    fn add_template(&self, template: &str, directory: &Path, report: &mut Report) -> Result<()> {
+        say!("{FETCH} Adding the {GREEN}{template}{RESET} template...");
+        let staging = env::temp_dir().join(format!("getflake-{0}-{template}", process::id()));
+        let _ = fs::remove_dir_all(&staging);
+        let copied = progress::spin("Copying", || self.source.copy_template(template, &staging))
+            .and_then(|command| {
+                report.commands.push(command);
+                let written = add_files(template, &staging, &staging, directory, self.on_conflict)?;
+                report.files_modified.extend(written);
+                Ok(())
+            });
+        let _ = fs::remove_dir_all(&staging);
+        copied?;
+        say!("{CREATED} Added the {GREEN}{template}{RESET} template {GREEN}successfully{RESET}\n");
+        Ok(())
+    }
+
+    /// Runs the pre-scaffold hooks in the current folder, before the project is generated
+    ///
+    /// Nothing was created yet, so the first to fail stops the run, e.g. when the name breaks a
+    /// naming policy.
+    fn run_pre_hooks(&self, report: &mut Report) -> Result<()> {
+        if self.pre_hooks.is_empty() {
+            return Ok(());
+        }
+        say!("{HOOK} Running the pre-scaffold hooks...");
+        let variables = self.hook_variables();
+        for hook in &self.pre_hooks {
+            if let Err(e) = hooks::run(hook, Path::new("."), &variables) {
+                eprintln!(
+                    "- {ERROR}The pre-scaffold hook {hook} failed, so the project wasn't created"
+                );
+                return Err(e);
+            }
+            say!("- {OK} {hook}");
+            report.commands.push(hook.clone());
+        }
+        say!("{HOOK} Ran the pre-scaffold hooks {GREEN}successfully{RESET}\n");
+        Ok(())
+    }
+
+    /// The placeholders' values, the template and the project's folder, which hooks get as
+    /// environment variables
+    ///
+    /// The folder is the project's final one, as an absolute path, even while it's generated
+    /// somewhere else.
+    fn hook_variables(&self) -> BTreeMap<String, String> {
+        let directory = self.directory();
+        let directory = path::absolute(&directory).unwrap_or_else(|_| PathBuf::from(directory));
+        let mut variables = self.variables_with_defaults();
+        variables.insert("template".to_owned(), self.template.clone());
+        variables.insert(
+            "directory".to_owned(),
+            directory.to_string_lossy().into_owned(),
+        );
+        variables
+    }
+
+    /// Runs each of `hooks` in `directory`, saying whether it succeeded
+    ///
+    /// The project is there either way, so a failing hook doesn't stop the others, and it's
+    /// printed so it can be run again by hand.
+    fn run_post_hooks(&self, directory: &Path, hooks: &[&String], report: &mut Report) {
+        if hooks.is_empty() {
+            return;
+        }
+        say!("{HOOK} Running the post-scaffold hooks...");
+        let variables = self.hook_variables();
+        let mut failed = 0;
+        for hook in hooks {
+            match hooks::run(hook, directory, &variables) {
+                Ok(()) => {
+                    say!("- {OK} {hook}");
+                    report
+                        .commands
+                        .push(format!("cd {0} && {hook}", directory.display()));
+                }
+                Err(e) => {
+                    eprintln!("- {ERROR}{e}");
+                    failed += 1;
+                }
+            }
+        }
+        if failed == 0 {
+            say!("{HOOK} Ran the post-scaffold hooks {GREEN}successfully{RESET}\n");
+        } else {
+            eprintln!(
+                "{WARNING} {failed} of the {0} post-scaffold hooks failed, run them yourself from the project's folder\n",
+                hooks.len()
+            );
+        }
+    }
+
+    /// Installs the template's pre-commit hooks, or else one running `nix flake check`
+    ///
+    /// The project is usable without them, so failing only prints how to install them by hand.
+    fn install_pre_commit(&self, directory: &Path, report: &mut Report) {
+        if directory.join(".pre-commit-config.yaml").is_file() && pre_commit_installed() {
+            say!("{HOOK} Installing the template's pre-commit hooks...");
+            match Self::pre_commit_install(directory) {
+                Ok(command) => {
+                    report.commands.push(command);
+                    say!("{HOOK} Installed the pre-commit hooks {GREEN}successfully{RESET}\n");
+                }
+                Err(e) => eprintln!("- {ERROR}Failed to install the pre-commit hooks, run pre-commit install yourself from the project's folder\n{e}\n"),
+            }
+            return;
+        }
+        match postprocess::write_pre_commit_hook(directory) {
+            Ok(Some(path)) => {
+                say!("{HOOK} Installed a pre-commit hook running {GREEN}nix flake check{RESET}\n");
+                report.files_modified.push(path);
+            }
+            Ok(None) => say!("{HOOK} Left the repository's own pre-commit hook in place\n"),
+            Err(e) => eprintln!("- {ERROR}Failed to install the pre-commit hook\n{e}\n"),
+        }
+    }
+
+    /// Runs `pre-commit install` in `directory`, returning how the command is displayed to the
+    /// user
+    fn pre_commit_install(directory: &Path) -> Result<String> {
+        let command_string = format!("cd {0} && pre-commit install", directory.display());
+        let output = Command::new("pre-commit")
+            .arg("install")
+            .current_dir(directory)
+            .run_logged()?;
+        if !output.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: command_string,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(command_string)
+    }
+
+    /// Merges the template's flake.nix, just written to `directory`, into `project_flake`
+    fn merge_flake_into(
+        &self,
+        directory: &Path,
+        project_flake: &str,
+        report: &mut Report,
+    ) -> Result<()> {
+        let path = directory.join("flake.nix");
+        let template_flake = fs::read_to_string(&path)?;
+        match flake::merge(project_flake, &template_flake, &self.template) {
+            Some(merged) => {
+                fs::write(&path, merged)?;
+                say!("{MERGE} Merged the template's inputs and outputs into {GREEN}flake.nix{RESET}\n");
+            }
+            // Kept next to it, to be merged by hand
+            None => {
+                let aside = directory.join(format!("flake.{0}.nix", self.template));
+                fs::write(&aside, template_flake)?;
+                fs::write(&path, project_flake)?;
+                eprintln!(
+                    "{WARNING} Unable to find the inputs and outputs of flake.nix, so the template's was saved as {0} to merge by hand\n",
+                    aside.to_string_lossy()
+                );
+                report.files_modified.push(aside);
+            }
+        }
+        report.files_modified.push(path);
+        Ok(())
+    }
+
+    /// Runs every step after the template was copied into `directory`, which had the `existing`
+    /// paths before if it's not a new folder
+    fn post_process(
+        &self,
+        directory: &Path,
+        existing: Option<&HashSet<PathBuf>>,
+        report: &mut Report,
+    ) -> Result<()> {
+        let manifest = Manifest::load(directory)?;
+        for path in manifest.apply(directory, &self.variables_with_defaults())? {
+            say!(
+                "- {OK} Left out {0}, as the template's conditions say",
+                relative(directory, &path)
+            );
+        }
+
+        say!("{UPDATE} Updating project details...");
+        for path in postprocess::render_tera(directory, &self.variables_with_defaults())? {
+            say!("- {OK} Rendered {0}", relative(directory, &path));
+            report.files_modified.push(path);
+        }
+        let placeholders = self.placeholders()?.ignoring(&manifest.ignore)?;
+        report
+            .files_modified
+            .extend(progress::spin("Replacing placeholders", || {
+                postprocess::replace_placeholders(directory, &placeholders)
+            }));
+
+        if !self.description.is_empty() {
+            for path in postprocess::set_description(directory, &self.description)? {
+                say!(
+                    "- {OK} Set the description in {0}",
+                    relative(directory, &path)
+                );
+                report.files_modified.push(path);
+            }
+        }
+
+        if let Some(license) = license::find(&self.license).filter(|_| self.license_file) {
+            match progress::spin("Writing the license", || {
+                license::write_license(directory, license, &self.author, current_year())
+            }) {
+                Ok(()) => {
+                    say!("- {OK} Wrote LICENSE ({0})", license.id);
+                    report.files_modified.push(directory.join("LICENSE"));
+                }
+                // The project is still usable, and the license can be added by hand
+                Err(e) => eprintln!("- {ERROR}Failed to write LICENSE\n{e}"),
+            }
+            for path in license::set_license_fields(directory, license)? {
+                say!("- {OK} Set the license in {0}", relative(directory, &path));
+                report.files_modified.push(path);
+            }
+        }
+
+        say!();
+
+        // Before Git is initialized, since nix ignores the files Git doesn't track
+        if self.format && self.source.uses_nix() && has_formatter(directory) {
+            say!("{FORMAT} Formatting the project with {GREEN}nix fmt{RESET}...");
+            match Self::nix_fmt(directory) {
+                Ok(command) => {
+                    report.commands.push(command);
+                    say!("{FORMAT} Formatted the project {GREEN}successfully{RESET}\n");
+                }
+                // Unformatted files still work
+                Err(e) => eprintln!("- {ERROR}Failed to format the project\n{e}\n"),
+            }
+        }
+
+        if self.init_git {
+            say!("{GIT} Initializing Git repository...");
+            report.commands.push(Self::git(directory, &["init"])?);
+
+            if postprocess::write_gitignore(directory, &self.template)? {
+                say!("- {OK} Created .gitignore");
+                report.files_modified.push(directory.join(".gitignore"));
+            }
+            say!("{GIT} Initialized Git repository {GREEN}successfully{RESET}\n");
+        }
+
+        if self.direnv && postprocess::write_envrc(directory)? {
+            say!("{DIRENV} Created .envrc with {GREEN}use flake{RESET}\n");
+            report.files_modified.push(directory.join(".envrc"));
+        }
+
+        if let Some(path) = postprocess::write_ci(directory, self.ci, &self.project_name)? {
+            say!(
+                "{CI} Created {GREEN}{0}{RESET}, which runs nix flake check and nix build\n",
+                path.strip_prefix(directory).unwrap_or(&path).display()
+            );
+            report.files_modified.push(path);
+        }
+
+        if self.editorconfig && postprocess::write_editorconfig(directory, &self.template)? {
+            say!("- {OK} Created .editorconfig");
+            report.files_modified.push(directory.join(".editorconfig"));
+        }
+        if self.gitattributes && postprocess::write_gitattributes(directory, &self.template)? {
+            say!("- {OK} Created .gitattributes");
+            report.files_modified.push(directory.join(".gitattributes"));
+        }
+
+        if self.devcontainer && postprocess::write_devcontainer(directory, &self.project_name)? {
+            say!("{CONTAINER} Created .devcontainer/devcontainer.json, with {GREEN}nix develop{RESET} as the shell\n");
+            report
+                .files_modified
+                .push(directory.join(".devcontainer").join("devcontainer.json"));
+        }
+
+        match self.readme {
+            Readme::Keep => {}
+            Readme::Retitle => {
+                say!("{CLEAN} Retitling README.md file...");
+                if postprocess::retitle_readme(directory, &self.project_name, &self.description)? {
+                    report.files_modified.push(directory.join("README.md"));
+                    say!("{CLEAN} Retitled README.md file {GREEN}successfully{RESET}\n");
+                } else {
+                    say!("{CLEAN} The template has no README.md file to retitle\n");
+                }
+            }
+            Readme::Replace => {
+                say!("{CLEAN} Clearing README.md file...");
+                postprocess::clear_readme(directory, &self.variables_with_defaults())?;
+                report.files_modified.push(directory.join("README.md"));
+                say!("{CLEAN} Cleared README.md file {GREEN}successfully{RESET}\n");
+            }
+        }
+
+        if !self.scripts.is_empty() {
+            say!("{HOOK} Running the scripts...");
+            for path in self.scripts.after(&self.hook_variables(), directory)? {
+                say!("- {OK} Rewrote {0}", relative(directory, &path));
+                report.files_modified.push(path);
+            }
+            say!();
+        }
+
+        Provenance::new(
+            &self.template,
+            &self.source.url,
+            report.rev.clone(),
+            self.variables_with_defaults(),
+        )
+        .write(directory)?;
+        say!(
+            "{PROVENANCE} Recorded how the project was generated in {GREEN}{0}{RESET}\n",
+            provenance::FILE_NAME
+        );
+        report
+            .files_modified
+            .push(directory.join(provenance::FILE_NAME));
+
+        if self.hooks {
+            let hooks: Vec<&String> = manifest.hooks.post.iter().chain(&self.post_hooks).collect();
+            self.run_post_hooks(directory, &hooks, report);
+        }
+
+        if self.commits() {
+            say!("{COMMIT} Creating initial commit...");
+            report
+                .commands
+                .push(Self::stage(directory, existing, report)?);
+            report.commands.push(Self::git(
+                directory,
+                &["commit", "-m", &self.commit_message],
+            )?);
+            say!("{COMMIT} Created initial commit {GREEN}successfully{RESET}\n");
+        }
+
+        // After the initial commit, which would otherwise wait on the checks
+        if self.init_git && self.pre_commit {
+            self.install_pre_commit(directory, report);
+        }
+
+        if self.creates_github_repo() {
+            say!(
+                "{GIT} Creating the {0} GitHub repository {GREEN}{1}{RESET}...",
+                self.github_repo,
+                self.project_name
+            );
+            // Like pushing, it's done by hand if it fails, without throwing the project away
+            match self.create_github_repo(directory) {
+                Ok(command) => {
+                    report.commands.push(command);
+                    say!("{GIT} Created the GitHub repository {GREEN}successfully{RESET}\n");
+                }
+                Err(e) => {
+                    eprintln!("- {ERROR}Failed to create the GitHub repository\n{e}");
+                    eprintln!("{HINT} Create it yourself with `gh repo create --source . --remote origin`\n");
+                }
+            }
+        } else if let Some(remote) = self.remote_url() {
+            report
+                .commands
+                .push(Self::git(directory, &["remote", "add", "origin", remote])?);
+            say!("{GIT} Added {GREEN}{remote}{RESET} as the origin remote");
+
+            if self.pushes() {
+                say!("{PUSH} Pushing the initial commit...");
+                // The project is complete either way, so it's pushed again by hand if this fails
+                match Self::git(directory, &["push", "--set-upstream", "origin", "HEAD"]) {
+                    Ok(command) => {
+                        report.commands.push(command);
+                        say!("{PUSH} Pushed to {GREEN}{remote}{RESET} successfully");
+                    }
+                    Err(e) => {
+                        eprintln!("- {ERROR}Failed to push\n{e}");
+                        eprintln!(
+                            "{HINT} Push it yourself with `git push --set-upstream origin HEAD`"
+                        );
+                    }
+                }
+            }
+            say!();
+        }
+
+        Ok(())
+    }
+
+    /// Whether there's a dev shell to enter with [`Scaffolder::develop`]
+    pub fn can_develop(&self) -> bool {
+        self.source.uses_nix()
+    }
+
+    /// Enters the dev shell of the project created by [`Scaffolder::run`] with `nix develop`,
+    /// returning once the user exits it
+    pub fn develop(&self, report: &Report) -> Result<()> {
+        say!("{NIX} Entering the dev shell, exit it to get back");
+        let mut command = Command::new("nix");
+        command
+            .args([
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "develop",
+            ])
+            .current_dir(&report.path);
+        interrupt::in_foreground(|| command.status()).map_err(GetflakeError::from_nix_io)?;
+        // The exit status is the one of the last command run in the shell, not getflake's concern
+        Ok(())
+    }
+
+    /// Prints what [`Scaffolder::run`] would do, without changing anything
+    pub fn print_plan(&self) -> Result<()> {
+        let directory = self.directory();
+        let (_, command_string) = self.nix_command(Path::new(&directory));
+
+        say!("\n{PLAN} Dry run, nothing will be changed. getflake would:");
+        if let Some(parent) = self.missing_parent() {
+            say!("- Create the {0} folder", parent.display());
+        }
+        if let Some(ExistingDirectory::Overwrite) = self.existing_directory {
+            say!("- Remove the existing {directory} folder");
+        }
+        if self.source.uses_nix() {
+            say!("- Run {GREEN}{command_string}{RESET}");
+        } else {
+            match self.source.copy_command(&self.template) {
+                Ok(command_string) => say!("- Run {GREEN}{command_string}{RESET}"),
+                Err(e) => eprintln!("- Unable to determine where to copy the template from\n{e}"),
+            }
+        }
+        say!("- Create the project in {GREEN}{directory}{RESET}");
+        if self.merge_flake && Path::new(&directory).join("flake.nix").is_file() {
+            say!("- Merge the template's inputs and outputs into {directory}/flake.nix");
+        }
+        for template in &self.extra_templates {
+            say!("- Add the files of the {GREEN}{template}{RESET} template, merging flake.nix and .gitignore into the project's");
+        }
+
+        // Copied the way the run would, so it works without nix too
+        let copied = TempDir::new().and_then(|temp_dir| {
+            let template_path = temp_dir.path().join("template");
+            progress::spin("Fetching the template", || {
+                self.source.copy_template(&self.template, &template_path)
+            })?;
+            Ok((temp_dir, template_path))
+        });
+        match copied {
+            Ok((_temp_dir, template_path)) => {
+                let manifest = Manifest::load(&template_path).unwrap_or_default();
+                let placeholders = self.placeholders()?.ignoring(&manifest.ignore)?;
+                let files = placeholders.files_containing(&template_path);
+                let paths = placeholders.paths_containing(&template_path);
+                if files.is_empty() && paths.is_empty() {
+                    say!("- Find no placeholders to replace");
+                }
+                for file in files {
+                    let file = Path::new(&directory)
+                        .join(file.strip_prefix(&template_path).unwrap_or(&file));
+                    say!("- Replace placeholders in {0}", file.to_string_lossy());
+                }
+                for path in paths {
+                    let path = Path::new(&directory)
+                        .join(path.strip_prefix(&template_path).unwrap_or(&path));
+                    let path_name = path.to_string_lossy();
+                    say!(
+                        "- Rename {path_name} to {GREEN}{0}{RESET}",
+                        placeholders.replace(&path_name)
+                    );
+                }
+            }
+            Err(e) => eprintln!("- Unable to determine the files of the template\n{e}"),
+        }
+
+        if !self.description.is_empty() {
+            say!("- Set the description in flake.nix, Cargo.toml, pyproject.toml and README.md, if there are any");
+        }
+        if let Some(license) = license::find(&self.license).filter(|_| self.license_file) {
+            say!(
+                "- Write {directory}/LICENSE with the {GREEN}{0}{RESET} license",
+                license.id
+            );
+            say!("- Set the license in Cargo.toml, pyproject.toml and flake.nix, if there are any");
+        }
+        if self.format && self.source.uses_nix() {
+            say!("- Run {GREEN}cd {directory} && nix fmt{RESET} if the template has a formatter");
+        }
+        if self.init_git {
+            say!("- Run {GREEN}git -C {directory} init{RESET}");
+            say!("- Create {directory}/.gitignore if the template has none");
+        }
+        match self.readme {
+            Readme::Keep => {}
+            Readme::Retitle => {
+                say!(
+                    "- Retitle {directory}/README.md {GREEN}{0}{RESET}",
+                    self.project_name
+                )
+            }
+            Readme::Replace => {
+                say!("- Replace {directory}/README.md with one rendered from the README template")
+            }
+        }
+        if self.direnv {
+            say!(
+                "- Create {directory}/.envrc with {GREEN}use flake{RESET} if the template has none"
+            );
+            if direnv_installed() {
+                say!("- Run {GREEN}direnv allow {directory}/.envrc{RESET}");
+            }
+        }
+        if let Some(file) = postprocess::ci_file(self.ci) {
+            say!("- Create {directory}/{file}, running nix flake check and nix build, if the template has none");
+        }
+        if self.editorconfig {
+            say!("- Create {directory}/.editorconfig if the template has none");
+        }
+        if self.gitattributes {
+            say!("- Create {directory}/.gitattributes if the template has none");
+        }
+        if self.devcontainer {
+            say!("- Create {directory}/.devcontainer/devcontainer.json with Nix, if the template has none");
+        }
+        say!(
+            "- Record how the project was generated in {directory}/{0}",
+            provenance::FILE_NAME
+        );
+        if !self.scripts.is_empty() {
+            say!("- Run the {GREEN}after{RESET} function of the scripts, if they have one");
+        }
+        if self.hooks {
+            for hook in &self.pre_hooks {
+                say!(
+                    "- Run {GREEN}{hook}{RESET} before copying the template, stopping if it fails"
+                );
+            }
+            for hook in &self.post_hooks {
+                say!("- Run {GREEN}cd {directory} && {hook}{RESET}");
+            }
+            say!("- Run the post-scaffold hooks the template's getflake.toml declares, if any");
+        }
+        if self.commits() {
+            say!(
+                "- Commit all files with the message {GREEN}{0}{RESET}",
+                self.commit_message
+            );
+        }
+        if self.init_git && self.pre_commit {
+            say!("- Install the template's pre-commit hooks with {GREEN}pre-commit install{RESET}, or else a hook running {GREEN}nix flake check{RESET}");
+        }
+        if self.creates_github_repo() {
+            let (_, command_string) = self.gh_repo_create_command(Path::new(&directory));
+            say!("- Run {GREEN}{command_string}{RESET}");
+        } else if let Some(remote) = self.remote_url() {
+            say!("- Run {GREEN}git -C {directory} remote add origin {remote}{RESET}");
+        }
+        if self.pushes() && !self.creates_github_repo() {
+            say!("- Run {GREEN}git -C {directory} push --set-upstream origin HEAD{RESET}");
+        }
+
+        if self.show_diff {
+            self.print_rendered(Path::new(&directory))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the files the template generates in `directory`, as a diff from the ones there
+    ///
+    /// Files that are already there are left alone when initializing, like `nix flake init` does,
+    /// so they're only listed.
+    fn print_rendered(&self, directory: &Path) -> Result<()> {
+        let rendered = render::render(
+            &self.source,
+            &self.template,
+            &self.variables_with_defaults(),
+        )?;
+        let overwritten = matches!(self.existing_directory, Some(ExistingDirectory::Overwrite));
+
+        say!("\n{PLAN} The files would be:");
+        for file in render::files(rendered.path()) {
+            let file_name = directory.join(&file).to_string_lossy().into_owned();
+            let new = fs::read(rendered.path().join(&file))?;
+            match fs::read(directory.join(&file)) {
+                Ok(_) if !overwritten => {
+                    say!("- {file_name} is already there, and would be left alone");
+                }
+                Ok(old) if old != new => print_unified(&file_name, &file_name, old, new),
+                Ok(_) => say!("- {file_name} would stay the same"),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    print_unified("/dev/null", &file_name, Vec::new(), new)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Stages the files of the project in `directory` for the initial commit, returning how the
+    /// command is displayed to the user
+    ///
+    /// In a folder that had `existing` paths before, only the files created or changed here are
+    /// staged, so the user's own files stay out of the commit.
+    fn stage(
+        directory: &Path,
+        existing: Option<&HashSet<PathBuf>>,
+        report: &Report,
+    ) -> Result<String> {
+        let Some(existing) = existing else {
+            return Self::git(directory, &["add", "-A"]);
+        };
+        let generated: BTreeSet<String> = new_paths(directory, existing)
+            .iter()
+            .chain(&report.files_modified)
+            .filter(|path| path.is_file() && !path.starts_with(directory.join(".git")))
+            .filter_map(|path| path.strip_prefix(directory).ok())
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        // Naming an ignored file fails the whole command, so they're left out
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(["check-ignore", "--"])
+            .args(&generated)
+            .run_logged()?;
+        let ignored: BTreeSet<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_owned)
+            .collect();
+
+        let mut args = vec!["add", "--"];
+        args.extend(
+            generated
+                .iter()
+                .filter(|path| !ignored.contains(*path))
+                .map(String::as_str),
+        );
+        Self::git(directory, &args)
+    }
+
+    /// Runs git in `directory`, returning how the command is displayed to the user
+    fn git(directory: &Path, args: &[&str]) -> Result<String> {
+        let command_string = format!("git -C {0} {1}", directory.display(), args.join(" "));
+        let output = progress::spin(&format!("Running git {0}", args[0]), || {
+            Command::new("git")
+                .arg("-C")
+                .arg(directory)
+                .args(args)
+                .run_logged()
+        })?;
+        if !output.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: command_string,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(command_string)
+    }
+
+    /// Runs the flake's formatter in `directory`, returning how the command is displayed to the user
+    fn nix_fmt(directory: &Path) -> Result<String> {
+        let command_string = format!("cd {0} && nix fmt", directory.display());
+        let output = progress::spin("Formatting", || {
+            Command::new("nix")
+                .args(["--extra-experimental-features", "nix-command flakes", "fmt"])
+                .current_dir(directory)
+                .run_logged()
+        })
+        .map_err(GetflakeError::from_nix_io)?;
+        if !output.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: command_string,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(command_string)
+    }
+
+    /// Creates the GitHub repository of the project in `directory`, returning how the command is
+    /// displayed to the user
+    fn create_github_repo(&self, directory: &Path) -> Result<String> {
+        let (mut command, command_string) = self.gh_repo_create_command(directory);
+        let output = progress::spin("Creating the GitHub repository", || command.run_logged())?;
+        if !output.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: command_string,
+                message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(command_string)
+    }
+
+    /// Returns the `gh repo create` command creating the GitHub repository of the project in
+    /// `directory`, wired up as its `origin` remote, and how it's displayed to the user
+    fn gh_repo_create_command(&self, directory: &Path) -> (Command, String) {
+        let visibility = format!("--{0}", self.github_repo);
+        let mut args = vec![
+            "repo",
+            "create",
+            self.project_name.as_str(),
+            visibility.as_str(),
+            "--source",
+            ".",
+            "--remote",
+            "origin",
+        ];
+        if !self.description.is_empty() {
+            args.extend(["--description", self.description.as_str()]);
+        }
+        if self.pushes() {
+            args.push("--push");
+        }
+
+        let mut command = Command::new("gh");
+        command.args(&args).current_dir(directory);
+        let command_string = format!("cd {0} && gh {1}", self.directory(), args.join(" "));
+        (command, command_string)
+    }
+
+    /// The folder the project is generated in that doesn't exist yet, and has to be created first
+    ///
+    /// `nix flake new` creates the project's own folder, but not the ones leading to it.
+    fn missing_parent(&self) -> Option<PathBuf> {
+        let directory = PathBuf::from(self.directory());
+        let parent = match self.new_or_init {
+            NewOrInit::New => directory.parent()?.to_path_buf(),
+            NewOrInit::Init => directory,
+        };
+        (!parent.as_os_str().is_empty() && !parent.exists()).then_some(parent)
+    }
+
+    /// Returns the `nix flake new/init` command generating the project in `directory`, and how
+    /// it's displayed to the user
+    fn nix_command(&self, directory: &Path) -> (Command, String) {
+        let url = format!("{}#{}", self.source.url.as_str(), self.template.as_str());
+
+        // Merging runs `nix flake init` from within the existing folder
+        let merge = matches!(self.existing_directory, Some(ExistingDirectory::Merge));
+        let new_or_init_string = if merge {
+            NewOrInit::Init.to_string()
+        } else {
+            self.new_or_init.to_string()
+        };
+        let new_or_init = new_or_init_string.as_str();
+
+        let args = [
+            "--extra-experimental-features",
+            "nix-command flakes",
+            "flake",
+            new_or_init,
+            "--template",
+            &url,
+        ];
+
+        let mut command_string = "nix ".to_string() + args.join(" ").as_str();
+
+        let mut command = nix_command();
+
+        command.args(args);
+
+        if merge {
+            command.current_dir(directory);
+            command_string = format!("cd {0} && {command_string}", self.directory());
+        } else if let NewOrInit::New = self.new_or_init {
+            command.arg(directory);
+            command_string.push_str(format!(" {0}", self.directory()).as_str());
+        } else {
+            command.current_dir(directory);
+            if self.target_directory.is_some() {
+                command_string = format!("cd {0} && {command_string}", self.directory());
+            }
+        }
+
+        (command, command_string)
+    }
+}
+
+/// Explains why `nix flake new/init` failed, from what it printed to stderr
+fn nix_failure(stderr: &str, status: &str) -> String {
+    let stderr = stderr.trim();
+    let mut message = if stderr.is_empty() {
+        format!("  nix {status}")
+    } else {
+        stderr
+            .lines()
+            .map(|line| format!("  {line}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let hint = if stderr.contains("does not provide attribute") {
+        Some("The template doesn't exist, see `getflake list` for the available ones")
+    } else if stderr.contains("refusing to overwrite") || stderr.contains("already exists") {
+        Some("The folder already has files the template would overwrite, use --force or pick another name")
+    } else if stderr.contains("error: ") && stderr.contains("evaluat") {
+        Some("The template's flake failed to evaluate, which is a problem with the template repository")
+    } else if is_auth_failure(stderr) {
+        Some("The template repository needs authentication: check that your SSH key is loaded, or set a token in `access_tokens` in the config file")
+    } else if stderr.contains("unable to download") || stderr.contains("Could not resolve host") {
+        Some("Check your network connection, or run `getflake doctor`")
+    } else {
+        None
+    };
+    if let Some(hint) = hint {
+        message.push_str(&format!("\n{HINT} {hint}"));
+    }
+
+    message
+}
+
+/// Whether the flake in `directory` declares a formatter for `nix fmt`
+///
+/// Evaluating it would take as long as running it, so this only looks for the output's name.
+/// `path` relative to the project in `directory`, which is how messages show it, since the project
+/// may still be generated in another folder
+fn relative(directory: &Path, path: &Path) -> String {
+    path.strip_prefix(directory)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn has_formatter(directory: &Path) -> bool {
+    fs::read_to_string(directory.join("flake.nix")).is_ok_and(|flake| flake.contains("formatter"))
+}
+
+/// Adds the files of `template` under `from` to `directory`, at the same place relative to it as
+/// they are to `root`, and returns the ones written
+///
+/// The files `directory` already has are merged when they're `flake.nix` or `.gitignore`, and
+/// otherwise resolved as `on_conflict` says, asking when it's `None` and there's a terminal.
+pub fn add_files(
+    template: &str,
+    root: &Path,
+    from: &Path,
+    directory: &Path,
+    on_conflict: Option<Conflict>,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for entry in WalkDir::new(from)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let target = directory.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if !target.exists() {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(entry.path(), &target)?;
+            written.push(target);
+            continue;
+        }
+
+        let ours = fs::read(&target)?;
+        let theirs = fs::read(entry.path())?;
+        if ours == theirs {
+            continue;
+        }
+        let name = relative.to_string_lossy();
+        let merged = match name.as_ref() {
+            "flake.nix" => flake::merge(
+                &String::from_utf8_lossy(&ours),
+                &String::from_utf8_lossy(&theirs),
+                template,
+            ),
+            ".gitignore" => Some(merge_lines(
+                &String::from_utf8_lossy(&ours),
+                &String::from_utf8_lossy(&theirs),
+            )),
+            _ => None,
+        };
+        if let Some(merged) = merged {
+            fs::write(&target, merged)?;
+            say!("- {OK} Merged the {template} template's {name} into the project's");
+            written.push(target);
+            continue;
+        }
+
+        let conflict = match on_conflict {
+            Some(conflict) => conflict,
+            None if prompt::is_interactive() => prompt::conflict(&name, template)?,
+            None => Conflict::Both,
+        };
+        match conflict {
+            Conflict::Keep => say!("- {OK} Kept the project's {name}"),
+            Conflict::Replace => {
+                fs::write(&target, theirs)?;
+                say!("- {OK} Replaced {name} with the {template} template's");
+                written.push(target);
+            }
+            Conflict::Both => {
+                let mut aside = target.into_os_string();
+                aside.push(format!(".{template}"));
+                let aside = PathBuf::from(aside);
+                fs::write(&aside, theirs)?;
+                say!(
+                    "- {OK} Kept the project's {name}, and saved the {template} template's as {0}",
+                    aside.to_string_lossy()
+                );
+                written.push(aside);
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Returns `ours` with the lines of `theirs` it doesn't have added at the end
+fn merge_lines(ours: &str, theirs: &str) -> String {
+    let mut merged = ours.to_owned();
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    for line in theirs.lines() {
+        if !ours.lines().any(|other| other == line) {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+/// Where a new project is generated before being moved into `directory`
+fn staging_directory(directory: &str) -> PathBuf {
+    let parent = Path::new(directory)
+        .parent()
+        .unwrap_or_else(|| Path::new("."));
+    parent.join(format!(".getflake-{0}", process::id()))
+}
+
+/// Returns every file and folder under `directory`, without looking into `.git`
+fn existing_paths(directory: &Path) -> HashSet<PathBuf> {
+    let mut paths: HashSet<PathBuf> = WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .collect();
+    // Otherwise an existing repository would count as new, and be removed along with the rest
+    let git = directory.join(".git");
+    if git.exists() {
+        paths.insert(git);
+    }
+    paths
+}
+
+/// Removes the files and folders under `directory` that aren't in `existing`
+fn remove_new_paths(directory: &Path, existing: &HashSet<PathBuf>) {
+    // Folders come before their contents, which are removed along with them
+    for path in new_paths(directory, existing) {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        if result.is_err() && path.exists() {
+            eprintln!("- {ERROR}Failed to remove {0}", path.to_string_lossy());
+        }
+    }
+}
+
+/// Returns the files and folders under `directory` that aren't in `existing`, folders first
+fn new_paths(directory: &Path, existing: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| !existing.contains(entry.path()) || entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| !existing.contains(path))
+        .collect()
+}
+
+/// Replaces a leading `~` in `path` with the home folder, as the shell would
+pub fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return path.to_owned(),
+    };
+    match env::var("HOME") {
+        Ok(home) => format!("{home}{rest}"),
+        Err(_) => path.to_owned(),
+    }
+}
+
+/// The editor projects are opened in when none is given: `$VISUAL`, `$EDITOR`, or VS Code
+pub fn default_editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "code".to_owned())
+}
+
+/// Opens `directory` with `editor`, a command with optional arguments (e.g. `code --new-window`),
+/// returning once the editor does
+pub fn open_in_editor(editor: &str, directory: &Path) -> Result<()> {
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err(GetflakeError::InvalidInput(
+            "the editor can't be empty".to_owned(),
+        ));
+    };
+
+    say!("{OPEN} Opening the project with {GREEN}{editor}{RESET}");
+    let mut command = Command::new(program);
+    command.args(words).arg(directory);
+    output::log_command(&command);
+    let status = interrupt::in_foreground(|| command.status())?;
+    if !status.success() {
+        return Err(GetflakeError::CommandFailed {
+            command: format!("{editor} {0}", directory.display()),
+            message: status.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether direnv is installed
+pub fn direnv_installed() -> bool {
+    Command::new("direnv")
+        .arg("version")
+        .run_logged()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether the GitHub CLI is installed
+pub fn gh_installed() -> bool {
+    Command::new("gh")
+        .arg("--version")
+        .run_logged()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether pre-commit is installed
+pub fn pre_commit_installed() -> bool {
+    Command::new("pre-commit")
+        .arg("--version")
+        .run_logged()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Reads a setting of the user's Git config (e.g. `user.name`), if it's set
+pub fn git_config(key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .run_logged()
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+pub fn is_non_empty_dir(path: &str) -> bool {
+    fs::read_dir(path)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
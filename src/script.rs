@@ -0,0 +1,237 @@
+//! Scripts: [Rhai](https://rhai.rs) files in the `hooks` folder of getflake's config folder, run
+//! around scaffolding a project to customize it without recompiling getflake
+//!
+//! A script defines a `before` function, an `after` function, or both, which get the project's
+//! details as a map: the placeholders' values, along with `template` and `directory`.
+//!
+//! ```rhai
+//! fn before(project) {
+//!     if project.project_name.contains("_") {
+//!         throw "use dashes in project names";
+//!     }
+//!     set_variable("project_team", "platform");
+//!     skip("direnv");
+//! }
+//!
+//! fn after(project) {
+//!     let readme = read_file("README.md");
+//!     write_file("README.md", readme + "\nMaintained by the platform team.\n");
+//! }
+//! ```
+//!
+//! `before` runs once the questions are answered: it can add variables with `set_variable`, skip
+//! steps with `skip`, or veto the project altogether by throwing. `after` runs once the
+//! placeholders are replaced, before the initial commit, and can list the project's files with
+//! `files`, and read and rewrite them with `read_file` and `write_file`.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::{
+    config::config_dir,
+    error::{GetflakeError, Result},
+    render,
+};
+
+/// Folder of getflake's config folder the scripts are in
+pub const DIRECTORY: &str = "hooks";
+
+/// Extension of the scripts
+pub const EXTENSION: &str = "rhai";
+
+/// The scripts, compiled, in the order of their names
+#[derive(Default)]
+pub struct Scripts {
+    scripts: Vec<(PathBuf, AST)>,
+}
+
+/// What the `before` functions asked for
+#[derive(Default, Debug)]
+pub struct Before {
+    /// Variables to set, on top of the answers
+    pub variables: BTreeMap<String, String>,
+    /// Steps not to take, e.g. `commit` or `direnv`
+    pub skipped: BTreeSet<String>,
+}
+
+/// What the functions called by a script changed
+#[derive(Default)]
+struct State {
+    before: Before,
+    written: Vec<PathBuf>,
+}
+
+impl Scripts {
+    /// Compiles the scripts in the `hooks` folder of getflake's config folder, if there's one
+    pub fn load() -> Result<Self> {
+        let Some(directory) = config_dir().map(|dir| dir.join(DIRECTORY)) else {
+            return Ok(Self::default());
+        };
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let engine = Engine::new();
+        let mut scripts = Vec::new();
+        for path in paths {
+            let ast = engine
+                .compile(fs::read_to_string(&path)?)
+                .map_err(|e| failed(&path, e.to_string()))?;
+            scripts.push((path, ast));
+        }
+        Ok(Self { scripts })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Calls the `before` function of each script with `project`, gathering what they asked for
+    ///
+    /// The variables set by one script are in the `project` the next ones get.
+    pub fn before(&self, project: &BTreeMap<String, String>) -> Result<Before> {
+        let state = Rc::new(RefCell::new(State::default()));
+        let mut engine = Engine::new();
+        let set_state = Rc::clone(&state);
+        engine.register_fn("set_variable", move |name: &str, value: &str| {
+            set_state
+                .borrow_mut()
+                .before
+                .variables
+                .insert(name.to_owned(), value.to_owned());
+        });
+        let skip_state = Rc::clone(&state);
+        engine.register_fn("skip", move |step: &str| {
+            skip_state
+                .borrow_mut()
+                .before
+                .skipped
+                .insert(step.to_owned());
+        });
+
+        for (path, ast) in &self.scripts {
+            let mut project = project.clone();
+            project.extend(state.borrow().before.variables.clone());
+            call(&engine, path, ast, "before", &project)?;
+        }
+        let before = std::mem::take(&mut state.borrow_mut().before);
+        Ok(before)
+    }
+
+    /// Calls the `after` function of each script with `project`, letting them rewrite the files
+    /// of the project in `directory`
+    ///
+    /// Returns the paths of the files that were written.
+    pub fn after(
+        &self,
+        project: &BTreeMap<String, String>,
+        directory: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let state = Rc::new(RefCell::new(State::default()));
+        let mut engine = Engine::new();
+        let root = directory.to_path_buf();
+        engine.register_fn("files", move || -> Array {
+            render::files(&root)
+                .into_iter()
+                .map(|path| Dynamic::from(path.to_string_lossy().into_owned()))
+                .collect()
+        });
+        let root = directory.to_path_buf();
+        engine.register_fn(
+            "read_file",
+            move |path: &str| -> std::result::Result<String, Box<EvalAltResult>> {
+                fs::read_to_string(resolve(&root, path)?)
+                    .map_err(|e| format!("unable to read {path}: {e}").into())
+            },
+        );
+        let root = directory.to_path_buf();
+        let write_state = Rc::clone(&state);
+        engine.register_fn(
+            "write_file",
+            move |path: &str, content: &str| -> std::result::Result<(), Box<EvalAltResult>> {
+                let target = resolve(&root, path)?;
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("unable to write {path}: {e}"))?;
+                }
+                fs::write(&target, content).map_err(|e| format!("unable to write {path}: {e}"))?;
+                write_state.borrow_mut().written.push(target);
+                Ok(())
+            },
+        );
+
+        for (path, ast) in &self.scripts {
+            call(&engine, path, ast, "after", project)?;
+        }
+        let mut written = std::mem::take(&mut state.borrow_mut().written);
+        written.sort();
+        written.dedup();
+        Ok(written)
+    }
+}
+
+/// Calls the `function` of the script at `path` with `project`, if it defines one
+fn call(
+    engine: &Engine,
+    path: &Path,
+    ast: &AST,
+    function: &str,
+    project: &BTreeMap<String, String>,
+) -> Result<()> {
+    let defined = ast
+        .iter_functions()
+        .any(|script_fn| script_fn.name == function && script_fn.params.len() == 1);
+    if !defined {
+        return Ok(());
+    }
+    let project: Map = project
+        .iter()
+        .map(|(name, value)| (name.as_str().into(), Dynamic::from(value.clone())))
+        .collect();
+    // Whatever the function returns is ignored, as it may well be its last expression's value
+    let _ = engine
+        .call_fn::<Dynamic>(
+            &mut Scope::new(),
+            ast,
+            function,
+            (Dynamic::from_map(project),),
+        )
+        .map_err(|e| failed(path, e.to_string()))?;
+    Ok(())
+}
+
+/// The path of `path` in the project's folder, as long as it's relative and stays in it
+fn resolve(root: &Path, path: &str) -> std::result::Result<PathBuf, Box<EvalAltResult>> {
+    let is_relative = Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_relative {
+        return Err(format!("{path} must be relative to the project, without ..").into());
+    }
+    Ok(root.join(path))
+}
+
+fn failed(path: &Path, message: String) -> GetflakeError {
+    GetflakeError::ScriptFailed {
+        path: path.to_string_lossy().into_owned(),
+        message,
+    }
+}
@@ -0,0 +1,248 @@
+//! Fetching templates from GitHub without nix
+//!
+//! The repository is downloaded as a tarball from GitHub's codeload API. Without nix, `flake.nix`
+//! can't be evaluated, so templates are found by their folder instead: `NAME/` or `templates/NAME/`,
+//! with a `flake.nix` of their own.
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    error::{GetflakeError, Result},
+    icons::ERROR,
+    output::{self, RunLogged},
+    render::TempDir,
+    retry,
+    templates::{access_token, is_auth_failure, is_offline, Template, Templates},
+};
+
+/// A GitHub repository, and the revision, tag or branch to download
+pub struct GitHubRepository {
+    /// `owner/repo`
+    pub name: String,
+    pub git_ref: Option<String>,
+    /// Folder of the flake within the repository, from the `dir` parameter
+    pub dir: Option<String>,
+}
+
+impl GitHubRepository {
+    /// Parses `github:owner/repo[/ref]` and `https://github.com/owner/repo` flake references, with
+    /// their `ref`, `rev` and `dir` parameters
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = url.split('#').next()?;
+        let (url, query) = url.split_once('?').unwrap_or((url, ""));
+        let parameter = |name: &str| {
+            query
+                .split('&')
+                .find_map(|parameter| parameter.strip_prefix(name)?.strip_prefix('='))
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+        };
+        let path = url
+            .strip_prefix("github:")
+            .or_else(|| url.strip_prefix("https://github.com/"))
+            .or_else(|| url.strip_prefix("git+https://github.com/"))?;
+
+        let mut parts = path.splitn(3, '/');
+        let owner = parts.next().filter(|owner| !owner.is_empty())?;
+        let repo = parts.next().filter(|repo| !repo.is_empty())?;
+        let repo = repo.strip_suffix(".git").unwrap_or(repo);
+        let git_ref = parts
+            .next()
+            .filter(|git_ref| !git_ref.is_empty())
+            .map(str::to_owned)
+            .or_else(|| parameter("rev"))
+            .or_else(|| parameter("ref"));
+
+        Some(Self {
+            name: format!("{owner}/{repo}"),
+            git_ref,
+            dir: parameter("dir"),
+        })
+    }
+
+    /// The URL of the tarball, from the API when there's a token, since private repositories can
+    /// only be downloaded from there
+    pub fn tarball_url(&self) -> String {
+        let git_ref = self.git_ref.as_deref().unwrap_or("HEAD");
+        if access_token("github.com").is_some() {
+            return format!(
+                "https://api.github.com/repos/{0}/tarball/{git_ref}",
+                self.name
+            );
+        }
+        format!(
+            "https://codeload.github.com/{0}/tar.gz/{git_ref}",
+            self.name
+        )
+    }
+
+    /// Downloads the repository into a temporary folder, retrying when the network fails
+    pub fn download(&self) -> Result<Downloaded> {
+        if is_offline() {
+            return Err(GetflakeError::NotAvailableOffline {
+                what: format!("github:{0}", self.name),
+            });
+        }
+        retry::retry(&format!("github:{0}", self.name), || self.download_once())
+    }
+
+    fn download_once(&self) -> Result<Downloaded> {
+        let temp_dir = TempDir::new()?;
+        let url = self.tarball_url();
+
+        let mut curl_command = Command::new("curl");
+        curl_command
+            .args([
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                url.as_str(),
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // The header is read from stdin, so the token doesn't end up in the logged command
+        let token = access_token("github.com");
+        if token.is_some() {
+            curl_command.args(["--header", "@-"]).stdin(Stdio::piped());
+        }
+        // curl isn't run with run_logged, so it enforces the timeout itself
+        if let Some(timeout) = output::command_timeout() {
+            curl_command.args(["--max-time", &timeout.as_secs().to_string()]);
+        }
+        output::log_command(&curl_command);
+        let mut curl = curl_command.spawn()?;
+        if let (Some(token), Some(mut stdin)) = (token, curl.stdin.take()) {
+            writeln!(stdin, "Authorization: Bearer {token}")?;
+        }
+        let tar = Command::new("tar")
+            .args(["-xz", "--strip-components=1", "-C"])
+            .arg(temp_dir.path())
+            .stdin(curl.stdout.take().map_or_else(Stdio::null, Stdio::from))
+            .run_logged()?;
+        let curl = curl.wait_with_output()?;
+
+        if !curl.status.success() {
+            let message = String::from_utf8_lossy(&curl.stderr).trim().to_owned();
+            // GitHub answers 404 for the private repositories the request can't see
+            if is_auth_failure(&message) || message.contains("returned error: 404") {
+                return Err(GetflakeError::AuthenticationRequired {
+                    url: format!("github:{0}", self.name),
+                    message,
+                });
+            }
+            return Err(GetflakeError::CommandFailed {
+                command: format!("curl {url}"),
+                message,
+            });
+        }
+        if !tar.status.success() {
+            return Err(GetflakeError::CommandFailed {
+                command: format!("tar -xz -C {0}", temp_dir.path().display()),
+                message: String::from_utf8_lossy(&tar.stderr).trim().to_owned(),
+            });
+        }
+
+        Ok(Downloaded {
+            temp_dir,
+            dir: self.dir.clone(),
+        })
+    }
+}
+
+/// A repository downloaded into a temporary folder, removed when dropped
+pub struct Downloaded {
+    temp_dir: TempDir,
+    dir: Option<String>,
+}
+
+impl Downloaded {
+    /// Returns the folders with a `flake.nix` at the top of the repository or under `templates/`
+    pub fn templates(&self) -> Templates {
+        find_templates(&self.root())
+    }
+
+    /// Returns the folder of `template`
+    pub fn template_path(&self, template: &str) -> Result<PathBuf> {
+        template_path(&self.root(), template)
+    }
+
+    /// The folder of the flake, which is the `dir` of the repository if it has one
+    fn root(&self) -> PathBuf {
+        let root = self.temp_dir.path();
+        match &self.dir {
+            Some(dir) => root.join(dir),
+            None => root.to_path_buf(),
+        }
+    }
+}
+
+/// Returns the folders with a `flake.nix` directly under `root` or under `root/templates/`, which
+/// is also how local sources are read without nix
+pub fn find_templates(root: &Path) -> Templates {
+    let mut templates: Templates = [root.to_path_buf(), root.join("templates")]
+        .iter()
+        .flat_map(|directory| {
+            WalkDir::new(directory)
+                .min_depth(1)
+                .max_depth(1)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+        })
+        .filter(|entry| entry.path().join("flake.nix").is_file())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            Template {
+                print_str: name.clone(),
+                name,
+                aliases: Vec::new(),
+            }
+        })
+        .collect();
+    let mut names = HashSet::new();
+    templates.retain(|template| names.insert(template.name.clone()));
+    templates
+}
+
+/// Returns the folder of `template` under `root`
+pub fn template_path(root: &Path, template: &str) -> Result<PathBuf> {
+    [root.join(template), root.join("templates").join(template)]
+        .into_iter()
+        .find(|path| path.join("flake.nix").is_file())
+        .ok_or_else(|| GetflakeError::TemplateNotFound(template.to_owned()))
+}
+
+/// Copies the files under `from` into `to`, leaving the files already in `to` alone like
+/// `nix flake init` does
+pub fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let Ok(relative) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+        let target = to.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if target.exists() {
+            eprintln!(
+                "- {ERROR}Not overwriting existing file {0}",
+                target.to_string_lossy()
+            );
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1004 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache,
+    error::{GetflakeError, Result},
+    icons::{ERROR, FETCH, WARNING},
+    manifest::Manifest,
+    output::{self, RunLogged},
+    progress, retry, say,
+    scaffold::expand_home,
+    tarball::{self, GitHubRepository},
+    tree,
+};
+
+pub const DEFAULT_SOURCE: &str = "github:nulladmin1/nix-flake-templates";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub print_str: String,
+    /// Names of the other templates with the same description
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl Template {
+    /// The template's name, then its aliases
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        [self.name.as_str()]
+            .into_iter()
+            .chain(self.aliases.iter().map(String::as_str))
+    }
+}
+
+pub type Templates = Vec<Template>;
+
+/// Everything a template's flake output says about it
+#[derive(Deserialize, Debug)]
+pub struct TemplateDetails {
+    pub description: String,
+    #[serde(rename = "welcomeText")]
+    pub welcome_text: Option<String>,
+    /// Path of the template in the nix store, which must only be read from
+    pub path: PathBuf,
+}
+
+/// Whether `template` is for `language`, e.g. both `rust` and `rust-bevy` are for `rust`
+pub fn is_language(template: &str, language: &str) -> bool {
+    template == language
+        || template
+            .strip_prefix(language)
+            .is_some_and(|rest| rest.starts_with('-'))
+}
+
+/// Words of a template's name or description that list it under `GUI`, whatever its language
+const GUI_WORDS: &[&str] = &["gui", "gtk", "qt", "iced", "egui", "slint", "tauri", "bevy"];
+const GUI_CATEGORY: &str = "GUI";
+
+/// Categories the templates are listed under after `GUI`, in order, with their languages
+const LANGUAGE_CATEGORIES: &[(&str, &[&str])] = &[
+    ("Systems", &["rust", "c", "cpp", "go", "zig", "nim", "odin"]),
+    (
+        "Web",
+        &[
+            "node",
+            "javascript",
+            "typescript",
+            "deno",
+            "bun",
+            "php",
+            "elixir",
+        ],
+    ),
+    (
+        "Scripting",
+        &[
+            "python", "ruby", "lua", "perl", "bash", "shell", "julia", "r",
+        ],
+    ),
+    (
+        "Functional",
+        &["haskell", "ocaml", "elm", "clojure", "scala", "gleam"],
+    ),
+    ("JVM", &["java", "kotlin", "groovy"]),
+];
+
+/// Where templates that aren't in any other category are listed
+const OTHER_CATEGORY: &str = "Other";
+
+/// Returns the category `template` is listed under, derived from its name and description
+pub fn category(template: &Template) -> &'static str {
+    // Without the name of its source, if there are several
+    let name = template
+        .name
+        .rsplit_once('/')
+        .map_or(template.name.as_str(), |(_, name)| name);
+    let text = format!("{name} {0}", template.print_str).to_lowercase();
+    if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| GUI_WORDS.contains(&word))
+    {
+        return GUI_CATEGORY;
+    }
+
+    LANGUAGE_CATEGORIES
+        .iter()
+        .find(|(_, languages)| languages.iter().any(|language| is_language(name, language)))
+        .map_or(OTHER_CATEGORY, |(category, _)| category)
+}
+
+/// Groups `templates` by [`category`], in the order the categories are listed in, keeping the
+/// order of the templates within each
+pub fn by_category(templates: &[Template]) -> Vec<(&'static str, Vec<&Template>)> {
+    let mut groups: Vec<(&'static str, Vec<&Template>)> = [GUI_CATEGORY]
+        .into_iter()
+        .chain(LANGUAGE_CATEGORIES.iter().map(|(category, _)| *category))
+        .chain([OTHER_CATEGORY])
+        .map(|category| (category, Vec::new()))
+        .collect();
+    for template in templates {
+        let category = category(template);
+        if let Some((_, group)) = groups.iter_mut().find(|(name, _)| *name == category) {
+            group.push(template);
+        }
+    }
+    groups.retain(|(_, group)| !group.is_empty());
+    groups
+}
+
+/// Finds a template by name or alias, falling back to the closest fuzzy match (e.g. "rs" finds
+/// "rust"), and returns the name that matched
+pub fn find_template<'a>(templates: &'a [Template], query: &str) -> Option<&'a str> {
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+
+    let names = || templates.iter().flat_map(Template::names);
+    names()
+        .find(|name| name.to_lowercase() == query)
+        .or_else(|| {
+            names()
+                .filter(|name| name.to_lowercase().starts_with(&query))
+                .min_by_key(|name| name.len())
+        })
+        .or_else(|| {
+            names()
+                .filter(|name| is_subsequence(&query, &name.to_lowercase()))
+                .min_by_key(|name| name.len())
+        })
+}
+
+/// Points the flake reference `url` at `git_ref`, which is a revision, tag or branch
+///
+/// e.g. `github:owner/repo` becomes `github:owner/repo/v1.2.0`, and `git+https://example.com/repo`
+/// becomes `git+https://example.com/repo?ref=v1.2.0`.
+pub fn pin(url: &str, git_ref: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+
+    if let Some((scheme, path)) = base.split_once(':') {
+        if ["github", "gitlab", "sourcehut"].contains(&scheme) {
+            // Any ref already in the url is replaced
+            let repository: Vec<&str> = path.splitn(3, '/').take(2).collect();
+            let pinned = format!("{scheme}:{0}/{git_ref}", repository.join("/"));
+            return match query {
+                Some(query) => format!("{pinned}?{query}"),
+                None => pinned,
+            };
+        }
+    }
+
+    // Any ref or revision already in the url is replaced, and the other parameters (e.g. `dir`)
+    // are kept
+    let is_revision = git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit());
+    let parameter = if is_revision { "rev" } else { "ref" };
+    let mut parameters: Vec<String> = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter(|parameter| !parameter.starts_with("ref=") && !parameter.starts_with("rev="))
+        .map(str::to_owned)
+        .collect();
+    parameters.push(format!("{parameter}={git_ref}"));
+    format!("{base}?{0}", parameters.join("&"))
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// A flake containing templates, e.g. `github:nulladmin1/nix-flake-templates`
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub url: String,
+    cache_ttl: Duration,
+    refresh: bool,
+    use_nix: bool,
+}
+
+impl Source {
+    /// `url` is normalized with [`normalize_url`] first
+    pub fn new(url: String, cache_ttl: Duration, refresh: bool) -> Self {
+        Self {
+            url: normalize_url(&url),
+            cache_ttl,
+            refresh,
+            use_nix: true,
+        }
+    }
+
+    /// Whether to fetch templates with nix, rather than downloading them from GitHub
+    ///
+    /// They are downloaded from GitHub anyway when nix isn't installed.
+    pub fn use_nix(mut self, use_nix: bool) -> Self {
+        self.use_nix = use_nix;
+        self
+    }
+
+    pub fn uses_nix(&self) -> bool {
+        self.use_nix && nix_installed()
+    }
+
+    fn require_nix(&self) -> Result<()> {
+        if !self.use_nix {
+            Err(GetflakeError::NixDisabled)
+        } else if !nix_installed() {
+            Err(GetflakeError::NixNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The folder of a `path:` source, which is read without nix when it isn't used
+    pub fn local_path(&self) -> Option<PathBuf> {
+        let path = self.url.strip_prefix("path:")?.split('#').next()?;
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let path = PathBuf::from(path);
+        match query
+            .split('&')
+            .find_map(|parameter| parameter.strip_prefix("dir="))
+        {
+            Some(dir) => Some(path.join(dir)),
+            None => Some(path),
+        }
+    }
+
+    /// Checks that `template` can be copied without the network, so an offline run fails before
+    /// any more questions are asked
+    pub fn check_offline(&self, template: &str) -> Result<()> {
+        if self.local_path().is_some() {
+            return Ok(());
+        }
+        let unavailable = || GetflakeError::NotAvailableOffline {
+            what: format!("{0}#{template}", self.url),
+        };
+        if !self.uses_nix() {
+            return Err(unavailable());
+        }
+        self.template_details(template)
+            .map(|_| ())
+            .map_err(|_| unavailable())
+    }
+
+    /// How [`Source::copy_template`] copies `template` when nix isn't used, as it's displayed to
+    /// the user
+    pub fn copy_command(&self, template: &str) -> Result<String> {
+        if let Some(local_path) = self.local_path() {
+            let template_path = tarball::template_path(&local_path, template)?;
+            return Ok(format!("cp -r {0}", template_path.display()));
+        }
+        Ok(format!("curl {0}", self.github_repository()?.tarball_url()))
+    }
+
+    fn github_repository(&self) -> Result<GitHubRepository> {
+        GitHubRepository::parse(&self.url).ok_or_else(|| {
+            if self.use_nix {
+                GetflakeError::NixNotFound
+            } else {
+                GetflakeError::NotOnGitHub(self.url.clone())
+            }
+        })
+    }
+
+    /// Returns the templates of this source, from the cache if it's fresh enough
+    ///
+    /// Local sources are never cached, since they're meant to be edited.
+    pub fn templates(&self) -> Result<Templates> {
+        let local_path = self.local_path();
+        if !self.refresh && local_path.is_none() {
+            // Offline, any cached list is better than none
+            let ttl = if is_offline() {
+                Duration::MAX
+            } else {
+                self.cache_ttl
+            };
+            if let Some(templates) = cache::load(&self.url, ttl) {
+                return Ok(templates);
+            }
+            if is_offline() && !self.uses_nix() {
+                return Err(GetflakeError::NotAvailableOffline {
+                    what: format!("The template list of {0}", self.url),
+                });
+            }
+        }
+
+        // Without nix there are no descriptions, so the list isn't worth caching
+        if !self.uses_nix() {
+            if let Some(local_path) = local_path {
+                let templates = tarball::find_templates(&local_path);
+                if templates.is_empty() {
+                    return Err(self.fetch_failed("no folder with a flake.nix was found"));
+                }
+                return Ok(templates);
+            }
+            return self.fetch_from_github();
+        }
+
+        let templates = self.fetch()?;
+        if local_path.is_none() {
+            if let Err(e) = cache::store(&self.url, &templates) {
+                eprintln!("{ERROR}Failed to cache templates: {e}");
+            }
+        }
+
+        Ok(templates)
+    }
+
+    /// Returns the cached templates of this source however old they are, without fetching them
+    pub fn cached_templates(&self) -> Option<Templates> {
+        cache::load(&self.url, Duration::MAX)
+    }
+
+    /// Returns the templates of this source, with the ones sharing a description grouped as
+    /// aliases of the first one
+    pub fn unique_templates(&self) -> Result<Templates> {
+        let mut unique: Templates = Vec::new();
+        for template in self.templates()? {
+            match unique
+                .iter_mut()
+                .find(|unique| unique.print_str == template.print_str)
+            {
+                Some(unique) => unique.aliases.push(template.name),
+                None => unique.push(template),
+            }
+        }
+        Ok(unique)
+    }
+
+    pub fn template_details(&self, name: &str) -> Result<TemplateDetails> {
+        self.require_nix()?;
+
+        let attribute = format!("{0}#templates.{1}", self.url, name);
+        let output = retry::retry(&attribute, || {
+            let output = nix_command()
+                .args([
+                    "--extra-experimental-features",
+                    "nix-command flakes",
+                    "eval",
+                    "--json",
+                    attribute.as_str(),
+                    "--apply",
+                    "t: { inherit (t) description path; welcomeText = t.welcomeText or null; }",
+                ])
+                .run_logged()
+                .map_err(GetflakeError::from_nix_io)?;
+            if !output.status.success() {
+                return Err(
+                    self.command_failed(format!("nix eval --json {attribute}"), &output.stderr)
+                );
+            }
+            Ok(output)
+        })?;
+
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    /// Returns the variables `template` declares in its `getflake.toml`
+    pub fn template_manifest(&self, template: &str) -> Result<Manifest> {
+        self.read_template(template, Manifest::load)
+    }
+
+    /// Returns the files and folders of `template`, drawn as a tree
+    pub fn template_tree(&self, template: &str) -> Result<Vec<String>> {
+        self.read_template(template, tree::file_tree)
+    }
+
+    /// Calls `read` with the folder of `template`, which only lasts as long as the call
+    fn read_template<T>(&self, template: &str, read: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+        if let (false, Some(local_path)) = (self.uses_nix(), self.local_path()) {
+            return read(&tarball::template_path(&local_path, template)?);
+        }
+        if !self.uses_nix() {
+            let downloaded = self.github_repository()?.download()?;
+            return read(&downloaded.template_path(template)?);
+        }
+        read(&self.template_details(template)?.path)
+    }
+
+    /// Returns the commit the source currently points at, if it's a Git repository
+    pub fn resolved_rev(&self) -> Result<Option<String>> {
+        if !self.uses_nix() {
+            return Ok(None);
+        }
+
+        let output = nix_command()
+            .args([
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "flake",
+                "metadata",
+                "--json",
+                self.url.as_str(),
+            ])
+            .run_logged()
+            .map_err(GetflakeError::from_nix_io)?;
+        if !output.status.success() {
+            return Err(self.command_failed(
+                format!("nix flake metadata --json {0}", self.url),
+                &output.stderr,
+            ));
+        }
+
+        let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        Ok(metadata
+            .get("revision")
+            .and_then(|revision| revision.as_str())
+            .map(str::to_owned))
+    }
+
+    /// Copies `template` into `directory`, returning how it was fetched
+    pub fn copy_template(&self, template: &str, directory: &Path) -> Result<String> {
+        if let (false, Some(local_path)) = (self.uses_nix(), self.local_path()) {
+            tarball::copy_dir(&tarball::template_path(&local_path, template)?, directory)?;
+            return self.copy_command(template);
+        }
+        if !self.uses_nix() {
+            let downloaded = self.github_repository()?.download()?;
+            tarball::copy_dir(&downloaded.template_path(template)?, directory)?;
+            return self.copy_command(template);
+        }
+
+        let template_url = format!("{0}#{template}", self.url);
+        let command_string = format!("nix flake new --template {template_url}");
+
+        // The welcome text is only noise here
+        let output = nix_command()
+            .args([
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "flake",
+                "new",
+                "--template",
+                template_url.as_str(),
+            ])
+            .arg(directory)
+            .stdout(Stdio::null())
+            .run_logged()
+            .map_err(GetflakeError::from_nix_io)?;
+        if !output.status.success() {
+            return Err(self.command_failed(command_string, &output.stderr));
+        }
+
+        Ok(command_string)
+    }
+
+    fn fetch_from_github(&self) -> Result<Templates> {
+        if self.use_nix {
+            say!("{WARNING} Nix isn't installed, so the templates are downloaded from GitHub instead");
+        }
+        let repository = self.github_repository()?;
+        say!(
+            "{FETCH} Downloading templates from {0}...",
+            repository.tarball_url()
+        );
+
+        let templates = progress::spin("Downloading", || repository.download())?.templates();
+        if templates.is_empty() {
+            return Err(self.fetch_failed("no folder with a flake.nix was found"));
+        }
+        Ok(templates)
+    }
+
+    fn fetch(&self) -> Result<Templates> {
+        say!("{FETCH} Fetching templates...");
+
+        let args = [
+            "--extra-experimental-features",
+            "nix-command flakes",
+            "flake",
+            "show",
+            "--json",
+            self.url.as_str(),
+        ];
+        let output = progress::spin("Fetching templates", || {
+            retry::retry(&self.url, || {
+                let output = nix_command()
+                    .args(args)
+                    .run_logged()
+                    .map_err(GetflakeError::from_nix_io)?;
+                if !output.status.success() {
+                    return Err(self.fetch_failed(String::from_utf8_lossy(&output.stderr).trim()));
+                }
+                Ok(output)
+            })
+        })?;
+
+        let parsed_json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        let Some(templates_json) = parsed_json.get("templates").and_then(|t| t.as_object()) else {
+            return Err(self.fetch_failed("the flake has no templates"));
+        };
+
+        let mut templates: Templates = Vec::new();
+
+        for (key, value) in templates_json {
+            let description = if key == &"default".to_owned() {
+                "Empty/Blank".to_string()
+            } else {
+                let description = value
+                    .get("description")
+                    .and_then(|description| description.as_str())
+                    .unwrap_or_default();
+                description
+                    .strip_prefix("Nix Flake Template for ")
+                    .unwrap_or(description)
+                    .to_string()
+            };
+            templates.push(Template {
+                name: key.to_string(),
+                print_str: description,
+                aliases: Vec::new(),
+            });
+        }
+
+        Ok(templates)
+    }
+
+    fn fetch_failed(&self, message: &str) -> GetflakeError {
+        if is_auth_failure(message) {
+            return GetflakeError::AuthenticationRequired {
+                url: self.url.clone(),
+                message: message.to_owned(),
+            };
+        }
+        GetflakeError::FlakeShowFailed {
+            url: self.url.clone(),
+            message: message.to_owned(),
+        }
+    }
+
+    /// The error for `command` failing with `stderr`, telling missing credentials apart
+    fn command_failed(&self, command: String, stderr: &[u8]) -> GetflakeError {
+        let message = String::from_utf8_lossy(stderr).trim().to_owned();
+        if is_auth_failure(&message) {
+            GetflakeError::AuthenticationRequired {
+                url: self.url.clone(),
+                message,
+            }
+        } else {
+            GetflakeError::CommandFailed { command, message }
+        }
+    }
+}
+
+/// The template flakes templates are picked from
+///
+/// When there are several, each has a name its templates are prefixed with, e.g. `work/rust` is
+/// the `rust` template of the source named `work`.
+#[derive(Clone, Debug)]
+pub struct Sources {
+    sources: Vec<(String, Source)>,
+}
+
+impl Sources {
+    /// `sources` must not be empty
+    pub fn new(sources: Vec<(String, Source)>) -> Self {
+        Self { sources }
+    }
+
+    pub fn single(source: Source) -> Self {
+        Self::new(vec![(String::new(), source)])
+    }
+
+    /// The first source, which is the only one unless several are configured
+    pub fn first(&self) -> &Source {
+        &self.sources[0].1
+    }
+
+    /// Returns the templates of every source, fetched at the same time, with their aliases
+    /// grouped like [`Source::unique_templates`] does
+    pub fn unique_templates(&self) -> Result<Templates> {
+        if let [(_, source)] = self.sources.as_slice() {
+            return source.unique_templates();
+        }
+
+        // Threads start out printing, even when this one doesn't
+        let quieted = output::is_quieted();
+        let fetched: Vec<Result<Templates>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .sources
+                .iter()
+                .map(|(_, source)| {
+                    scope.spawn(move || {
+                        if quieted {
+                            output::quietly(|| source.unique_templates())
+                        } else {
+                            source.unique_templates()
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("fetching the templates panicked"))
+                .collect()
+        });
+
+        let mut merged = Vec::new();
+        for ((name, _), templates) in self.sources.iter().zip(fetched) {
+            merged.extend(
+                templates?
+                    .into_iter()
+                    .map(|template| prefixed(name, template)),
+            );
+        }
+        Ok(merged)
+    }
+
+    /// Returns the cached templates of every source, like [`Source::cached_templates`]
+    pub fn cached_templates(&self) -> Option<Templates> {
+        if let [(_, source)] = self.sources.as_slice() {
+            return source.cached_templates();
+        }
+
+        let mut merged = Vec::new();
+        for (name, source) in &self.sources {
+            if let Some(templates) = source.cached_templates() {
+                merged.extend(
+                    templates
+                        .into_iter()
+                        .map(|template| prefixed(name, template)),
+                );
+            }
+        }
+        (!merged.is_empty()).then_some(merged)
+    }
+
+    /// Returns the names of the templates `names_of` returns for each source, prefixed
+    pub fn prefixed_names(&self, names_of: impl Fn(&Source) -> Vec<String>) -> Vec<String> {
+        self.sources
+            .iter()
+            .flat_map(|(name, source)| {
+                names_of(source)
+                    .into_iter()
+                    .map(move |template| prefix(name, &template))
+            })
+            .collect()
+    }
+
+    /// Splits `template` into the source it's from and its name in there
+    ///
+    /// A name without a prefix is looked for in every source, and must only be in one of them.
+    pub fn resolve(&self, template: &str) -> Result<(Source, String)> {
+        if let [(_, source)] = self.sources.as_slice() {
+            return Ok((source.clone(), template.to_owned()));
+        }
+
+        if let Some((name, rest)) = template.split_once('/') {
+            if let Some((_, source)) = self.sources.iter().find(|(other, _)| other == name) {
+                return Ok((source.clone(), rest.to_owned()));
+            }
+        }
+
+        let having: Vec<&(String, Source)> = self
+            .sources
+            .iter()
+            .filter(|(_, source)| {
+                source
+                    .templates()
+                    .is_ok_and(|templates| templates.iter().any(|other| other.name == template))
+            })
+            .collect();
+        match having.as_slice() {
+            [(_, source)] => Ok((source.clone(), template.to_owned())),
+            [] => Err(GetflakeError::TemplateNotFound(template.to_owned())),
+            _ => Err(GetflakeError::InvalidInput(format!(
+                "several sources have a {template} template, pick one of {0}",
+                having
+                    .iter()
+                    .map(|(name, _)| prefix(name, template))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
+
+/// Returns the flake reference `url` stands for if it's an alias: from `registry` (getflake's own,
+/// in the config file) or else from `nix registry list`
+///
+/// Anything but a bare name (e.g. `mytemplates`) is returned as it is.
+pub fn resolve_alias(url: &str, registry: &BTreeMap<String, String>) -> String {
+    let is_alias = !url.is_empty()
+        && url
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_alias {
+        return url.to_owned();
+    }
+    if let Some(resolved) = registry.get(url) {
+        return resolved.clone();
+    }
+    if !nix_installed() {
+        return url.to_owned();
+    }
+
+    // Lines are like `user   flake:mytemplates github:me/templates`
+    let output = nix_command()
+        .args([
+            "--extra-experimental-features",
+            "nix-command flakes",
+            "registry",
+            "list",
+        ])
+        .run_logged();
+    let Ok(output) = output else {
+        return url.to_owned();
+    };
+    if !output.status.success() {
+        return url.to_owned();
+    }
+    let alias = format!("flake:{url}");
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            (fields.next()? == alias).then(|| fields.next().map(str::to_owned))?
+        })
+        .unwrap_or_else(|| url.to_owned())
+}
+
+/// Turns the forms of `url` nix doesn't understand as flake references into ones it does
+///
+/// - Local folders (`./my-templates`, `~/templates`, `file:///home/me/templates`) become `path:`
+///   references with an absolute path, so they work from any folder
+/// - `https://` URLs of GitHub, GitLab and sourcehut repositories become `github:`, `gitlab:` and
+///   `sourcehut:` references, which nix fetches as tarballs
+/// - Codeberg, which nix has no scheme for, is fetched with Git: `codeberg:owner/repo` and
+///   `https://codeberg.org/owner/repo` become `git+https://codeberg.org/owner/repo`
+pub fn normalize_url(url: &str) -> String {
+    if let Some(url) = local_url(url) {
+        return url;
+    }
+
+    const HOSTS: &[(&str, &str)] = &[
+        ("https://github.com/", "github:"),
+        ("https://gitlab.com/", "gitlab:"),
+        ("https://git.sr.ht/", "sourcehut:"),
+        ("https://codeberg.org/", "git+https://codeberg.org/"),
+        ("codeberg:", "git+https://codeberg.org/"),
+    ];
+    for (prefix, replacement) in HOSTS {
+        if let Some(rest) = url.strip_prefix(prefix) {
+            let (path, query) = match rest.split_once('?') {
+                Some((path, query)) => (path, Some(query)),
+                None => (rest, None),
+            };
+            let path = path.trim_end_matches('/');
+            // Tarball URLs are already flake references
+            if path.ends_with(".tar.gz") || path.ends_with(".zip") {
+                break;
+            }
+            let path = if replacement.starts_with("git+") {
+                path.to_owned()
+            } else {
+                path.strip_suffix(".git").unwrap_or(path).to_owned()
+            };
+            return match query {
+                Some(query) => format!("{replacement}{path}?{query}"),
+                None => format!("{replacement}{path}"),
+            };
+        }
+    }
+
+    url.to_owned()
+}
+
+/// Turns a local folder into a `path:` flake reference, or returns `None` if `url` isn't one
+fn local_url(url: &str) -> Option<String> {
+    let path = if let Some(path) = url.strip_prefix("file://") {
+        path.to_owned()
+    } else if let Some(path) = url.strip_prefix("path:") {
+        path.to_owned()
+    } else if url.starts_with(['.', '/', '~']) {
+        expand_home(url)
+    } else {
+        return None;
+    };
+
+    // Relative paths would be resolved against the folder nix runs in, which isn't this one
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path.to_owned(), Some(query.to_owned())),
+        None => (path, None),
+    };
+    let path = fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path));
+    Some(match query {
+        Some(query) => format!("path:{0}?{query}", path.display()),
+        None => format!("path:{0}", path.display()),
+    })
+}
+
+fn prefix(source_name: &str, template: &str) -> String {
+    if source_name.is_empty() {
+        template.to_owned()
+    } else {
+        format!("{source_name}/{template}")
+    }
+}
+
+fn prefixed(source_name: &str, template: Template) -> Template {
+    Template {
+        name: prefix(source_name, &template.name),
+        print_str: template.print_str,
+        aliases: template
+            .aliases
+            .iter()
+            .map(|alias| prefix(source_name, alias))
+            .collect(),
+    }
+}
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether to do without the network, using the cached template lists and the templates nix
+/// already downloaded
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Tokens private repositories are fetched with, by host
+static ACCESS_TOKENS: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// Sets the tokens private repositories are fetched with, by host (e.g. `github.com`)
+///
+/// nix gets them as `extra-access-tokens`, on top of the ones in its own configuration.
+pub fn set_access_tokens(tokens: BTreeMap<String, String>) {
+    let _ = ACCESS_TOKENS.set(tokens);
+}
+
+/// The token for `host`, falling back to `$GITHUB_TOKEN` for GitHub
+pub fn access_token(host: &str) -> Option<String> {
+    ACCESS_TOKENS
+        .get()
+        .and_then(|tokens| tokens.get(host).cloned())
+        .or_else(|| {
+            (host == "github.com")
+                .then(|| env::var("GITHUB_TOKEN").ok())
+                .flatten()
+        })
+        .filter(|token| !token.is_empty())
+}
+
+/// A `nix` command that fetches private repositories with the configured access tokens, and
+/// doesn't fetch anything when offline
+pub fn nix_command() -> Command {
+    let mut command = Command::new("nix");
+    if is_offline() {
+        command.arg("--offline");
+    }
+    if let Some(tokens) = ACCESS_TOKENS.get().filter(|tokens| !tokens.is_empty()) {
+        let tokens: Vec<String> = tokens
+            .iter()
+            .map(|(host, token)| format!("{host}={token}"))
+            .collect();
+        // Through the environment, so they don't end up in the logged command
+        let mut nix_config = env::var("NIX_CONFIG").unwrap_or_default();
+        nix_config.push_str(&format!("\nextra-access-tokens = {0}", tokens.join(" ")));
+        command.env("NIX_CONFIG", nix_config);
+    }
+    command
+}
+
+/// Whether nix, Git or curl failed because the repository needs credentials
+pub fn is_auth_failure(stderr: &str) -> bool {
+    const PATTERNS: &[&str] = &[
+        "Permission denied (publickey",
+        "Host key verification failed",
+        "Authentication failed",
+        "could not read Username",
+        "terminal prompts disabled",
+        "HTTP error 401",
+        "HTTP error 403",
+        "returned error: 401",
+        "returned error: 403",
+    ];
+    PATTERNS.iter().any(|pattern| stderr.contains(pattern))
+}
+
+/// Whether nix can be run, which is only checked once
+fn nix_installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| Command::new("nix").arg("--version").run_logged().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pin_forge_references() {
+        assert_eq!(
+            pin("github:owner/repo", "v1.2.0"),
+            "github:owner/repo/v1.2.0"
+        );
+        assert_eq!(
+            pin("github:owner/repo/main", "v1.2.0"),
+            "github:owner/repo/v1.2.0"
+        );
+        assert_eq!(
+            pin("gitlab:owner/repo?dir=templates", "v1.2.0"),
+            "gitlab:owner/repo/v1.2.0?dir=templates"
+        );
+    }
+
+    #[test]
+    fn pin_other_references() {
+        assert_eq!(
+            pin("git+https://example.com/repo", "v1.2.0"),
+            "git+https://example.com/repo?ref=v1.2.0"
+        );
+        let rev = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            pin("git+https://example.com/repo?ref=main&dir=templates", rev),
+            format!("git+https://example.com/repo?dir=templates&rev={rev}")
+        );
+    }
+
+    #[test]
+    fn normalize_forge_urls() {
+        assert_eq!(
+            normalize_url("https://github.com/owner/repo"),
+            "github:owner/repo"
+        );
+        assert_eq!(
+            normalize_url("https://github.com/owner/repo.git/"),
+            "github:owner/repo"
+        );
+        assert_eq!(
+            normalize_url("https://gitlab.com/owner/repo?dir=templates"),
+            "gitlab:owner/repo?dir=templates"
+        );
+        assert_eq!(
+            normalize_url("https://git.sr.ht/~owner/repo"),
+            "sourcehut:~owner/repo"
+        );
+        assert_eq!(
+            normalize_url("codeberg:owner/repo"),
+            "git+https://codeberg.org/owner/repo"
+        );
+    }
+
+    #[test]
+    fn normalize_leaves_flake_references_alone() {
+        for url in [
+            "github:owner/repo",
+            "git+https://example.com/repo",
+            "https://github.com/owner/repo/archive/main.tar.gz",
+        ] {
+            assert_eq!(normalize_url(url), url);
+        }
+    }
+
+    #[test]
+    fn normalize_local_paths() {
+        let root = fs::canonicalize("/").unwrap();
+        assert_eq!(
+            normalize_url("file:///"),
+            format!("path:{0}", root.display())
+        );
+        assert_eq!(
+            normalize_url("/?dir=templates"),
+            format!("path:{0}?dir=templates", root.display())
+        );
+    }
+}
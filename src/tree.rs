@@ -0,0 +1,37 @@
+//! Directory listings drawn as a tree, to preview what a template contains
+
+use std::{fs, io, path::Path};
+
+use crate::error::Result;
+
+/// Lists the files and folders under `directory` like `tree` does, folders first, with a `/`
+/// after their name
+pub fn file_tree(directory: &Path) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    push_entries(directory, "", &mut lines)?;
+    Ok(lines)
+}
+
+fn push_entries(directory: &Path, indent: &str, lines: &mut Vec<String>) -> Result<()> {
+    let mut entries = fs::read_dir(directory)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| (!entry.path().is_dir(), entry.file_name()));
+
+    for (index, entry) in entries.iter().enumerate() {
+        let is_last = index + 1 == entries.len();
+        let is_dir = entry.path().is_dir();
+        let (branch, continuation) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!(
+            "{indent}{branch}{0}{1}",
+            entry.file_name().to_string_lossy(),
+            if is_dir { "/" } else { "" }
+        ));
+        if is_dir {
+            push_entries(&entry.path(), &format!("{indent}{continuation}"), lines)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,676 @@
+//! The `--tui` mode: the templates and the main answers on one screen, instead of one question
+//! after the other
+//!
+//! The template list, filtered as you type, is on the left. The selected template's description
+//! and files are on the right, above a form for the project's name and options. What the form
+//! doesn't cover (the commit, the remote repository, the template's variables) is asked
+//! afterwards as usual.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use getflake::{
+    answers::Answers,
+    config::Config,
+    output, progress,
+    scaffold::{direnv_installed, git_config, DEFAULT_LICENSE},
+    templates::{Sources, Template},
+    validate, Ci, GetflakeError, NewOrInit, Readme, Result,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+use crate::args::ScaffoldArgs;
+
+/// A line of the form
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Field {
+    Mode,
+    Name,
+    Author,
+    Email,
+    Description,
+    License,
+    Git,
+    Readme,
+    Direnv,
+    Ci,
+    Devcontainer,
+}
+
+const FIELDS: &[Field] = &[
+    Field::Mode,
+    Field::Name,
+    Field::Author,
+    Field::Email,
+    Field::Description,
+    Field::License,
+    Field::Git,
+    Field::Readme,
+    Field::Direnv,
+    Field::Ci,
+    Field::Devcontainer,
+];
+
+impl Field {
+    /// Whether the field is typed in, rather than toggled
+    fn is_text(self) -> bool {
+        matches!(
+            self,
+            Self::Name | Self::Author | Self::Email | Self::Description | Self::License
+        )
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Mode => "Create",
+            Self::Name => "Name",
+            Self::Author => "Author",
+            Self::Email => "Email",
+            Self::Description => "Description",
+            Self::License => "License",
+            Self::Git => "Git repository",
+            Self::Readme => "README.md",
+            Self::Direnv => "direnv",
+            Self::Ci => "CI workflow",
+            Self::Devcontainer => "Dev container",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Templates,
+    Form,
+}
+
+/// The files of a template, once they're fetched
+enum Files {
+    Loading,
+    Fetched(Vec<String>),
+    Failed(String),
+}
+
+/// The files of a template, sent back by the thread fetching them
+type FetchedFiles = (String, Files);
+
+struct Form {
+    new_or_init: NewOrInit,
+    name: String,
+    author: String,
+    email: String,
+    description: String,
+    license: String,
+    init_git: bool,
+    readme: Readme,
+    direnv: bool,
+    ci: Ci,
+    devcontainer: bool,
+    /// The fields changed on this screen or given beforehand, which the template's defaults
+    /// leave alone
+    touched: BTreeSet<Field>,
+    /// The defaults when the config file has none, looked up once rather than on every key
+    git_author: Option<String>,
+    git_email: Option<String>,
+    direnv_installed: bool,
+}
+
+impl Form {
+    fn text(&mut self, field: Field) -> Option<&mut String> {
+        match field {
+            Field::Name => Some(&mut self.name),
+            Field::Author => Some(&mut self.author),
+            Field::Email => Some(&mut self.email),
+            Field::Description => Some(&mut self.description),
+            Field::License => Some(&mut self.license),
+            _ => None,
+        }
+    }
+
+    fn toggle(&mut self, field: Field) {
+        match field {
+            Field::Mode => {
+                self.new_or_init = match self.new_or_init {
+                    NewOrInit::New => NewOrInit::Init,
+                    NewOrInit::Init => NewOrInit::New,
+                }
+            }
+            Field::Git => self.init_git = !self.init_git,
+            Field::Direnv => self.direnv = !self.direnv,
+            Field::Devcontainer => self.devcontainer = !self.devcontainer,
+            Field::Ci => {
+                self.ci = match self.ci {
+                    Ci::Skip => Ci::GitHub,
+                    Ci::GitHub => Ci::GitLab,
+                    Ci::GitLab => Ci::Skip,
+                }
+            }
+            Field::Readme => {
+                self.readme = match self.readme {
+                    Readme::Keep => Readme::Retitle,
+                    Readme::Retitle => Readme::Replace,
+                    Readme::Replace => Readme::Keep,
+                }
+            }
+            _ => return,
+        }
+        self.touched.insert(field);
+    }
+
+    fn value(&self, field: Field) -> String {
+        let yes_no = |value: bool| if value { "yes" } else { "no" }.to_owned();
+        match field {
+            Field::Mode => match self.new_or_init {
+                NewOrInit::New => "a new folder".to_owned(),
+                NewOrInit::Init => "in this folder".to_owned(),
+            },
+            Field::Name => self.name.clone(),
+            Field::Author => self.author.clone(),
+            Field::Email => self.email.clone(),
+            Field::Description => self.description.clone(),
+            Field::License => self.license.clone(),
+            Field::Git => yes_no(self.init_git),
+            Field::Readme => self.readme.to_string(),
+            Field::Direnv => yes_no(self.direnv),
+            Field::Ci => self.ci.to_string(),
+            Field::Devcontainer => yes_no(self.devcontainer),
+        }
+    }
+
+    /// Starts the fields that weren't touched out with the defaults for `template`
+    fn take_defaults(&mut self, config: &Config, template: &str) {
+        let defaults = config.defaults(Some(template));
+        let untouched = |field| !self.touched.contains(&field);
+        if untouched(Field::Author) {
+            self.author = defaults
+                .author
+                .or_else(|| self.git_author.clone())
+                .unwrap_or_default();
+        }
+        if untouched(Field::Email) {
+            self.email = defaults
+                .email
+                .or_else(|| self.git_email.clone())
+                .unwrap_or_default();
+        }
+        if untouched(Field::License) {
+            self.license = defaults
+                .license
+                .unwrap_or_else(|| DEFAULT_LICENSE.to_owned());
+        }
+        if untouched(Field::Git) {
+            self.init_git = defaults.init_git.unwrap_or(true);
+        }
+        if untouched(Field::Readme) {
+            self.readme = defaults.readme.unwrap_or(Readme::Keep);
+        }
+        if untouched(Field::Direnv) {
+            self.direnv = defaults.direnv.unwrap_or(self.direnv_installed);
+        }
+        if untouched(Field::Ci) {
+            self.ci = defaults.ci.unwrap_or(Ci::Skip);
+        }
+        if untouched(Field::Devcontainer) {
+            self.devcontainer = defaults.devcontainer.unwrap_or(false);
+        }
+    }
+}
+
+struct Tui<'a> {
+    sources: &'a Sources,
+    config: &'a Config,
+    templates: Vec<Template>,
+    filter: String,
+    /// Indexes in `templates` of the ones matching the filter
+    shown: Vec<usize>,
+    list: ListState,
+    focus: Focus,
+    form: Form,
+    field: usize,
+    files: BTreeMap<String, Files>,
+    fetched: (Sender<FetchedFiles>, Receiver<FetchedFiles>),
+    /// Why the form can't be submitted yet
+    problem: Option<String>,
+}
+
+/// What was picked on the screen, besides the options set in the arguments
+pub struct Picked {
+    pub new_or_init: NewOrInit,
+    pub project_name: String,
+}
+
+/// Shows the screen until the form is submitted, setting what it covers in `args`
+///
+/// The answers already given (in `args`, then in `answers`) start out the form.
+pub fn run(
+    sources: &Sources,
+    config: &Config,
+    args: &mut ScaffoldArgs,
+    new_or_init: Option<NewOrInit>,
+    project_name: Option<String>,
+    answers: &Answers,
+) -> Result<Picked> {
+    let templates = progress::spin("Fetching templates", || sources.unique_templates())?;
+    let mut tui = Tui::new(
+        sources,
+        config,
+        templates,
+        args,
+        new_or_init,
+        project_name,
+        answers,
+    );
+
+    let mut terminal = ratatui::try_init()?;
+    let submitted = tui.run(&mut terminal);
+    ratatui::restore();
+    let template = submitted?;
+
+    let form = tui.form;
+    args.template = Some(template);
+    args.author = Some(form.author);
+    args.email = Some(form.email);
+    args.description = Some(form.description);
+    args.license = Some(form.license);
+    (args.git, args.no_git) = (form.init_git, !form.init_git);
+    (args.clear_readme, args.no_clear_readme) = (false, false);
+    args.readme = Some(form.readme.to_string());
+    (args.direnv, args.no_direnv) = (form.direnv, !form.direnv);
+    args.ci = Some(form.ci.to_string());
+    (args.devcontainer, args.no_devcontainer) = (form.devcontainer, !form.devcontainer);
+    Ok(Picked {
+        new_or_init: form.new_or_init,
+        project_name: form.name,
+    })
+}
+
+impl<'a> Tui<'a> {
+    fn new(
+        sources: &'a Sources,
+        config: &'a Config,
+        templates: Vec<Template>,
+        args: &ScaffoldArgs,
+        new_or_init: Option<NewOrInit>,
+        project_name: Option<String>,
+        answers: &Answers,
+    ) -> Self {
+        let mut touched = BTreeSet::new();
+        let mut given = |field, value: Option<String>| {
+            if value.is_some() {
+                touched.insert(field);
+            }
+            value.unwrap_or_default()
+        };
+        let mut form = Form {
+            new_or_init: new_or_init.or(answers.mode).unwrap_or(NewOrInit::New),
+            name: given(Field::Name, project_name.or(answers.name.clone())),
+            author: given(
+                Field::Author,
+                args.author.clone().or(answers.author.clone()),
+            ),
+            email: given(Field::Email, args.email.clone().or(answers.email.clone())),
+            description: given(
+                Field::Description,
+                args.description.clone().or(answers.description.clone()),
+            ),
+            license: given(
+                Field::License,
+                args.license.clone().or(answers.license.clone()),
+            ),
+            init_git: false,
+            readme: Readme::Keep,
+            direnv: false,
+            ci: Ci::Skip,
+            devcontainer: false,
+            touched: BTreeSet::new(),
+            git_author: git_config("user.name"),
+            git_email: git_config("user.email"),
+            direnv_installed: direnv_installed(),
+        };
+        for (field, value, set) in [
+            (
+                Field::Git,
+                args.init_git().or(answers.git),
+                &mut form.init_git,
+            ),
+            (
+                Field::Direnv,
+                args.direnv().or(answers.direnv),
+                &mut form.direnv,
+            ),
+            (
+                Field::Devcontainer,
+                args.devcontainer().or(answers.devcontainer),
+                &mut form.devcontainer,
+            ),
+        ] {
+            if let Some(value) = value {
+                *set = value;
+                touched.insert(field);
+            }
+        }
+        if let Some(ci) = args
+            .ci
+            .as_deref()
+            .and_then(|ci| ci.parse().ok())
+            .or(answers.ci)
+        {
+            form.ci = ci;
+            touched.insert(Field::Ci);
+        }
+        if let Some(readme) = args.readme().ok().flatten().or(answers.readme) {
+            form.readme = readme;
+            touched.insert(Field::Readme);
+        }
+        form.touched = touched;
+
+        let given_template = args.template.clone().or(answers.template.clone());
+        let given_template = given_template.map(|template| config.template_name(&template));
+        let default = given_template.or_else(|| {
+            let template = config.template.as_deref()?;
+            Some(config.template_name(template))
+        });
+        let selected = default
+            .and_then(|default| {
+                templates
+                    .iter()
+                    .position(|template| template.name == default)
+            })
+            .unwrap_or(0);
+
+        let mut tui = Self {
+            sources,
+            config,
+            shown: (0..templates.len()).collect(),
+            templates,
+            filter: String::new(),
+            list: ListState::default().with_selected(Some(selected)),
+            focus: Focus::Templates,
+            form,
+            field: 1,
+            files: BTreeMap::new(),
+            fetched: mpsc::channel(),
+            problem: None,
+        };
+        tui.select(selected);
+        tui
+    }
+
+    /// Handles keys until the form is submitted, returning the selected template
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<String> {
+        loop {
+            while let Ok((template, files)) = self.fetched.1.try_recv() {
+                self.files.insert(template, files);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            // Raw mode keeps Ctrl-C from reaching the handler, so it's handled like it would be
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Err(io::Error::from(io::ErrorKind::Interrupted).into());
+            }
+
+            let submitted = match self.focus {
+                Focus::Templates => self.on_templates_key(key)?,
+                Focus::Form => self.on_form_key(key),
+            };
+            if let Some(template) = submitted {
+                return Ok(template);
+            }
+        }
+    }
+
+    fn on_templates_key(&mut self, key: KeyEvent) -> Result<Option<String>> {
+        match key.code {
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Enter | KeyCode::Tab if self.selected().is_some() => self.focus = Focus::Form,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.apply_filter();
+            }
+            KeyCode::Esc => return Err(GetflakeError::Aborted),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.apply_filter();
+            }
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.apply_filter();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn on_form_key(&mut self, key: KeyEvent) -> Option<String> {
+        let field = FIELDS[self.field];
+        self.problem = None;
+        match key.code {
+            KeyCode::Up | KeyCode::BackTab => self.field = self.field.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => self.field = (self.field + 1).min(FIELDS.len() - 1),
+            KeyCode::Esc => self.focus = Focus::Templates,
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') if !field.is_text() => {
+                self.form.toggle(field)
+            }
+            KeyCode::Backspace => {
+                if let Some(text) = self.form.text(field) {
+                    text.pop();
+                    self.form.touched.insert(field);
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(text) = self.form.text(field) {
+                    text.push(c);
+                    self.form.touched.insert(field);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The selected template, if the form's answers are valid for it
+    fn submit(&mut self) -> Option<String> {
+        let template = self.selected()?.name.clone();
+        let template_name = match self.sources.resolve(&template) {
+            Ok((_, template_name)) => template_name,
+            Err(e) => {
+                self.problem = Some(e.to_string());
+                return None;
+            }
+        };
+        if let Err(e) = validate::project_name(&template_name, &self.form.name) {
+            self.problem = Some(e.to_string());
+            self.field = FIELDS.iter().position(|&field| field == Field::Name)?;
+            return None;
+        }
+        Some(template)
+    }
+
+    fn selected(&self) -> Option<&Template> {
+        let index = self.shown.get(self.list.selected()?)?;
+        self.templates.get(*index)
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        if self.shown.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0);
+        let moved = current.saturating_add_signed(by).min(self.shown.len() - 1);
+        self.select(moved);
+    }
+
+    /// Selects the `index`th template shown, fetching its files if they aren't yet
+    fn select(&mut self, index: usize) {
+        self.list.select(Some(index));
+        let Some(template) = self.selected() else {
+            return;
+        };
+        let name = template.name.clone();
+        self.form.take_defaults(self.config, &name);
+        if self.files.contains_key(&name) {
+            return;
+        }
+
+        self.files.insert(name.clone(), Files::Loading);
+        let sources = self.sources.clone();
+        let sender = self.fetched.0.clone();
+        thread::spawn(move || {
+            // Anything printed would end up in the middle of the screen
+            let files = output::quietly(|| {
+                let (source, template) = sources.resolve(&name)?;
+                source.template_tree(&template)
+            });
+            let files = match files {
+                Ok(files) => Files::Fetched(files),
+                Err(e) => Files::Failed(e.to_string()),
+            };
+            let _ = sender.send((name, files));
+        });
+    }
+
+    fn apply_filter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.shown = self
+            .templates
+            .iter()
+            .enumerate()
+            .filter(|(_, template)| {
+                format!("{0} {1}", template.name, template.print_str)
+                    .to_lowercase()
+                    .contains(&filter)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if self.shown.is_empty() {
+            self.list.select(None);
+        } else {
+            self.select(0);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [details, form] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(FIELDS.len() as u16 + 3),
+        ])
+        .areas(right);
+
+        let focused = |focus| {
+            if self.focus == focus {
+                Style::new().fg(Color::Green)
+            } else {
+                Style::new()
+            }
+        };
+
+        let items: Vec<ListItem> = self
+            .shown
+            .iter()
+            .map(|&index| ListItem::new(self.templates[index].name.as_str()))
+            .collect();
+        let title = if self.filter.is_empty() {
+            " Templates (type to filter) ".to_owned()
+        } else {
+            format!(" Templates: {0} ", self.filter)
+        };
+        let list = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(focused(Focus::Templates)),
+            )
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        let mut lines = Vec::new();
+        if let Some(template) = self.selected() {
+            lines.push(Line::from(template.name.as_str().bold()));
+            lines.push(Line::from(template.print_str.as_str()));
+            lines.push(Line::default());
+            match self.files.get(&template.name) {
+                Some(Files::Fetched(files)) => {
+                    lines.extend(files.iter().map(|line| Line::from(line.as_str())))
+                }
+                Some(Files::Failed(e)) => lines.push(Line::from(e.as_str().red())),
+                Some(Files::Loading) | None => {
+                    lines.push(Line::from("Fetching the files...".dim()))
+                }
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Template "))
+                .wrap(Wrap { trim: false }),
+            details,
+        );
+
+        let mut lines: Vec<Line> = FIELDS
+            .iter()
+            .enumerate()
+            .map(|(index, &field)| {
+                let is_current = self.focus == Focus::Form && index == self.field;
+                let mut value = self.form.value(field);
+                if is_current && field.is_text() {
+                    value.push('▏');
+                }
+                let label = format!("{0:>16}: ", field.label());
+                Line::from(vec![
+                    Span::raw(label),
+                    if is_current {
+                        Span::raw(value).reversed()
+                    } else {
+                        Span::raw(value)
+                    },
+                ])
+            })
+            .collect();
+        if let Some(problem) = &self.problem {
+            lines.push(Line::from(problem.as_str().red()));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .title(" Project ")
+                    .border_style(focused(Focus::Form)),
+            ),
+            form,
+        );
+
+        let keys = match self.focus {
+            Focus::Templates => {
+                "↑↓ select · type to filter · Enter/Tab fill in the project · Esc quit"
+            }
+            Focus::Form => "↑↓ field · Space toggle · Enter create · Esc back to the templates",
+        };
+        frame.render_widget(Paragraph::new(keys.dim()), help);
+    }
+}
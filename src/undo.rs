@@ -0,0 +1,234 @@
+//! What the last scaffold changed, so `getflake undo` can take it back
+//!
+//! A new project is undone by removing its folder. A project initialized in an existing folder is
+//! undone by removing the files and folders it created, and restoring the files it changed from
+//! the copies taken beforehand. The initial commit it made in a repository that was already there
+//! is reset too.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::cache_dir,
+    error::{GetflakeError, Result},
+    output::RunLogged,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UndoState {
+    /// Absolute path of the project's folder
+    pub directory: PathBuf,
+    /// Whether getflake created the folder, in which case undoing removes all of it
+    pub created_directory: bool,
+    /// Files and folders created in an existing folder
+    pub created: Vec<PathBuf>,
+    /// Files that existed before and were changed, mapped to the copies of their old contents
+    pub modified: BTreeMap<PathBuf, PathBuf>,
+    /// The commit made in a repository that existed before
+    #[serde(default)]
+    pub commit: Option<Commit>,
+}
+
+/// A commit made by a scaffold, in a repository it didn't create
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Commit {
+    pub id: String,
+    /// What `HEAD` was before, or `None` if the repository had no commits yet
+    pub parent: Option<String>,
+}
+
+/// Copies of the files of a folder, taken before a project is initialized in it
+#[derive(Debug, Default)]
+pub struct Backup {
+    files: BTreeMap<PathBuf, PathBuf>,
+}
+
+fn undo_dir() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("undo"))
+}
+
+fn state_path() -> Option<PathBuf> {
+    undo_dir().map(|dir| dir.join("state.json"))
+}
+
+/// Copies the files among `existing` into the cache, replacing the copies of the last scaffold
+pub fn back_up(existing: &HashSet<PathBuf>) -> Result<Backup> {
+    let Some(undo_dir) = undo_dir() else {
+        return Ok(Backup::default());
+    };
+    clear();
+
+    let files_dir = undo_dir.join("files");
+    fs::create_dir_all(&files_dir)?;
+    let mut backup = Backup::default();
+    for (i, path) in existing.iter().filter(|path| path.is_file()).enumerate() {
+        let copy = files_dir.join(i.to_string());
+        fs::copy(path, &copy)?;
+        backup.files.insert(path.clone(), copy);
+    }
+    Ok(backup)
+}
+
+impl Backup {
+    /// Records that a project was initialized in `directory`, creating the `created` paths and
+    /// making `commit`, and only keeps the copies of the files that changed since
+    pub fn save(
+        self,
+        directory: &Path,
+        created: Vec<PathBuf>,
+        commit: Option<Commit>,
+    ) -> Result<()> {
+        let mut modified = BTreeMap::new();
+        for (path, copy) in self.files {
+            let unchanged = fs::read(&path).ok() == fs::read(&copy).ok();
+            if unchanged {
+                fs::remove_file(&copy)?;
+            } else {
+                modified.insert(absolute(&path), copy);
+            }
+        }
+
+        // Removing a folder removes what's in it
+        let created_folders: Vec<PathBuf> = created
+            .iter()
+            .filter(|path| path.is_dir())
+            .cloned()
+            .collect();
+        let created = created
+            .iter()
+            .filter(|path| {
+                !created_folders
+                    .iter()
+                    .any(|folder| path.starts_with(folder) && path != &folder)
+            })
+            .map(|path| absolute(path))
+            .collect();
+
+        save(&UndoState {
+            directory: absolute(directory),
+            created_directory: false,
+            created,
+            modified,
+            commit,
+        })
+    }
+}
+
+/// Records that a new project was created in `directory`
+pub fn save_created(directory: &Path) -> Result<()> {
+    clear();
+    save(&UndoState {
+        directory: absolute(directory),
+        created_directory: true,
+        created: Vec::new(),
+        modified: BTreeMap::new(),
+        commit: None,
+    })
+}
+
+fn save(state: &UndoState) -> Result<()> {
+    let Some(path) = state_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+/// Returns what the last scaffold changed, unless it was undone already
+pub fn load() -> Option<UndoState> {
+    let content = fs::read_to_string(state_path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Forgets the last scaffold, along with the copies of the files it changed
+pub fn clear() {
+    if let Some(undo_dir) = undo_dir() {
+        let _ = fs::remove_dir_all(undo_dir);
+    }
+}
+
+impl UndoState {
+    /// Takes back the changes of the scaffold, then forgets it
+    pub fn undo(&self) -> Result<()> {
+        if self.created_directory {
+            if self.directory.exists() {
+                fs::remove_dir_all(&self.directory)?;
+            }
+        } else {
+            if let Some(commit) = &self.commit {
+                self.reset(commit)?;
+            }
+            for path in &self.created {
+                if path.is_dir() {
+                    fs::remove_dir_all(path)?;
+                } else if path.exists() {
+                    fs::remove_file(path)?;
+                }
+            }
+            for (path, copy) in &self.modified {
+                fs::copy(copy, path)?;
+            }
+        }
+        clear();
+        Ok(())
+    }
+}
+
+impl UndoState {
+    /// Resets the repository to before `commit`, keeping the files, which are taken back after
+    ///
+    /// Commits made since are left alone, along with the one of the scaffold.
+    fn reset(&self, commit: &Commit) -> Result<()> {
+        if head(&self.directory).as_ref() != Some(&commit.id) {
+            return Ok(());
+        }
+        match &commit.parent {
+            Some(parent) => git(&self.directory, &["reset", "--quiet", parent]),
+            // Back to a branch without commits, with nothing staged
+            None => {
+                git(&self.directory, &["update-ref", "-d", "HEAD"])?;
+                git(&self.directory, &["reset", "--quiet"])
+            }
+        }
+    }
+}
+
+/// The commit `HEAD` points to in the repository in `directory`, if there's one
+pub fn head(directory: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .run_logged()
+        .ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
+
+fn git(directory: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .run_logged()?;
+    if !output.status.success() {
+        return Err(GetflakeError::CommandFailed {
+            command: format!("git -C {0} {1}", directory.display(), args.join(" ")),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+    Ok(())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,173 @@
+//! Bringing a project up to date with the latest version of its template
+
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    cache,
+    colors::{GREEN, RESET},
+    error::{GetflakeError, Result},
+    icons::{DONE, ERROR, GENERATE, HINT, OK, PLAN, UPDATE, UP_TO_DATE, WARNING},
+    output::RunLogged,
+    provenance::Provenance,
+    render::{self, render},
+    say,
+    templates::{pin, Source},
+};
+
+/// Merges the changes the template made since the project in `directory` was generated into it
+///
+/// Each file is merged with `git merge-file`, against the template at the revision recorded in
+/// `.getflake.toml`, so the project's own changes are kept and conflicts are marked in the file.
+pub fn upgrade(directory: &Path, dry_run: bool) -> Result<()> {
+    let mut provenance = Provenance::load(directory)?;
+    let source = Source::new(provenance.source.clone(), cache::DEFAULT_TTL, false);
+    let (Some(rev), Some(latest_rev)) = (provenance.rev.clone(), source.resolved_rev()?) else {
+        return Err(GetflakeError::UnknownRevision(source.url));
+    };
+    if latest_rev == rev {
+        say!(
+            "{UP_TO_DATE} The project is up to date with {GREEN}{0}{RESET}",
+            source.url
+        );
+        return Ok(());
+    }
+
+    say!(
+        "{GENERATE} Generating {GREEN}{0}{RESET} at {rev} and at {latest_rev}...",
+        provenance.template
+    );
+    let pinned = |rev: &str| Source::new(pin(&source.url, rev), cache::DEFAULT_TTL, false);
+    let base = render(&pinned(&rev), &provenance.template, &provenance.variables)?;
+    let latest = render(
+        &pinned(&latest_rev),
+        &provenance.template,
+        &provenance.variables,
+    )?;
+
+    if dry_run {
+        say!("\n{PLAN} Dry run, nothing will be changed. getflake would:");
+    } else {
+        say!("\n{UPDATE} Merging the changes to the template...");
+    }
+
+    let base_files = render::files(base.path());
+    let latest_files = render::files(latest.path());
+    let mut conflicts = 0;
+
+    for file in base_files.union(&latest_files) {
+        let file_name = file.to_string_lossy();
+        let base_file = base.path().join(file);
+        let latest_file = latest.path().join(file);
+        let project_file = directory.join(file);
+
+        let in_base = base_files.contains(file);
+        let in_latest = latest_files.contains(file);
+        let in_project = project_file.exists();
+
+        match (in_base, in_latest, in_project) {
+            (false, true, false) => {
+                if dry_run {
+                    say!("- Add {file_name}");
+                    continue;
+                }
+                if let Some(parent) = project_file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&latest_file, &project_file)?;
+                say!("- {OK} Added {file_name}");
+            }
+            (true, true, false) => say!("- {HINT} Skipped {file_name}, which the project removed"),
+            (true, false, true) => {
+                if !same_contents(&base_file, &project_file) {
+                    say!(
+                        "- {HINT} Kept {file_name}, which the template removed but the project changed"
+                    );
+                } else if dry_run {
+                    say!("- Remove {file_name}");
+                } else {
+                    fs::remove_file(&project_file)?;
+                    say!("- {OK} Removed {file_name}");
+                }
+            }
+            (_, true, true) => {
+                if in_base && same_contents(&base_file, &latest_file) {
+                    continue;
+                }
+                if same_contents(&latest_file, &project_file) {
+                    continue;
+                }
+                if dry_run {
+                    say!("- Merge the changes to {file_name}");
+                    continue;
+                }
+                // Both sides added the file, so it's merged against an empty one
+                if !in_base {
+                    if let Some(parent) = base_file.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&base_file, "")?;
+                }
+                match merge_file(&project_file, &base_file, &latest_file) {
+                    Ok(0) => say!("- {OK} Merged the changes to {file_name}"),
+                    Ok(_) => {
+                        conflicts += 1;
+                        eprintln!("- {ERROR}Conflicts in {file_name}");
+                    }
+                    Err(e) => eprintln!("- {ERROR}Failed to merge the changes to {file_name}\n{e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    provenance.rev = Some(latest_rev);
+    provenance.getflake_version = env!("CARGO_PKG_VERSION").to_owned();
+    provenance.write(directory)?;
+
+    if conflicts > 0 {
+        say!("\n{WARNING} Upgraded with conflicts in {conflicts} files, resolve them and remove the conflict markers");
+    } else {
+        say!("\n{DONE} Upgraded the project {GREEN}successfully{RESET}");
+    }
+
+    Ok(())
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    match (fs::read(a), fs::read(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Merges the changes from `base` to `latest` into `project` in place, returning the number of
+/// conflicts
+fn merge_file(project: &Path, base: &Path, latest: &Path) -> Result<i32> {
+    let output = Command::new("git")
+        .args([
+            "merge-file",
+            "-L",
+            "project",
+            "-L",
+            "base",
+            "-L",
+            "template",
+        ])
+        .arg(project)
+        .arg(base)
+        .arg(latest)
+        .run_logged()?;
+
+    // The exit code is the number of conflicts, or negative on errors
+    match output.status.code() {
+        Some(conflicts @ 0..=127) => Ok(conflicts),
+        _ => Err(GetflakeError::CommandFailed {
+            command: format!("git merge-file {0}", project.to_string_lossy()),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        }),
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    error::{GetflakeError, Result},
+    templates::is_language,
+};
+
+/// Checks that `name` makes a working project with `template`, following the conventions of its language
+pub fn project_name(template: &str, name: &str) -> Result<()> {
+    let invalid = |message: &str| Err(GetflakeError::InvalidInput(message.to_owned()));
+
+    if name.is_empty() {
+        return invalid("the project name can't be empty");
+    }
+    if name == "." || name == ".." || name.contains(['/', '\\']) {
+        return invalid("the project name must be a valid folder name");
+    }
+    // It ends up in flake attribute names and store paths
+    if name.chars().any(char::is_whitespace) {
+        return invalid("the project name can't contain spaces");
+    }
+
+    let starts_with_digit = name.starts_with(|c: char| c.is_ascii_digit());
+    let is_identifier = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    let is_lowercase = !name.chars().any(|c| c.is_ascii_uppercase());
+
+    let language_error = if is_language(template, "rust") && (!is_identifier || starts_with_digit) {
+        Some("Rust crate names may only contain letters, digits, '-' and '_', and can't start with a digit")
+    } else if is_language(template, "python") && (!is_identifier || starts_with_digit) {
+        Some("Python package names may only contain letters, digits, '-' and '_', and can't start with a digit")
+    } else if is_language(template, "go") && !is_lowercase {
+        Some("Go module names should be lowercase")
+    } else if (is_language(template, "default") || is_language(template, "nix"))
+        && (!is_identifier || starts_with_digit || !is_lowercase)
+    {
+        Some("Nix attribute names should be lowercase letters, digits, '-' and '_', and can't start with a digit")
+    } else {
+        None
+    };
+
+    if let Some(message) = language_error {
+        return invalid(message);
+    }
+
+    Ok(())
+}
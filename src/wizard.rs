@@ -0,0 +1,725 @@
+//! The wizard's questions, asked one step after the other
+//!
+//! Answering `back` (or pressing Esc in a menu) goes back to the previous question that was
+//! asked, skipping the ones answered on the command line or in the answers file. The steps after
+//! it are worked out again, since their questions may depend on the new answer.
+
+use std::{collections::BTreeMap, path::Path, thread::JoinHandle};
+
+use getflake::{
+    answers::Answers,
+    colors::{GREEN, RESET},
+    config::Config,
+    icons::{FOLDER, WARNING},
+    manifest::{Manifest, Variable, VariableKind},
+    output, progress,
+    prompt::{self, retry, DEFAULT_MAX_ATTEMPTS},
+    recent, say,
+    scaffold::{
+        direnv_installed, expand_home, gh_installed, git_config, is_non_empty_dir,
+        DEFAULT_COMMIT_MESSAGE, DEFAULT_LICENSE,
+    },
+    templates::{self, Source, Sources, Templates},
+    validate, Ci, ExistingDirectory, GetflakeError, GitHubRepo, NewOrInit, Readme, Result,
+};
+
+use crate::args::ScaffoldArgs;
+
+/// A question of the wizard, or a few that only make sense together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Mode,
+    Author,
+    Email,
+    Description,
+    License,
+    Git,
+    Commit,
+    CommitMessage,
+    GitHubRepo,
+    PreCommit,
+    Readme,
+    Direnv,
+    Ci,
+    Devcontainer,
+    Template,
+    Name,
+    ExistingDirectory,
+    MergeFlake,
+    Remote,
+    Push,
+    Variables,
+}
+
+/// The steps in the order they're taken
+///
+/// The template is picked after the questions that don't depend on it, so the templates can be
+/// fetched in the meantime. When templates have defaults of their own in the config file, it's
+/// picked first instead, so the questions start out with them.
+const STEPS: &[Step] = &[
+    Step::Mode,
+    Step::Author,
+    Step::Email,
+    Step::Description,
+    Step::License,
+    Step::Git,
+    Step::Commit,
+    Step::CommitMessage,
+    Step::GitHubRepo,
+    Step::PreCommit,
+    Step::Readme,
+    Step::Direnv,
+    Step::Ci,
+    Step::Devcontainer,
+    Step::Template,
+    Step::Name,
+    Step::ExistingDirectory,
+    Step::MergeFlake,
+    Step::Remote,
+    Step::Push,
+    Step::Variables,
+];
+
+const ANSWERED: &str = "every step is answered once the last one is done";
+
+/// Everything the project is scaffolded with
+pub struct Answered {
+    pub source: Source,
+    pub template: String,
+    pub new_or_init: NewOrInit,
+    pub project_name: String,
+    pub target_directory: Option<String>,
+    pub existing_directory: Option<ExistingDirectory>,
+    pub merge_flake: bool,
+    pub author: String,
+    pub email: String,
+    pub description: String,
+    pub license: String,
+    pub init_git: bool,
+    pub commit: bool,
+    pub commit_message: String,
+    pub github_repo: GitHubRepo,
+    pub remote: Option<String>,
+    pub push: bool,
+    pub pre_commit: bool,
+    pub readme: Readme,
+    pub direnv: bool,
+    pub ci: Ci,
+    pub devcontainer: bool,
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Answered {
+    /// The answers, as they're recorded with `--record` and in the history
+    pub fn answers(&self) -> Answers {
+        Answers {
+            template: Some(self.template.clone()),
+            mode: Some(self.new_or_init),
+            name: Some(self.project_name.clone()),
+            author: Some(self.author.clone()),
+            email: Some(self.email.clone()),
+            description: Some(self.description.clone()),
+            license: Some(self.license.clone()),
+            git: Some(self.init_git),
+            commit: Some(self.commit),
+            commit_message: Some(self.commit_message.clone()),
+            github_repo: Some(self.github_repo),
+            remote: Some(self.remote.clone().unwrap_or_default()),
+            push: Some(self.push),
+            pre_commit: Some(self.pre_commit),
+            readme: Some(self.readme),
+            direnv: Some(self.direnv),
+            ci: Some(self.ci),
+            devcontainer: Some(self.devcontainer),
+            variables: self.variables.clone(),
+        }
+    }
+}
+
+pub struct Wizard<'a> {
+    sources: &'a Sources,
+    config: &'a Config,
+    args: &'a ScaffoldArgs,
+    max_attempts: u32,
+    steps: Vec<Step>,
+    /// Answers given on the command line or in the answers file, which aren't asked for
+    given: Answers,
+    /// The templates being fetched in the background, until the picker needs them
+    prefetch: Option<JoinHandle<Result<Templates>>>,
+    templates: Option<Templates>,
+    /// The manifest of the template picked last, so going back doesn't read it again
+    manifest: Option<(String, Manifest)>,
+
+    source: Option<Source>,
+    template: Option<String>,
+    new_or_init: Option<NewOrInit>,
+    project_name: Option<String>,
+    existing_directory: Option<ExistingDirectory>,
+    merge_flake: Option<bool>,
+    author: Option<String>,
+    email: Option<String>,
+    description: Option<String>,
+    license: Option<String>,
+    init_git: Option<bool>,
+    commit: Option<bool>,
+    commit_message: Option<String>,
+    github_repo: Option<GitHubRepo>,
+    remote: Option<String>,
+    push: Option<bool>,
+    pre_commit: Option<bool>,
+    readme: Option<Readme>,
+    direnv: Option<bool>,
+    ci: Option<Ci>,
+    devcontainer: Option<bool>,
+    variables: BTreeMap<String, String>,
+}
+
+impl<'a> Wizard<'a> {
+    /// Answers in `args` take precedence over the ones in `answers`
+    pub fn new(
+        sources: &'a Sources,
+        config: &'a Config,
+        args: &'a ScaffoldArgs,
+        new_or_init: Option<NewOrInit>,
+        project_name: Option<String>,
+        answers: Answers,
+    ) -> Result<Self> {
+        let given = Answers {
+            template: args
+                .template
+                .clone()
+                .or(answers.template)
+                .or_else(|| config.template.clone().filter(|_| args.yes)),
+            mode: new_or_init.or(answers.mode),
+            name: project_name.or(answers.name),
+            author: args.author.clone().or(answers.author),
+            email: args.email.clone().or(answers.email),
+            description: args.description.clone().or(answers.description),
+            license: args.license.clone().or(answers.license),
+            git: args.init_git().or(answers.git),
+            commit: args.commit().or(answers.commit),
+            commit_message: args.commit_message.clone().or(answers.commit_message),
+            github_repo: match args.github_repo.as_deref().map(str::parse).transpose()? {
+                Some(github_repo) => Some(github_repo),
+                None => answers.github_repo,
+            },
+            remote: args.remote.clone().or(answers.remote),
+            push: args.push().or(answers.push),
+            pre_commit: args.pre_commit().or(answers.pre_commit),
+            readme: args.readme()?.or(answers.readme),
+            direnv: args.direnv().or(answers.direnv),
+            ci: match args.ci.as_deref().map(str::parse).transpose()? {
+                Some(ci) => Some(ci),
+                None => answers.ci,
+            },
+            devcontainer: args.devcontainer().or(answers.devcontainer),
+            variables: answers.variables,
+        };
+
+        // Fetching the templates takes longest, so it's done while the questions that don't
+        // depend on the template are answered, unless the template has defaults for them
+        let mut steps = STEPS.to_vec();
+        if given.template.is_none() && config.has_template_defaults() {
+            steps.retain(|&step| step != Step::Template);
+            steps.insert(0, Step::Template);
+        }
+        let prefetch = given.template.is_none().then(|| {
+            let sources = sources.clone();
+            std::thread::spawn(move || output::quietly(|| sources.unique_templates()))
+        });
+
+        Ok(Self {
+            sources,
+            config,
+            args,
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            steps,
+            given,
+            prefetch,
+            templates: None,
+            manifest: None,
+            source: None,
+            template: None,
+            new_or_init: None,
+            project_name: None,
+            existing_directory: None,
+            merge_flake: None,
+            author: None,
+            email: None,
+            description: None,
+            license: None,
+            init_git: None,
+            commit: None,
+            commit_message: None,
+            github_repo: None,
+            remote: None,
+            push: None,
+            pre_commit: None,
+            readme: None,
+            direnv: None,
+            ci: None,
+            devcontainer: None,
+            variables: BTreeMap::new(),
+        })
+    }
+
+    /// Takes every step, going back whenever asked to
+    pub fn run(mut self) -> Result<Answered> {
+        // The steps whose questions were asked, which are the ones to go back to
+        let mut asked: Vec<usize> = Vec::new();
+        let mut index = 0;
+        while let Some(&step) = self.steps.get(index) {
+            match self.take(step) {
+                Ok(true) => {
+                    asked.push(index);
+                    index += 1;
+                }
+                Ok(false) => index += 1,
+                Err(GetflakeError::Back) => {
+                    // At the first question, it's asked again
+                    if let Some(previous) = asked.pop() {
+                        index = previous;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Answered {
+            source: self.source.expect(ANSWERED),
+            template: self.template.expect(ANSWERED),
+            new_or_init: self.new_or_init.expect(ANSWERED),
+            project_name: self.project_name.expect(ANSWERED),
+            target_directory: self.args.dir.as_deref().map(expand_home),
+            existing_directory: self.existing_directory,
+            merge_flake: self.merge_flake.expect(ANSWERED),
+            author: self.author.expect(ANSWERED),
+            email: self.email.expect(ANSWERED),
+            description: self.description.expect(ANSWERED),
+            license: self.license.expect(ANSWERED),
+            init_git: self.init_git.expect(ANSWERED),
+            commit: self.commit.expect(ANSWERED),
+            commit_message: self.commit_message.expect(ANSWERED),
+            github_repo: self.github_repo.expect(ANSWERED),
+            remote: self.remote,
+            push: self.push.expect(ANSWERED),
+            pre_commit: self.pre_commit.expect(ANSWERED),
+            readme: self.readme.expect(ANSWERED),
+            direnv: self.direnv.expect(ANSWERED),
+            ci: self.ci.expect(ANSWERED),
+            devcontainer: self.devcontainer.expect(ANSWERED),
+            variables: self.variables,
+        })
+    }
+
+    /// Answers `step`, returning whether its question was asked
+    fn take(&mut self, step: Step) -> Result<bool> {
+        let max_attempts = self.max_attempts;
+        let config = self.config;
+        let given = &self.given;
+        let yes = self.takes_defaults();
+        let defaults = config.defaults(self.template.as_deref());
+
+        match step {
+            Step::Mode => {
+                let (new_or_init, was_asked) = match given.mode {
+                    Some(new_or_init) => (new_or_init, false),
+                    None if yes => (NewOrInit::New, false),
+                    None => (retry(max_attempts, prompt::new_or_init)?, true),
+                };
+                self.new_or_init = Some(new_or_init);
+                Ok(was_asked)
+            }
+            Step::Author => {
+                let default = defaults
+                    .author
+                    .or_else(|| git_config("user.name"))
+                    .unwrap_or_default();
+                let (author, was_asked) = match &given.author {
+                    Some(author) => (author.clone(), false),
+                    None if yes => (default, false),
+                    None => (prompt::author(&default)?, true),
+                };
+                self.author = Some(author);
+                Ok(was_asked)
+            }
+            Step::Email => {
+                let default = defaults
+                    .email
+                    .or_else(|| git_config("user.email"))
+                    .unwrap_or_default();
+                let (email, was_asked) = match &given.email {
+                    Some(email) => (email.clone(), false),
+                    None if yes => (default, false),
+                    None => (prompt::email(&default)?, true),
+                };
+                self.email = Some(email);
+                Ok(was_asked)
+            }
+            Step::Description => {
+                let (description, was_asked) = match &given.description {
+                    Some(description) => (description.clone(), false),
+                    None if yes => (String::new(), false),
+                    None => (prompt::description()?, true),
+                };
+                self.description = Some(description);
+                Ok(was_asked)
+            }
+            Step::License => {
+                let default = defaults.license.as_deref().unwrap_or(DEFAULT_LICENSE);
+                let (license, was_asked) = match &given.license {
+                    Some(license) => (license.clone(), false),
+                    None if yes => (default.to_owned(), false),
+                    None => (prompt::license(default)?, true),
+                };
+                self.license = Some(license);
+                Ok(was_asked)
+            }
+            Step::Git => {
+                let default = defaults.init_git.unwrap_or(true);
+                let (init_git, was_asked) = match given.git {
+                    Some(init_git) => (init_git, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::init_git(default))?, true),
+                };
+                self.init_git = Some(init_git);
+                Ok(was_asked)
+            }
+            Step::Commit => {
+                let (commit, was_asked) = match given.commit {
+                    _ if !self.init_git() => (false, false),
+                    Some(commit) => (commit, false),
+                    None if yes => (true, false),
+                    None => (retry(max_attempts, prompt::commit)?, true),
+                };
+                self.commit = Some(commit);
+                Ok(was_asked)
+            }
+            Step::CommitMessage => {
+                let (commit_message, was_asked) = match &given.commit_message {
+                    Some(commit_message) => (commit_message.clone(), false),
+                    None if self.commit == Some(true) && !yes => (prompt::commit_message()?, true),
+                    None => (DEFAULT_COMMIT_MESSAGE.to_owned(), false),
+                };
+                self.commit_message = Some(commit_message);
+                Ok(was_asked)
+            }
+            Step::GitHubRepo => {
+                let (github_repo, was_asked) = match given.github_repo {
+                    _ if !self.init_git() => (GitHubRepo::Skip, false),
+                    Some(github_repo) => (github_repo, false),
+                    None if !yes && self.args.remote.is_none() && gh_installed() => {
+                        (retry(max_attempts, prompt::github_repo)?, true)
+                    }
+                    None => (GitHubRepo::Skip, false),
+                };
+                self.github_repo = Some(github_repo);
+                Ok(was_asked)
+            }
+            Step::PreCommit => {
+                let default = defaults.pre_commit.unwrap_or(false);
+                let (pre_commit, was_asked) = match given.pre_commit {
+                    _ if !self.init_git() => (false, false),
+                    Some(pre_commit) => (pre_commit, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::pre_commit(default))?, true),
+                };
+                self.pre_commit = Some(pre_commit);
+                Ok(was_asked)
+            }
+            Step::Readme => {
+                let default = defaults.readme.unwrap_or(Readme::Keep);
+                let (readme, was_asked) = match given.readme {
+                    Some(readme) => (readme, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::readme(default))?, true),
+                };
+                self.readme = Some(readme);
+                Ok(was_asked)
+            }
+            Step::Direnv => {
+                let default = defaults.direnv.unwrap_or_else(direnv_installed);
+                let (direnv, was_asked) = match given.direnv {
+                    Some(direnv) => (direnv, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::direnv(default))?, true),
+                };
+                self.direnv = Some(direnv);
+                Ok(was_asked)
+            }
+            Step::Ci => {
+                let default = defaults.ci.unwrap_or(Ci::Skip);
+                let (ci, was_asked) = match given.ci {
+                    Some(ci) => (ci, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::ci(default))?, true),
+                };
+                self.ci = Some(ci);
+                Ok(was_asked)
+            }
+            Step::Devcontainer => {
+                let default = defaults.devcontainer.unwrap_or(false);
+                let (devcontainer, was_asked) = match given.devcontainer {
+                    Some(devcontainer) => (devcontainer, false),
+                    None if yes => (default, false),
+                    None => (retry(max_attempts, || prompt::devcontainer(default))?, true),
+                };
+                self.devcontainer = Some(devcontainer);
+                Ok(was_asked)
+            }
+            Step::Template => self.template(),
+            Step::Name => {
+                let template = self.template.clone().expect(ANSWERED);
+                let (project_name, was_asked) = match &self.given.name {
+                    Some(project_name) => {
+                        validate::project_name(&template, project_name)?;
+                        (project_name.clone(), false)
+                    }
+                    None => (
+                        retry(max_attempts, || prompt::project_name(&template))?,
+                        true,
+                    ),
+                };
+                self.project_name = Some(project_name);
+                Ok(was_asked)
+            }
+            Step::ExistingDirectory => {
+                let directory = match self.args.dir.as_deref() {
+                    Some(dir) => expand_home(dir),
+                    None => self.project_name.clone().expect(ANSWERED),
+                };
+                let is_new = matches!(self.new_or_init, Some(NewOrInit::New));
+                let (existing_directory, was_asked) = if !is_new || !is_non_empty_dir(&directory) {
+                    (None, false)
+                } else if self.args.force {
+                    (Some(ExistingDirectory::Overwrite), false)
+                } else {
+                    let existing_directory =
+                        retry(max_attempts, || prompt::existing_directory(&directory))?;
+                    (Some(existing_directory), true)
+                };
+                self.existing_directory = existing_directory;
+                Ok(was_asked)
+            }
+            Step::MergeFlake => {
+                let is_new = matches!(self.new_or_init, Some(NewOrInit::New));
+                let directory = match self.args.dir.as_deref() {
+                    Some(dir) => expand_home(dir),
+                    None if is_new => self.project_name.clone().expect(ANSWERED),
+                    None => ".".to_owned(),
+                };
+                // Only the folders the template is copied into as they are can have one
+                let copied_into =
+                    !is_new || matches!(self.existing_directory, Some(ExistingDirectory::Merge));
+                let has_flake = copied_into && Path::new(&directory).join("flake.nix").is_file();
+                let (merge_flake, was_asked) = match self.args.merge_flake() {
+                    _ if !has_flake => (false, false),
+                    Some(merge_flake) => (merge_flake, false),
+                    None if yes => (true, false),
+                    None => (
+                        retry(max_attempts, || prompt::merge_flake(&directory))?,
+                        true,
+                    ),
+                };
+                self.merge_flake = Some(merge_flake);
+                Ok(was_asked)
+            }
+            Step::Remote => {
+                let skipped = !self.init_git() || self.github_repo != Some(GitHubRepo::Skip);
+                let (remote, was_asked) = match &given.remote {
+                    _ if skipped => (None, false),
+                    Some(remote) => (
+                        Some(remote.clone()).filter(|remote| !remote.is_empty()),
+                        false,
+                    ),
+                    None => {
+                        let project_name = self.project_name.as_deref().expect(ANSWERED);
+                        let suggested = config.suggested_remote(project_name).unwrap_or_default();
+                        if yes {
+                            (Some(suggested).filter(|remote| !remote.is_empty()), false)
+                        } else {
+                            (prompt::remote(&suggested)?, true)
+                        }
+                    }
+                };
+                self.remote = remote;
+                Ok(was_asked)
+            }
+            Step::Push => {
+                let nowhere = self.remote.is_none() && self.github_repo == Some(GitHubRepo::Skip);
+                let (push, was_asked) = match given.push {
+                    _ if self.commit != Some(true) || nowhere => (false, false),
+                    Some(push) => (push, false),
+                    None if yes => (true, false),
+                    None => (retry(max_attempts, prompt::push)?, true),
+                };
+                self.push = Some(push);
+                Ok(was_asked)
+            }
+            Step::Variables => self.variables(),
+        }
+    }
+
+    /// The value `variable` starts out with, from the template's `extra_vars` in the config
+    /// file or from its manifest
+    fn default_variable(
+        &self,
+        variable: &Variable,
+        extra_vars: &BTreeMap<String, String>,
+    ) -> Option<String> {
+        let default = extra_vars
+            .get(&variable.name)
+            .cloned()
+            .or_else(|| variable.default_value());
+        match variable.kind {
+            VariableKind::Bool if self.takes_defaults() => default.or(Some("false".to_owned())),
+            _ => default,
+        }
+    }
+
+    /// Whether questions with a default take it without being asked: with `--yes`, or when
+    /// there's no terminal to answer them in
+    fn takes_defaults(&self) -> bool {
+        self.args.yes || !prompt::is_interactive()
+    }
+
+    fn init_git(&self) -> bool {
+        self.init_git == Some(true)
+    }
+
+    /// Picks the template, once the templates are fetched
+    fn template(&mut self) -> Result<bool> {
+        let (template, was_asked) = match self.given.template.clone() {
+            Some(template) => (template, false),
+            None => {
+                if self.templates.is_none() {
+                    let templates = match self.prefetch.take() {
+                        Some(prefetch) => progress::spin("Fetching templates", || prefetch.join())
+                            .expect("fetching the templates panicked")?,
+                        None => self.sources.unique_templates()?,
+                    };
+                    self.templates = Some(templates);
+                }
+                let mut default = self.template.clone().or_else(|| {
+                    let template = self.config.template.as_deref()?;
+                    Some(self.config.template_name(template))
+                });
+                let preview = self.args.preview || self.config.preview == Some(true);
+                loop {
+                    let template = prompt::template(
+                        self.templates
+                            .as_ref()
+                            .expect("the templates were just fetched"),
+                        default.as_deref(),
+                        self.config.favorites.as_deref().unwrap_or_default(),
+                        &self
+                            .sources
+                            .prefixed_names(|source| recent::load(&source.url)),
+                        self.max_attempts,
+                    )?;
+                    if !preview || self.preview(&template)? {
+                        break (template, true);
+                    }
+                    default = Some(template);
+                }
+            }
+        };
+
+        let template = self.config.template_name(&template);
+        let (source, template) = self.sources.resolve(&template)?;
+        if templates::is_offline() {
+            progress::spin("Checking that the template is available offline", || {
+                source.check_offline(&template)
+            })?;
+        }
+        self.source = Some(source);
+        self.template = Some(template);
+        Ok(was_asked)
+    }
+
+    /// Shows the files of the picked `template`, returning whether to use it rather than pick
+    /// another one
+    fn preview(&self, template: &str) -> Result<bool> {
+        let (source, name) = self.sources.resolve(&self.config.template_name(template))?;
+        match progress::spin("Fetching the template's files", || {
+            source.template_tree(&name)
+        }) {
+            Ok(files) => {
+                say!("{FOLDER} Files of {GREEN}{template}{RESET}:");
+                for line in files {
+                    say!("  {line}");
+                }
+            }
+            Err(e) => {
+                eprintln!("{WARNING} Unable to list the files of the template\n{e}");
+                return Ok(true);
+            }
+        }
+        match retry(self.max_attempts, || prompt::use_template(template)) {
+            Err(GetflakeError::Back) => Ok(false),
+            result => result,
+        }
+    }
+
+    /// Asks for the variables the template declares in its `getflake.toml`, which can be gone
+    /// back through one by one too
+    fn variables(&mut self) -> Result<bool> {
+        let template = self.template.clone().expect(ANSWERED);
+        // Extra placeholders from the config file are replaced too, unless they're given
+        let extra_vars = self
+            .config
+            .defaults(Some(template.as_str()))
+            .extra_vars
+            .unwrap_or_default();
+        if self.manifest.as_ref().map(|(name, _)| name) != Some(&template) {
+            let source = self.source.as_ref().expect(ANSWERED);
+            let manifest = match progress::spin("Reading the template's variables", || {
+                source.template_manifest(&template)
+            }) {
+                Ok(manifest) => manifest,
+                Err(e @ GetflakeError::InvalidManifest { .. }) => return Err(e),
+                Err(e) => {
+                    eprintln!("{WARNING} Unable to read the variables of the template\n{e}");
+                    Manifest::default()
+                }
+            };
+            self.manifest = Some((template, manifest));
+        }
+        let Some((_, manifest)) = &self.manifest else {
+            return Ok(false);
+        };
+
+        self.variables = extra_vars.clone();
+        self.variables.extend(self.given.variables.clone());
+        let mut asked: Vec<usize> = Vec::new();
+        let mut index = 0;
+        while let Some(variable) = manifest.variables.get(index) {
+            if let Some(value) = self.given.variables.get(&variable.name) {
+                variable.validate(value)?;
+                index += 1;
+                continue;
+            }
+            let default = self.default_variable(variable, &extra_vars);
+            if let Some(default) = default.clone().filter(|_| self.takes_defaults()) {
+                self.variables.insert(variable.name.clone(), default);
+                index += 1;
+                continue;
+            }
+            match retry(self.max_attempts, || {
+                prompt::variable(variable, default.as_deref())
+            }) {
+                Ok(value) => {
+                    self.variables.insert(variable.name.clone(), value);
+                    asked.push(index);
+                    index += 1;
+                }
+                Err(GetflakeError::Back) => match asked.pop() {
+                    Some(previous) => index = previous,
+                    None => return Err(GetflakeError::Back),
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(!asked.is_empty())
+    }
+}
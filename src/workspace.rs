@@ -0,0 +1,94 @@
+//! Workspaces: several projects scaffolded side by side in one folder, with a flake at its root
+//! that wires them together
+
+use std::{fs, path::Path, str::FromStr};
+
+use crate::error::{GetflakeError, Result};
+
+/// A project of a workspace, given as `name:template` (e.g. `api:rust`)
+#[derive(Clone, Debug)]
+pub struct Member {
+    /// Name of the project, and of its folder in the workspace
+    pub name: String,
+    pub template: String,
+}
+
+impl FromStr for Member {
+    type Err = GetflakeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some((name, template)) if !name.is_empty() && !template.is_empty() => Ok(Self {
+                name: name.to_owned(),
+                template: template.to_owned(),
+            }),
+            _ => Err(GetflakeError::InvalidInput(format!(
+                "expected the project as name:template (e.g. api:rust), not {s}"
+            ))),
+        }
+    }
+}
+
+/// Returns the flake at the root of the workspace
+///
+/// Each member is one of its inputs, and it has their default packages and dev shells under
+/// their names, along with a default dev shell with every member's tools.
+pub fn flake(name: &str, members: &[Member]) -> String {
+    let names: Vec<&str> = members.iter().map(|member| member.name.as_str()).collect();
+    let inputs: String = names
+        .iter()
+        .map(|name| format!("    {name}.url = \"path:./{name}\";\n"))
+        .collect();
+    format!(
+        r#"{{
+  description = "{name}, a workspace of {0}";
+
+  inputs = {{
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+{inputs}  }};
+
+  outputs =
+    {{ nixpkgs, ... }}@inputs:
+    let
+      members = {{
+        inherit (inputs) {1};
+      }};
+      # The systems any member has a package or dev shell for
+      systems = nixpkgs.lib.unique (
+        nixpkgs.lib.concatMap (
+          member:
+          builtins.attrNames (member.packages or {{ }}) ++ builtins.attrNames (member.devShells or {{ }})
+        ) (builtins.attrValues members)
+      );
+      # The default package or dev shell of each member that has one for the system
+      defaults =
+        output: system:
+        nixpkgs.lib.filterAttrs (name: value: value != null) (
+          builtins.mapAttrs (name: member: member.${{output}}.${{system}}.default or null) members
+        );
+    in
+    {{
+      packages = nixpkgs.lib.genAttrs systems (system: defaults "packages" system);
+      devShells = nixpkgs.lib.genAttrs systems (
+        system:
+        defaults "devShells" system
+        // {{
+          # Every member's tools at once
+          default = nixpkgs.legacyPackages.${{system}}.mkShell {{
+            inputsFrom = builtins.attrValues (defaults "devShells" system);
+          }};
+        }}
+      );
+    }};
+}}
+"#,
+        names.join(", "),
+        names.join(" "),
+    )
+}
+
+/// Writes the flake at the root of the workspace in `directory`
+pub fn write_flake(directory: &Path, name: &str, members: &[Member]) -> Result<()> {
+    fs::write(directory.join("flake.nix"), flake(name, members))?;
+    Ok(())
+}